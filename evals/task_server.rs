@@ -0,0 +1,58 @@
+use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Task {
+    id: u64,
+    name: String,
+    completed: bool,
+}
+
+struct AppState {
+    tasks: Mutex<Vec<Task>>,
+}
+
+async fn list_tasks(data: web::Data<AppState>) -> impl Responder {
+    let tasks = data.tasks.lock().unwrap();
+    HttpResponse::Ok().json(&*tasks)
+}
+
+async fn create_task(task: web::Json<Task>, data: web::Data<AppState>) -> impl Responder {
+    let mut tasks = data.tasks.lock().unwrap();
+    tasks.push(task.into_inner());
+    HttpResponse::Ok().finish()
+}
+
+async fn read_task(id: web::Path<u64>, data: web::Data<AppState>) -> impl Responder {
+    let tasks = data.tasks.lock().unwrap();
+    match tasks.iter().find(|task| task.id == *id) {
+        Some(task) => HttpResponse::Ok().json(task),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+async fn delete_task(id: web::Path<u64>, data: web::Data<AppState>) -> impl Responder {
+    let mut tasks = data.tasks.lock().unwrap();
+    tasks.retain(|task| task.id != *id);
+    HttpResponse::Ok().finish()
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let data = web::Data::new(AppState {
+        tasks: Mutex::new(vec![]),
+    });
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(data.clone())
+            .route("/task", web::get().to(list_tasks))
+            .route("/task", web::post().to(create_task))
+            .route("/task/{id}", web::get().to(read_task))
+            .route("/task/{id}", web::delete().to(delete_task))
+    })
+    .bind("127.0.0.1:8080")?
+    .run()
+    .await
+}
@@ -42,4 +42,22 @@ pub fn print_site_urls(_project_description: &str) {
     ///   prints:
     /// ["https://api.binance.com/api/v3/exchangeInfo", "https://api.binance.com/api/v3/klines?symbol=BTCUSDT&interval=1d"]
     println!(OUTPUT)
-}
\ No newline at end of file
+}
+
+#[ai_function]
+pub fn print_replacement_site_urls(_project_description_and_failed_urls: &str) {
+    /// Input: Takes in a PROJECT_DESCRIPTION, the WORKING_URLS found so far and the REJECTED_URLS with the reason each one failed
+    /// Function: Outputs a list of alternative external public API endpoints that provide the data the website needs
+    /// Important: Never repeats a url from REJECTED_URLS or WORKING_URLS
+    /// Important: Only selects url endpoint(s) which do not require any API Keys, logins or sign ups at all
+    /// Important: Avoids urls from the same provider as a rejected url if the reason was an API key or login requirement
+    /// Output: Prints a list response of external urls in the following format:
+    /// ["url1", "url2", "url3", ...]
+    /// Example:
+    ///   PROJECT_DESCRIPTION = "build a website that shows the current time in different timezones"
+    ///   WORKING_URLS = []
+    ///   REJECTED_URLS = ["https://api.timezonedb.com/v2.1/list-time-zone (status 400: response asks for an API key)"]
+    ///   prints:
+    /// ["https://worldtimeapi.org/api/timezone", "https://timeapi.io/api/TimeZone/AvailableTimeZones"]
+    println!(OUTPUT)
+}
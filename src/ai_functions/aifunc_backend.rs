@@ -2,47 +2,59 @@ use ai_functions::ai_function;
 
 #[ai_function]
 pub fn print_backend_webserver_code(_project_description_and_template: &str) {
-    /// INPUT: Takes in a PROJECT_DESCRIPTION and CODE_TEMPLATE for a website backend build
+    /// INPUT: Takes in the FRAMEWORK, STORAGE, ALLOWED_CRATES, ROUTE_SYNTAX and PORT, a PROJECT_DESCRIPTION and CODE_TEMPLATE for a website backend build
+    /// IMPORTANT: When AUTH and an AUTH_MODULE are given, users log in through that module exactly as AUTH describes. Never store or compare plaintext passwords.
     /// IMPORTANT: The backend code is ONLY an example. If the Project Description requires it, make as many changes as you like.
     /// IMPORTANT: You do not need to follow the backend code exactly. Write functions that make sense for the users request if required.
     /// FUNCTION: Takes an existing set of code marked as CODE_TEMPLATE and updates or re-writes it to work for the purpose in the PROJECT_DESCRIPTION
-    /// IMPORTANT: The following libraries are already installed
-    ///   reqwest, serde, serde_json, tokio, actix-web, async-trait, actix_cors
+    /// IMPORTANT: Only the libraries in ALLOWED_CRATES are installed. Use the FRAMEWORK and STORAGE of the CODE_TEMPLATE, write routes in its ROUTE_SYNTAX and keep reading the port as the template does.
     /// No other external libraries should be used. Write functions that fit with the description from the PROJECT_DESCRIPTION
+    /// IMPORTANT: When a MIGRATION is given, structs and queries match its tables exactly. The server applies the migrations at startup as the CODE_TEMPLATE does.
+    /// IMPORTANT: EXTERNAL_API_SAMPLES lists each external url with the JSON shape it really returns. Write serde structs that match these shapes exactly.
     /// OUTPUT: Print ONLY the code, nothing else. This function ONLY prints code.
     println!(OUTPUT)
 }
 
 #[ai_function]
 pub fn print_improved_webserver_code(_project_description_and_template: &str) {
-    /// INPUT: Takes in a PROJECT_DESCRIPTION and CODE_TEMPLATE for a website backend build
+    /// INPUT: Takes in the FRAMEWORK, STORAGE, ALLOWED_CRATES, ROUTE_SYNTAX and PORT, a PROJECT_DESCRIPTION and CODE_TEMPLATE for a website backend build
     /// FUNCTION: Performs the following tasks:
     ///   1. Removes any bugs in the code and adds minor additional functionality
     ///   2. Makes sure everything requested in the spec from a backend standpoint was followed. If not, add the feature. No code should be implemented later. Everything should be written now.
     ///   3. ONLY writes the code. No commentary.
-    /// IMPORTANT: The following libraries are already installed. Does not use ANY libraries other than what was provided in the template
-    ///   reqwest, serde, serde_json, tokio, actix-web, async-trait
+    /// IMPORTANT: Only the libraries in ALLOWED_CRATES are installed. Does not use ANY other libraries and stays on the FRAMEWORK given
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_amended_webserver_code(_code_and_change_request: &str) {
+    /// INPUT: Takes in the FRAMEWORK, STORAGE, ALLOWED_CRATES, ROUTE_SYNTAX and PORT, the PROJECT_DESCRIPTION, working backend CODE_INPUT and a CHANGE to make to it
+    /// FUNCTION: Applies the CHANGE to CODE_INPUT and nothing else. Every existing route keeps its path, method and behaviour unless the CHANGE says otherwise
+    /// IMPORTANT: Only the libraries in ALLOWED_CRATES are installed. Stays on the FRAMEWORK and STORAGE of CODE_INPUT
+    /// IMPORTANT: When a MIGRATION is given, structs and queries match its tables exactly, including the columns its last statements add
+    /// IMPORTANT: Only prints out the complete changed code. No commentary or anything else
     println!(OUTPUT)
 }
 
 #[ai_function]
 pub fn print_fixed_code(_broken_code_with_bugs: &str) {
-    /// INPUT: Takes in Rust BROKEN_CODE and the ERROR_BUGS found
-    /// FUNCTION: Removes bugs from code
+    /// INPUT: Takes in Rust BROKEN_CODE and the ERROR_BUGS found, with the FRAMEWORK and ALLOWED_CRATES it was written for
+    /// FUNCTION: Removes bugs from code without switching framework or adding libraries outside ALLOWED_CRATES
     /// IMPORTANT: Only prints out the new and improved code. No commentary or anything else
     println!(OUTPUT)
 }
 
 #[ai_function]
 pub fn print_rest_api_endpoints(_code_input: &str) {
-    /// INPUT: Takes in Rust webserver CODE_INPUT based on actix-web
+    /// INPUT: Takes in Rust webserver CODE_INPUT written with the FRAMEWORK given, whose routes use the ROUTE_SYNTAX shown
     /// FUNCTION: Prints out the JSON schema for url endpoints and their respective types
     /// LOGIC: Script analyses all code and can categorize into the following object keys:
-    ///   "route": This represents the url path of the endpoint
-    ///   "is_route_dynamic": if a route has curly braces in it such as {symbol} or {id} as an example, then this will be set to true
+    ///   "route": This represents the url path of the endpoint. Path params are always printed in curly braces, so axum's /item/:id is printed as /item/{id}
+    ///   "is_route_dynamic": if a route has path params such as {symbol} or {id} as an example, then this will be set to true
     ///   "method": This represents the method being called
     ///   "request_body": This represents the body of a post method request
     ///   "response": This represents the output based upon the structs in the code and understanding the functions
+    ///   "requires_auth": true if the route sits behind a session or token middleware such as auth::require_session, otherwise false
     /// IMPORTANT: Only prints out the JSON schema. No commentary or anything else.
     /// MUST READ: All keys are strings. Even bool should be wrapped in double quotes as "bool"
     /// EXAMPLE:
@@ -109,4 +121,33 @@ pub fn print_rest_api_endpoints(_code_input: &str) {
     ///   ... // etc
     /// ]
     println!(OUTPUT)
-}
\ No newline at end of file
+}
+
+#[ai_function]
+pub fn print_sql_migration(_data_model: &str) {
+    /// INPUT: Takes in the DATA_MODEL of a website backend, the entities and fields it stores, and its PROJECT_DESCRIPTION
+    /// FUNCTION: Prints the SQLite migration that creates a table for every entity in the DATA_MODEL
+    /// IMPORTANT: Only uses CREATE TABLE IF NOT EXISTS and CREATE INDEX IF NOT EXISTS statements. Every table has an INTEGER PRIMARY KEY named id. Bools are stored as INTEGER.
+    /// IMPORTANT: Usernames are UNIQUE. A foreign key references the id of the table it points to.
+    /// OUTPUT: Print ONLY the SQL, nothing else. No commentary and no markdown.
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_amended_migration(_migration_and_change: &str) {
+    /// INPUT: Takes in the SQLite MIGRATION a website backend already applies and a CHANGE to make to the backend
+    /// FUNCTION: Prints the migration that runs after MIGRATION and gives the database what the CHANGE needs
+    /// IMPORTANT: Only adds to the schema, never drops or renames. Uses CREATE TABLE IF NOT EXISTS, CREATE INDEX IF NOT EXISTS and ALTER TABLE ADD COLUMN. New NOT NULL columns have a DEFAULT.
+    /// IMPORTANT: When the CHANGE does not touch the stored data, prints only NONE.
+    /// OUTPUT: Print ONLY the SQL or NONE, nothing else. No commentary and no markdown.
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_seed_data(_migration: &str) {
+    /// INPUT: Takes in the SQLite MIGRATION of a website backend and its PROJECT_DESCRIPTION
+    /// FUNCTION: Prints INSERT OR IGNORE statements that fill every table of the MIGRATION with 2 or 3 realistic example rows
+    /// IMPORTANT: Rows use explicit ids starting at 1. Foreign keys point at seeded rows. Passwords are always "changeme".
+    /// OUTPUT: Print ONLY the SQL, nothing else. No commentary and no markdown.
+    println!(OUTPUT)
+}
@@ -12,4 +12,66 @@ pub fn convert_user_input_to_goal(_user_request: &str) {
     ///   user_request = "Create something that stores crypto price data in a database using supabase and retrieves prices on the frontend."
     ///   OUTPUT = "build a website that fetches and stores crypto price data within a supabase setup including a frontend UI to fetch the data."
     println!(OUTPUT)
-}
\ No newline at end of file
+}
+
+#[ai_function]
+pub fn print_clarifying_questions(_user_request: &str) {
+    /// Input: Takes in a user request for a website
+    /// Function: Finds what is ambiguous or missing in the request that would change how the website backend is built
+    /// Important: Asks at most 5 short questions a non-technical user can answer. Asks nothing if the request is already clear
+    /// Output: Prints a list of questions in the following format:
+    /// ["question1", "question2", ...]
+    /// Example:
+    ///   user_request = "I need a website to track my workouts"
+    ///   prints:
+    ///   ["Should several users each have their own workouts, or is it just for you?", "What should be recorded for each workout, e.g. exercise, sets, reps, duration?", "Do you need progress statistics over time?"]
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_requirements(_user_request_and_clarifications: &str) {
+    /// Input: Takes in a user request for a website and the user's answers to clarifying questions
+    /// Function: Writes structured requirements for the website backend. Where an answer is missing, makes a sensible assumption and lists it
+    /// Output: Prints an object response in the following format:
+    ///   {
+    ///     "summary": string, // one sentence description of the website
+    ///     "features": [string], // what users can do
+    ///     "data_entities": [string], // records the backend stores, with their main fields
+    ///     "constraints": [string], // limits and rules the user asked for
+    ///     "assumptions": [string] // decisions made where the user did not say
+    ///   }
+    /// Example:
+    ///   user_request_and_clarifications = "USER_REQUEST: I need a website to track my workouts CLARIFICATIONS: [{"question": "Should several users each have their own workouts?", "answer": "yes"}]"
+    ///   prints:
+    ///   {
+    ///     "summary": "A workout tracker where each registered user logs their own workouts",
+    ///     "features": ["register, log in and log out", "add, edit and delete workouts", "list past workouts"],
+    ///     "data_entities": ["user: id, username, password", "workout: id, user_id, date, exercise, sets, reps"],
+    ///     "constraints": ["users only see their own workouts"],
+    ///     "assumptions": ["no progress statistics are needed"]
+    ///   }
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_change_plan(_factsheet_and_change_request: &str) {
+    /// Input: Takes in the FACTSHEET of a website backend that was already built and a CHANGE_REQUEST from the user
+    /// Function: Works out how the change request alters the project and which work has to be redone
+    /// Important: The CHANGE describes only what to modify in the existing backend code, so every route that already works is kept
+    /// Important: rerun_architect is true only when the change alters the project scope (CRUD, user login and logout, external urls) or needs new external urls
+    /// Output: Prints an object response in the following format:
+    ///   {
+    ///     "project_description": string, // the project description with the change folded in, starting with "build a website that ..."
+    ///     "change": string, // the targeted modification to make to the backend code
+    ///     "rerun_architect": bool
+    ///   }
+    /// Example:
+    ///   factsheet_and_change_request = "FACTSHEET: {"project_description": "build a website that stores tasks", ...} CHANGE_REQUEST: let tasks have a due date"
+    ///   prints:
+    ///   {
+    ///     "project_description": "build a website that stores tasks with a due date",
+    ///     "change": "add an optional due_date string field to the task struct, accept it when creating and updating tasks and return it when listing them",
+    ///     "rerun_architect": false
+    ///   }
+    println!(OUTPUT)
+}
@@ -1,3 +1,46 @@
 pub mod aifunc_architect;
 pub mod aifunc_backend;
-pub mod aifunc_managing;
\ No newline at end of file
+pub mod aifunc_managing;
+
+use aifunc_architect::{print_project_scope, print_replacement_site_urls, print_site_urls};
+use aifunc_backend::{
+    print_amended_migration, print_amended_webserver_code, print_backend_webserver_code,
+    print_fixed_code, print_improved_webserver_code, print_rest_api_endpoints, print_seed_data,
+    print_sql_migration,
+};
+use aifunc_managing::{
+    convert_user_input_to_goal, print_change_plan, print_clarifying_questions, print_requirements,
+};
+
+// Returns the function's definition, which the prompt asks the LLM to evaluate
+pub type AIFunction = fn(&str) -> &'static str;
+
+// Every ai_function by name, for prompt overrides and eval scenarios
+pub const AI_FUNCTIONS: [(&str, AIFunction); 15] = [
+    ("print_clarifying_questions", print_clarifying_questions),
+    ("print_requirements", print_requirements),
+    ("convert_user_input_to_goal", convert_user_input_to_goal),
+    ("print_change_plan", print_change_plan),
+    ("print_project_scope", print_project_scope),
+    ("print_site_urls", print_site_urls),
+    ("print_replacement_site_urls", print_replacement_site_urls),
+    ("print_backend_webserver_code", print_backend_webserver_code),
+    (
+        "print_improved_webserver_code",
+        print_improved_webserver_code,
+    ),
+    ("print_amended_webserver_code", print_amended_webserver_code),
+    ("print_fixed_code", print_fixed_code),
+    ("print_rest_api_endpoints", print_rest_api_endpoints),
+    ("print_sql_migration", print_sql_migration),
+    ("print_amended_migration", print_amended_migration),
+    ("print_seed_data", print_seed_data),
+];
+
+// Built-in definition of an ai_function by name
+pub fn ai_function_by_name(name: &str) -> Option<AIFunction> {
+    AI_FUNCTIONS
+        .iter()
+        .find(|(ai_name, _)| *ai_name == name)
+        .map(|(_, ai_func)| *ai_func)
+}
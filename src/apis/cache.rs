@@ -0,0 +1,309 @@
+use crate::models::general::llm::{APIUsage, CompletionParams, LLMResponse, Message};
+use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_CACHE_DIR: &str = ".llm_cache";
+const DEFAULT_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+// Content address of a request - identical parameters and messages share an entry
+pub fn cache_key(params: &CompletionParams, messages: &Vec<Message>) -> String {
+    let request: serde_json::Value = serde_json::json!({
+        "params": params,
+        "messages": messages,
+    });
+    let digest = Sha256::digest(request.to_string().as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheEntry {
+    pub created_at: u64,
+    pub ai_function: String,
+    pub model: String,
+    pub content: String,
+    pub usage: Option<APIUsage>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub expired: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+    // ai_functions that always go to the provider
+    pub bypass: Vec<String>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf, ttl: Duration, bypass: Vec<String>) -> Self {
+        Self { dir, ttl, bypass }
+    }
+
+    // LLM_CACHE=on enables the cache, LLM_CACHE_DIR, LLM_CACHE_TTL_SECS and
+    // LLM_CACHE_BYPASS (comma separated ai_function names) tune it
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let dir: PathBuf =
+            PathBuf::from(env::var("LLM_CACHE_DIR").unwrap_or(DEFAULT_CACHE_DIR.to_string()));
+        let ttl_secs: u64 = env::var("LLM_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        let bypass: Vec<String> = env::var("LLM_CACHE_BYPASS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+        Self::new(dir, Duration::from_secs(ttl_secs), bypass)
+    }
+
+    pub fn is_bypassed(&self, ai_function: &str) -> bool {
+        self.bypass.iter().any(|name| name == ai_function)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn is_expired(&self, entry: &CacheEntry) -> bool {
+        now_secs().saturating_sub(entry.created_at) >= self.ttl.as_secs()
+    }
+
+    pub fn get(&self, key: &str) -> Option<LLMResponse> {
+        let raw: String = fs::read_to_string(self.entry_path(key)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&raw).ok()?;
+        if self.is_expired(&entry) {
+            let _ = fs::remove_file(self.entry_path(key));
+            return None;
+        }
+        Some(LLMResponse {
+            content: entry.content,
+            model: entry.model,
+            usage: entry.usage,
+        })
+    }
+
+    pub fn put(&self, key: &str, ai_function: &str, llm_response: &LLMResponse) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry: CacheEntry = CacheEntry {
+            created_at: now_secs(),
+            ai_function: ai_function.to_string(),
+            model: llm_response.model.clone(),
+            content: llm_response.content.clone(),
+            usage: llm_response.usage,
+        };
+        let raw: String = serde_json::to_string_pretty(&entry)?;
+
+        // Write then rename so a crashed run never leaves half an entry behind
+        let tmp_path: PathBuf = self.dir.join(format!("{}.tmp", key));
+        fs::write(&tmp_path, raw)?;
+        fs::rename(tmp_path, self.entry_path(key))
+    }
+
+    // Every readable entry with its key, oldest first
+    pub fn entries(&self) -> Vec<(String, CacheEntry)> {
+        let mut entries: Vec<(String, CacheEntry)> = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir
+                .filter_map(|item| item.ok())
+                .filter_map(|item| {
+                    let path: PathBuf = item.path();
+                    let key: String = path
+                        .file_name()?
+                        .to_str()?
+                        .strip_suffix(".json")?
+                        .to_string();
+                    let entry: CacheEntry =
+                        serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+                    Some((key, entry))
+                })
+                .collect(),
+            Err(_) => vec![],
+        };
+        entries.sort_by_key(|(_, entry)| entry.created_at);
+        entries
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let mut stats: CacheStats = CacheStats::default();
+        for (key, entry) in self.entries() {
+            stats.entries += 1;
+            if self.is_expired(&entry) {
+                stats.expired += 1;
+            }
+            stats.bytes += fs::metadata(self.entry_path(&key)).map_or(0, |meta| meta.len());
+        }
+        stats
+    }
+
+    // Remove all entries, or only the expired ones. Returns how many were removed.
+    pub fn clear(&self, expired_only: bool) -> io::Result<usize> {
+        let mut removed: usize = 0;
+        for (key, entry) in self.entries() {
+            if !expired_only || self.is_expired(&entry) {
+                fs::remove_file(self.entry_path(&key))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+// None unless LLM_CACHE is switched on
+pub fn response_cache_from_env() -> Option<ResponseCache> {
+    dotenv().ok();
+
+    match env::var("LLM_CACHE")
+        .unwrap_or_default()
+        .to_lowercase()
+        .as_str()
+    {
+        "on" | "1" | "true" => Some(ResponseCache::from_env()),
+        _ => None,
+    }
+}
+
+// Handle `cache stats|list|clear [--expired]` from the command line
+pub fn cache_command(args: &[String]) -> Result<String, String> {
+    let cache: ResponseCache = ResponseCache::from_env();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("stats") => {
+            let stats: CacheStats = cache.stats();
+            Ok(format!(
+                "{}: {} entries ({} expired), {} bytes, ttl {}s",
+                cache.dir.display(),
+                stats.entries,
+                stats.expired,
+                stats.bytes,
+                cache.ttl.as_secs()
+            ))
+        }
+        Some("list") => {
+            let lines: Vec<String> = cache
+                .entries()
+                .iter()
+                .map(|(key, entry)| {
+                    format!(
+                        "{}  {:<36} {:<12} age {}s{}",
+                        &key[..12.min(key.len())],
+                        entry.ai_function,
+                        entry.model,
+                        now_secs().saturating_sub(entry.created_at),
+                        if cache.is_expired(entry) {
+                            " (expired)"
+                        } else {
+                            ""
+                        }
+                    )
+                })
+                .collect();
+            Ok(lines.join("\n"))
+        }
+        Some("clear") => {
+            let expired_only: bool = args.iter().any(|arg| arg == "--expired");
+            cache
+                .clear(expired_only)
+                .map(|removed| format!("Removed {} cache entries", removed))
+                .map_err(|e| e.to_string())
+        }
+        _ => Err("Usage: cache stats | cache list | cache clear [--expired]".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_messages() -> Vec<Message> {
+        vec![Message {
+            role: "user".to_string(),
+            content: "Build me a todo app".to_string(),
+        }]
+    }
+
+    fn test_cache(name: &str, ttl: Duration) -> ResponseCache {
+        let dir: PathBuf = env::temp_dir().join(format!("llm_cache_{}_{}", name, now_secs()));
+        let _ = fs::remove_dir_all(&dir);
+        ResponseCache::new(dir, ttl, vec!["print_fixed_code".to_string()])
+    }
+
+    #[test]
+    fn tests_cache_key() {
+        let params: CompletionParams = CompletionParams::default();
+        let key: String = cache_key(&params, &test_messages());
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key(&params, &test_messages()));
+
+        let warmer: CompletionParams = CompletionParams {
+            temperature: 0.7,
+            ..params.clone()
+        };
+        assert_ne!(key, cache_key(&warmer, &test_messages()));
+
+        let cheaper: CompletionParams = CompletionParams {
+            model: "gpt-4o".to_string(),
+            ..params
+        };
+        assert_ne!(key, cache_key(&cheaper, &test_messages()));
+    }
+
+    #[test]
+    fn tests_cache_put_get_clear() {
+        let cache: ResponseCache = test_cache("put_get", Duration::from_secs(60));
+        let key: String = cache_key(&CompletionParams::default(), &test_messages());
+        let llm_response: LLMResponse = LLMResponse {
+            content: "Build a todo app".to_string(),
+            model: "gpt-4-0613".to_string(),
+            usage: None,
+        };
+
+        assert!(cache.get(&key).is_none());
+        cache
+            .put(&key, "convert_user_input_to_goal", &llm_response)
+            .unwrap();
+        assert_eq!(cache.get(&key).unwrap().content, "Build a todo app");
+        assert_eq!(cache.stats().entries, 1);
+        assert!(cache.is_bypassed("print_fixed_code"));
+
+        assert_eq!(cache.clear(true).unwrap(), 0);
+        assert_eq!(cache.clear(false).unwrap(), 1);
+        assert!(cache.get(&key).is_none());
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn tests_cache_ttl_expiry() {
+        let cache: ResponseCache = test_cache("ttl", Duration::from_secs(0));
+        let key: String = cache_key(&CompletionParams::default(), &test_messages());
+        let llm_response: LLMResponse = LLMResponse {
+            content: "stale".to_string(),
+            model: "gpt-4".to_string(),
+            usage: None,
+        };
+
+        cache
+            .put(&key, "print_project_scope", &llm_response)
+            .unwrap();
+        assert_eq!(cache.stats().expired, 1);
+        assert!(cache.get(&key).is_none());
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+}
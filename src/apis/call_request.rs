@@ -1,21 +1,31 @@
-use crate::models::general::llm::{APIResponse, ChatCompletion, Message};
+use crate::apis::retry::{LLMError, RetryPolicy};
+use crate::helpers::command_line::PrintCommand;
+use crate::models::general::llm::{
+    APIResponse, APIStreamChunk, ChatCompletion, CompletionParams, LLMResponse, Message,
+    StreamOptions,
+};
 use dotenv::dotenv;
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::Client;
 use std::env;
+use std::time::Duration;
+use tokio::time;
 
-// Call Large Language Model (i.e. GPT-4)
-pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::error::Error + Send>> {
+const CHAT_COMPLETIONS_URL: &str = "https://api.openai.com/v1/chat/completions";
+
+// A missing key stops the call with an error instead of a panic
+fn config_var(name: &str) -> Result<String, LLMError> {
+    env::var(name)
+        .map_err(|_| LLMError::Config(format!("{} not found in environment variables", name)))
+}
+
+// Create client with Open AI auth headers
+fn openai_client() -> Result<Client, LLMError> {
     dotenv().ok();
 
     // Extract API Key information
-    let api_key: String =
-        env::var("OPEN_AI_KEY").expect("OPEN_AI_KEY not found in enviornment variables");
-    let api_org: String =
-        env::var("OPEN_AI_ORG").expect("OPEN_AI_ORG not found in enviornment variables");
-
-    // Confirm endpoint
-    let url: &str = "https://api.openai.com/v1/chat/completions";
+    let api_key: String = config_var("OPEN_AI_KEY")?;
+    let api_org: String = config_var("OPEN_AI_ORG")?;
 
     // Create headers
     let mut headers: HeaderMap = HeaderMap::new();
@@ -24,28 +34,93 @@ pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::err
     headers.insert(
         "authorization",
         HeaderValue::from_str(&format!("Bearer {}", api_key))
-            .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?,
+            .map_err(|e| LLMError::Config(e.to_string()))?,
     );
 
     // Create Open AI Org header
     headers.insert(
         "OpenAI-Organization",
-        HeaderValue::from_str(api_org.as_str())
-            .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?,
+        HeaderValue::from_str(api_org.as_str()).map_err(|e| LLMError::Config(e.to_string()))?,
     );
 
     // Create client
-    let client: Client = Client::builder()
+    Client::builder()
         .default_headers(headers)
+        .connect_timeout(Duration::from_secs(10))
         .build()
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
+        .map_err(|e| LLMError::Config(e.to_string()))
+}
 
-    // Create chat completion
-    let chat_completion: ChatCompletion = ChatCompletion {
-        model: "gpt-4".to_string(),
+// Decide whether to try again, waiting out the backoff if so
+async fn wait_for_retry(policy: &RetryPolicy, attempt: u32, error: &LLMError) -> bool {
+    match policy.next_delay(attempt, error) {
+        Some(delay) => {
+            PrintCommand::Issue.print_agent_message(
+                "OpenAI",
+                &format!(
+                    "{} - retrying in {:.1}s (attempt {}/{})",
+                    error,
+                    delay.as_secs_f32(),
+                    attempt + 1,
+                    policy.max_attempts
+                ),
+            );
+            time::sleep(delay).await;
+            true
+        }
+        None => false,
+    }
+}
+
+// Send one request and fail on any non-success status.
+// The timeout covers the whole request, body included.
+async fn send_completion(
+    client: &Client,
+    url: &str,
+    chat_completion: &ChatCompletion,
+    timeout: Option<Duration>,
+) -> Result<reqwest::Response, LLMError> {
+    let mut request: reqwest::RequestBuilder = client.post(url).json(chat_completion);
+    if let Some(timeout) = timeout {
+        request = request.timeout(timeout);
+    }
+    let response: reqwest::Response = request.send().await.map_err(LLMError::from_reqwest)?;
+
+    if !response.status().is_success() {
+        let status: u16 = response.status().as_u16();
+        let headers: HeaderMap = response.headers().clone();
+        let body: String = response.text().await.unwrap_or_default();
+        return Err(LLMError::from_status(status, &headers, body));
+    }
+    Ok(response)
+}
+
+// Call Large Language Model (i.e. GPT-4)
+pub async fn call_gpt(
+    messages: Vec<Message>,
+    params: &CompletionParams,
+) -> Result<LLMResponse, LLMError> {
+    let client: Client = openai_client()?;
+    call_gpt_with(
+        &client,
+        CHAT_COMPLETIONS_URL,
+        &RetryPolicy::from_env(),
+        params,
         messages,
-        temperature: 0.1,
-    };
+    )
+    .await
+}
+
+// Call Large Language Model at a given endpoint, retrying as the policy allows
+pub async fn call_gpt_with(
+    client: &Client,
+    url: &str,
+    policy: &RetryPolicy,
+    params: &CompletionParams,
+    messages: Vec<Message>,
+) -> Result<LLMResponse, LLMError> {
+    // Create chat completion
+    let chat_completion: ChatCompletion = ChatCompletion::new(params, messages);
 
     // // Troubleshooting
     // let res_raw = client
@@ -56,24 +131,205 @@ pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::err
     //   .unwrap();
     // dbg!(res_raw.text().await.unwrap());
 
-    // Extract API Response
-    let res: APIResponse = client
-        .post(url)
-        .json(&chat_completion)
-        .send()
+    let mut attempt: u32 = 1;
+    loop {
+        // Extract API Response
+        let res: Result<APIResponse, LLMError> = match send_completion(
+            client,
+            url,
+            &chat_completion,
+            Some(policy.request_timeout),
+        )
         .await
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?
-        .json()
+        {
+            Ok(response) => response.json().await.map_err(LLMError::from_reqwest),
+            Err(e) => Err(e),
+        };
+
+        match res {
+            // Send Response
+            Ok(res) if !res.choices.is_empty() => {
+                return Ok(LLMResponse {
+                    content: res.choices[0].message.content.clone(),
+                    model: res.model.unwrap_or(params.model.clone()),
+                    usage: res.usage,
+                })
+            }
+            Ok(_) => return Err(LLMError::Decode("Response had no choices".to_string())),
+            Err(e) => {
+                if !wait_for_retry(policy, attempt, &e).await {
+                    return Err(e);
+                }
+            }
+        }
+        attempt += 1;
+    }
+}
+
+// Call Large Language Model with a streamed (SSE) response.
+// Each token is handed to on_token as it arrives, the stream is cancelled
+// if nothing is received for idle_timeout.
+pub async fn call_gpt_stream<F: FnMut(&str) + Send>(
+    messages: Vec<Message>,
+    params: &CompletionParams,
+    idle_timeout: Duration,
+    on_token: F,
+) -> Result<LLMResponse, LLMError> {
+    let client: Client = openai_client()?;
+    call_gpt_stream_with(
+        &client,
+        CHAT_COMPLETIONS_URL,
+        &RetryPolicy::from_env(),
+        params,
+        messages,
+        idle_timeout,
+        on_token,
+    )
+    .await
+}
+
+// Streamed call at a given endpoint. A stream that fails before its first token is retried,
+// after that the tokens were already handed on so the error is returned
+pub async fn call_gpt_stream_with<F: FnMut(&str) + Send>(
+    client: &Client,
+    url: &str,
+    policy: &RetryPolicy,
+    params: &CompletionParams,
+    messages: Vec<Message>,
+    idle_timeout: Duration,
+    mut on_token: F,
+) -> Result<LLMResponse, LLMError> {
+    // Create streamed chat completion
+    let chat_completion: ChatCompletion = ChatCompletion {
+        stream: Some(true),
+        stream_options: Some(StreamOptions {
+            include_usage: true,
+        }),
+        ..ChatCompletion::new(params, messages)
+    };
+
+    let mut attempt: u32 = 1;
+    let mut received_token: bool = false;
+    loop {
+        // Headers must arrive within the idle timeout, the body may take longer
+        let sent: Result<reqwest::Response, LLMError> = time::timeout(
+            idle_timeout,
+            send_completion(client, url, &chat_completion, None),
+        )
         .await
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
+        .unwrap_or_else(|_| {
+            Err(LLMError::Timeout(format!(
+                "No response within {:.1}s",
+                idle_timeout.as_secs_f32()
+            )))
+        });
+        let res: Result<LLMResponse, LLMError> = match sent {
+            Ok(response) => {
+                let forward_token = |token: &str| {
+                    received_token = true;
+                    on_token(token);
+                };
+                read_sse_completion(response, idle_timeout, params.model.clone(), forward_token)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
 
-    // Send Response
-    Ok(res.choices[0].message.content.clone())
+        match res {
+            Ok(llm_response) => return Ok(llm_response),
+            Err(e) => {
+                if received_token || !wait_for_retry(policy, attempt, &e).await {
+                    return Err(e);
+                }
+            }
+        }
+        attempt += 1;
+    }
+}
+
+// Assemble the full completion from an SSE response body
+pub async fn read_sse_completion<F: FnMut(&str) + Send>(
+    mut response: reqwest::Response,
+    idle_timeout: Duration,
+    default_model: String,
+    mut on_token: F,
+) -> Result<LLMResponse, LLMError> {
+    let mut buffer: Vec<u8> = vec![];
+    let mut llm_response: LLMResponse = LLMResponse {
+        content: String::new(),
+        model: default_model,
+        usage: None,
+    };
+
+    loop {
+        // Cancel stalled streams
+        let chunk = time::timeout(idle_timeout, response.chunk())
+            .await
+            .map_err(|_| {
+                LLMError::Timeout(format!(
+                    "Stream idle for more than {:.1}s, cancelled",
+                    idle_timeout.as_secs_f32()
+                ))
+            })?
+            .map_err(LLMError::from_reqwest)?;
+
+        let is_finished: bool = match chunk {
+            Some(bytes) => {
+                buffer.extend(bytes.iter().filter(|byte| **byte != b'\r'));
+                false
+            }
+            // Flush any final event without a trailing blank line
+            None => {
+                buffer.extend_from_slice(b"\n\n");
+                true
+            }
+        };
+
+        for data in drain_sse_data(&mut buffer) {
+            if data == "[DONE]" {
+                return Ok(llm_response);
+            }
+
+            let stream_chunk: APIStreamChunk =
+                serde_json::from_str(&data).map_err(|e| LLMError::Decode(e.to_string()))?;
+            if let Some(model) = stream_chunk.model {
+                llm_response.model = model;
+            }
+            if stream_chunk.usage.is_some() {
+                llm_response.usage = stream_chunk.usage;
+            }
+            for choice in stream_chunk.choices {
+                if let Some(token) = choice.delta.content {
+                    on_token(&token);
+                    llm_response.content.push_str(&token);
+                }
+            }
+        }
+
+        if is_finished {
+            return Ok(llm_response);
+        }
+    }
+}
+
+// Pull complete "data:" payloads out of the buffer, leaving any partial event
+fn drain_sse_data(buffer: &mut Vec<u8>) -> Vec<String> {
+    let mut payloads: Vec<String> = vec![];
+    while let Some(pos) = buffer.windows(2).position(|pair| pair == b"\n\n") {
+        let event: Vec<u8> = buffer.drain(..pos + 2).collect();
+        for line in String::from_utf8_lossy(&event).lines() {
+            if let Some(data) = line.strip_prefix("data:") {
+                payloads.push(data.trim().to_string());
+            }
+        }
+    }
+    payloads
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::stub_server::{StubResponse, StubServer};
 
     #[tokio::test]
     async fn tests_call_to_openai() {
@@ -84,15 +340,196 @@ mod tests {
 
         let messages: Vec<Message> = vec![message];
 
-        let res: Result<String, Box<dyn std::error::Error + Send>> = call_gpt(messages).await;
+        let res: Result<LLMResponse, LLMError> =
+            call_gpt(messages, &CompletionParams::default()).await;
         match res {
-            Ok(res_str) => {
-                dbg!(res_str);
-                assert!(true);
+            Ok(res) => {
+                dbg!(&res.content);
+                assert!(res.usage.is_some());
             }
-            Err(_) => {
-                assert!(false);
+            Err(e) => {
+                panic!("{:?}", e);
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn tests_missing_config_var_is_an_error() {
+        assert_eq!(
+            config_var("AUTO_GIPPITY_UNSET_TEST_VAR"),
+            Err(LLMError::Config(
+                "AUTO_GIPPITY_UNSET_TEST_VAR not found in environment variables".to_string()
+            ))
+        );
+    }
+
+    fn test_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+            request_timeout: Duration::from_millis(300),
+            jitter: true,
+        }
+    }
+
+    fn test_messages() -> Vec<Message> {
+        vec![Message {
+            role: "user".to_string(),
+            content: "Hi".to_string(),
+        }]
+    }
+
+    #[tokio::test]
+    async fn tests_call_gpt_retries_transient_errors() {
+        let completion: &str =
+            "{\"model\":\"gpt-4-0613\",\"choices\":[{\"message\":{\"content\":\"Hello\"}}]}";
+        let server: StubServer = StubServer::start(vec![(
+            "/v1/chat/completions",
+            vec![
+                StubResponse::json(429, "{\"error\":\"rate_limit_exceeded\"}")
+                    .header("Retry-After", "0"),
+                StubResponse::json(503, "{\"error\":\"overloaded\"}"),
+                StubResponse::json(200, completion),
+            ],
+        )])
+        .await;
+
+        let res: LLMResponse = call_gpt_with(
+            &Client::new(),
+            &server.url("/v1/chat/completions"),
+            &test_policy(),
+            &CompletionParams::default(),
+            test_messages(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(res.content, "Hello");
+        assert_eq!(server.hit_count("/v1/chat/completions"), 3);
+    }
+
+    #[tokio::test]
+    async fn tests_call_gpt_fails_fast_on_client_error() {
+        let server: StubServer = StubServer::start(vec![(
+            "/v1/chat/completions",
+            vec![StubResponse::json(401, "{\"error\":\"invalid_api_key\"}")],
+        )])
+        .await;
+
+        let res: Result<LLMResponse, LLMError> = call_gpt_with(
+            &Client::new(),
+            &server.url("/v1/chat/completions"),
+            &test_policy(),
+            &CompletionParams::default(),
+            test_messages(),
+        )
+        .await;
+
+        assert!(matches!(res, Err(LLMError::Client { status: 401, .. })));
+        assert_eq!(server.hit_count("/v1/chat/completions"), 1);
+    }
+
+    #[tokio::test]
+    async fn tests_call_gpt_request_timeout() {
+        let server: StubServer = StubServer::start(vec![(
+            "/v1/chat/completions",
+            vec![StubResponse::json(200, "{}").delay(Duration::from_secs(2))],
+        )])
+        .await;
+
+        let res: Result<LLMResponse, LLMError> = call_gpt_with(
+            &Client::new(),
+            &server.url("/v1/chat/completions"),
+            &test_policy(),
+            &CompletionParams::default(),
+            test_messages(),
+        )
+        .await;
+
+        assert!(matches!(res, Err(LLMError::Timeout(_))));
+        assert_eq!(server.hit_count("/v1/chat/completions"), 3);
+    }
+
+    #[tokio::test]
+    async fn tests_read_sse_completion() {
+        let sse_body: &str = "data: {\"model\":\"gpt-4-0613\",\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\r\n\r\n\
+data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n\
+data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n\
+data: {\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":2,\"total_tokens\":11}}\n\n\
+data: [DONE]\n\n";
+        let server: StubServer = StubServer::start(vec![(
+            "/stream",
+            vec![StubResponse::new(200, sse_body).header("Content-Type", "text/event-stream")],
+        )])
+        .await;
+
+        let response: reqwest::Response = reqwest::get(server.url("/stream")).await.unwrap();
+        let mut tokens: Vec<String> = vec![];
+        let res: LLMResponse = read_sse_completion(
+            response,
+            Duration::from_secs(5),
+            "gpt-4".to_string(),
+            |token: &str| tokens.push(token.to_string()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(tokens, vec!["Hello".to_string(), " world".to_string()]);
+        assert_eq!(res.content, "Hello world");
+        assert_eq!(res.model, "gpt-4-0613");
+        assert_eq!(res.usage.unwrap().completion_tokens, 2);
+    }
+
+    #[tokio::test]
+    async fn tests_stream_not_retried_after_first_token() {
+        let sse_body: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n\
+data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n\
+data: [DONE]\n\n";
+        let first_event_len: usize = sse_body.find("\n\n").unwrap() + 2;
+        let server: StubServer = StubServer::start(vec![(
+            "/v1/chat/completions",
+            vec![
+                StubResponse::new(200, sse_body).cut_off(first_event_len),
+                StubResponse::new(200, sse_body),
+            ],
+        )])
+        .await;
+
+        let mut tokens: Vec<String> = vec![];
+        let res: Result<LLMResponse, LLMError> = call_gpt_stream_with(
+            &Client::new(),
+            &server.url("/v1/chat/completions"),
+            &test_policy(),
+            &CompletionParams::default(),
+            test_messages(),
+            Duration::from_secs(5),
+            |token: &str| tokens.push(token.to_string()),
+        )
+        .await;
+
+        assert!(matches!(res, Err(LLMError::Connection(_))));
+        assert_eq!(tokens, vec!["Hello".to_string()]);
+        assert_eq!(server.hit_count("/v1/chat/completions"), 1);
+    }
+
+    #[tokio::test]
+    async fn tests_read_sse_completion_idle_timeout() {
+        let server: StubServer = StubServer::start(vec![(
+            "/stalled",
+            vec![StubResponse::new(200, "data: [DONE]\n\n").body_delay(Duration::from_secs(3))],
+        )])
+        .await;
+
+        let response: reqwest::Response = reqwest::get(server.url("/stalled")).await.unwrap();
+        let res: Result<LLMResponse, LLMError> = read_sse_completion(
+            response,
+            Duration::from_millis(200),
+            "gpt-4".to_string(),
+            |_: &str| {},
+        )
+        .await;
+
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::models::general::llm::CompletionParams;
+use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_CONFIG_PATH: &str = "llm_config.json";
+
+// Any subset of parameters - unset fields fall through to the next level
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ParamOverrides {
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub top_p: Option<f32>,
+    pub stop: Option<Vec<String>>,
+    pub seed: Option<u64>,
+}
+
+impl ParamOverrides {
+    fn apply(&self, params: &mut CompletionParams) {
+        if let Some(model) = &self.model {
+            params.model = model.clone();
+        }
+        if let Some(temperature) = self.temperature {
+            params.temperature = temperature;
+        }
+        if self.max_tokens.is_some() {
+            params.max_tokens = self.max_tokens;
+        }
+        if self.top_p.is_some() {
+            params.top_p = self.top_p;
+        }
+        if self.stop.is_some() {
+            params.stop = self.stop.clone();
+        }
+        if self.seed.is_some() {
+            params.seed = self.seed;
+        }
+    }
+}
+
+// Model parameters per agent position and per ai_function, e.g.
+// {
+//   "defaults": { "model": "gpt-4" },
+//   "agents": { "Project Manager": { "model": "gpt-3.5-turbo" } },
+//   "functions": { "print_improved_webserver_code": { "temperature": 0.4, "seed": 7 } }
+// }
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LLMConfig {
+    #[serde(default)]
+    pub defaults: ParamOverrides,
+    #[serde(default)]
+    pub agents: BTreeMap<String, ParamOverrides>,
+    #[serde(default)]
+    pub functions: BTreeMap<String, ParamOverrides>,
+}
+
+impl LLMConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw: String = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        serde_json::from_str(&raw).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+
+    // LLM_CONFIG_PATH, defaults to llm_config.json. A missing file means built-in defaults.
+    pub fn from_env() -> Result<Self, String> {
+        dotenv().ok();
+
+        let path: String = env::var("LLM_CONFIG_PATH").unwrap_or(DEFAULT_CONFIG_PATH.to_string());
+        if Path::new(&path).exists() {
+            Self::load(Path::new(&path))
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    // Built-in defaults, then config defaults, then the agent, then the ai_function
+    pub fn resolve(&self, agent_position: &str, ai_function: &str) -> CompletionParams {
+        let mut params: CompletionParams = CompletionParams::default();
+        self.defaults.apply(&mut params);
+        if let Some(overrides) = self.agents.get(agent_position) {
+            overrides.apply(&mut params);
+        }
+        if let Some(overrides) = self.functions.get(ai_function) {
+            overrides.apply(&mut params);
+        }
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_resolve_precedence() {
+        let config: LLMConfig = serde_json::from_str(
+            r#"{
+                "defaults": { "max_tokens": 2000 },
+                "agents": {
+                    "Project Manager": { "model": "gpt-3.5-turbo" },
+                    "Backend Developer": { "model": "gpt-4o", "temperature": 0.2 }
+                },
+                "functions": {
+                    "print_improved_webserver_code": { "temperature": 0.6, "seed": 7, "stop": ["```"] }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let manager: CompletionParams =
+            config.resolve("Project Manager", "convert_user_input_to_goal");
+        assert_eq!(manager.model, "gpt-3.5-turbo");
+        assert_eq!(manager.temperature, 0.1);
+        assert_eq!(manager.max_tokens, Some(2000));
+
+        let improve: CompletionParams =
+            config.resolve("Backend Developer", "print_improved_webserver_code");
+        assert_eq!(improve.model, "gpt-4o");
+        assert_eq!(improve.temperature, 0.6);
+        assert_eq!(improve.seed, Some(7));
+        assert_eq!(improve.stop, Some(vec!["```".to_string()]));
+
+        let unknown: CompletionParams = config.resolve("Solutions Architect", "print_site_urls");
+        assert_eq!(unknown.model, "gpt-4");
+    }
+
+    #[test]
+    fn tests_rejects_unknown_fields() {
+        let res: Result<LLMConfig, serde_json::Error> =
+            serde_json::from_str(r#"{ "defaults": { "temprature": 0.5 } }"#);
+        assert!(res.is_err());
+    }
+}
@@ -1 +1,7 @@
-pub mod call_request;
\ No newline at end of file
+pub mod cache;
+pub mod call_request;
+pub mod llm_config;
+pub mod provider;
+pub mod retry;
+pub mod run_log;
+pub mod usage;
@@ -0,0 +1,273 @@
+use crate::apis::cache::{cache_key, ResponseCache};
+use crate::apis::call_request::{call_gpt, call_gpt_stream};
+use crate::apis::retry::LLMError;
+use crate::models::general::llm::{CompletionParams, LLMResponse, Message};
+use async_trait::async_trait;
+use dotenv::dotenv;
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Anything that can answer a chat completion
+#[async_trait]
+pub trait LLMProvider: Send + Sync {
+    fn name(&self) -> String;
+
+    async fn complete(
+        &self,
+        ai_function: &str,
+        messages: Vec<Message>,
+        params: &CompletionParams,
+    ) -> Result<LLMResponse, LLMError>;
+
+    // Providers that cannot stream hand over the whole completion as one token
+    async fn complete_stream(
+        &self,
+        ai_function: &str,
+        messages: Vec<Message>,
+        params: &CompletionParams,
+        _idle_timeout: Duration,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<LLMResponse, LLMError> {
+        let llm_response: LLMResponse = self.complete(ai_function, messages, params).await?;
+        on_token(&llm_response.content);
+        Ok(llm_response)
+    }
+}
+
+#[async_trait]
+impl<P: LLMProvider + ?Sized> LLMProvider for Arc<P> {
+    fn name(&self) -> String {
+        self.as_ref().name()
+    }
+
+    async fn complete(
+        &self,
+        ai_function: &str,
+        messages: Vec<Message>,
+        params: &CompletionParams,
+    ) -> Result<LLMResponse, LLMError> {
+        self.as_ref().complete(ai_function, messages, params).await
+    }
+
+    async fn complete_stream(
+        &self,
+        ai_function: &str,
+        messages: Vec<Message>,
+        params: &CompletionParams,
+        idle_timeout: Duration,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<LLMResponse, LLMError> {
+        self.as_ref()
+            .complete_stream(ai_function, messages, params, idle_timeout, on_token)
+            .await
+    }
+}
+
+// LLM_PROVIDER=openai|mock, openai by default
+pub fn provider_from_env() -> Result<Arc<dyn LLMProvider>, String> {
+    dotenv().ok();
+
+    match env::var("LLM_PROVIDER")
+        .unwrap_or_default()
+        .to_lowercase()
+        .as_str()
+    {
+        "" | "openai" => Ok(Arc::new(OpenAIProvider)),
+        "mock" => Ok(Arc::new(MockProvider)),
+        other => Err(format!(
+            "Unknown LLM_PROVIDER {}, expected openai or mock",
+            other
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenAIProvider;
+
+#[async_trait]
+impl LLMProvider for OpenAIProvider {
+    fn name(&self) -> String {
+        "openai".to_string()
+    }
+
+    async fn complete(
+        &self,
+        _ai_function: &str,
+        messages: Vec<Message>,
+        params: &CompletionParams,
+    ) -> Result<LLMResponse, LLMError> {
+        call_gpt(messages, params).await
+    }
+
+    async fn complete_stream(
+        &self,
+        _ai_function: &str,
+        messages: Vec<Message>,
+        params: &CompletionParams,
+        idle_timeout: Duration,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<LLMResponse, LLMError> {
+        call_gpt_stream(messages, params, idle_timeout, on_token).await
+    }
+}
+
+// Replays recorded responses so evaluations run offline and deterministically.
+// With a recorder, misses are fetched from it and written as new cassettes.
+pub struct CassetteProvider {
+    cassettes: ResponseCache,
+    recorder: Option<Box<dyn LLMProvider>>,
+}
+
+impl CassetteProvider {
+    pub fn replay(dir: PathBuf) -> Self {
+        Self {
+            cassettes: ResponseCache::new(dir, Duration::MAX, vec![]),
+            recorder: None,
+        }
+    }
+
+    pub fn record(dir: PathBuf, recorder: Box<dyn LLMProvider>) -> Self {
+        Self {
+            cassettes: ResponseCache::new(dir, Duration::MAX, vec![]),
+            recorder: Some(recorder),
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CassetteProvider {
+    fn name(&self) -> String {
+        match &self.recorder {
+            Some(recorder) => format!("cassettes recording {}", recorder.name()),
+            None => format!("cassettes {}", self.cassettes.dir.display()),
+        }
+    }
+
+    async fn complete(
+        &self,
+        ai_function: &str,
+        messages: Vec<Message>,
+        params: &CompletionParams,
+    ) -> Result<LLMResponse, LLMError> {
+        let key: String = cache_key(params, &messages);
+        if let Some(recorded) = self.cassettes.get(&key) {
+            return Ok(recorded);
+        }
+
+        match &self.recorder {
+            Some(recorder) => {
+                let llm_response: LLMResponse =
+                    recorder.complete(ai_function, messages, params).await?;
+                self.cassettes
+                    .put(&key, ai_function, &llm_response)
+                    .map_err(|e| LLMError::Config(format!("Could not write cassette: {}", e)))?;
+                Ok(llm_response)
+            }
+            None => Err(LLMError::NotRecorded(format!(
+                "no cassette for {} ({})",
+                ai_function, key
+            ))),
+        }
+    }
+}
+
+// Backend endpoints of the code template, as print_rest_api_endpoints would list them
+const MOCK_API_ENDPOINTS: &str = r#"[
+  { "is_route_dynamic": false, "method": "post", "request_body": { "id": "number", "name": "string", "complete": "bool" }, "response": null, "route": "/task" },
+  { "is_route_dynamic": false, "method": "get", "request_body": null, "response": [{ "id": "number", "name": "string", "complete": "bool" }], "route": "/task" },
+  { "is_route_dynamic": false, "method": "put", "request_body": { "id": "number", "name": "string", "complete": "bool" }, "response": null, "route": "/task" },
+  { "is_route_dynamic": true, "method": "get", "request_body": null, "response": { "id": "number", "name": "string", "complete": "bool" }, "route": "/task/{id}" },
+  { "is_route_dynamic": true, "method": "delete", "request_body": null, "response": null, "route": "/task/{id}" },
+  { "is_route_dynamic": false, "method": "post", "request_body": { "id": "number", "username": "string", "password": "string" }, "response": null, "route": "/register" },
+  { "is_route_dynamic": false, "method": "post", "request_body": { "username": "string", "password": "string" }, "response": null, "route": "/login" }
+]"#;
+
+// Canned answers for every ai_function so a whole project runs offline:
+// a task API without external urls, written as the code template itself
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockProvider;
+
+#[async_trait]
+impl LLMProvider for MockProvider {
+    fn name(&self) -> String {
+        "mock".to_string()
+    }
+
+    async fn complete(
+        &self,
+        ai_function: &str,
+        _messages: Vec<Message>,
+        _params: &CompletionParams,
+    ) -> Result<LLMResponse, LLMError> {
+        let content: &str = match ai_function {
+            "print_clarifying_questions" => r#"["Should each user only see their own tasks?"]"#,
+            "print_requirements" => {
+                r#"{"summary": "A task list where registered users manage their own tasks", "features": ["register, log in and log out", "add, update and delete tasks"], "data_entities": ["user: id, username, password", "task: id, user_id, title, done"], "constraints": ["users only see their own tasks"], "assumptions": []}"#
+            }
+            "convert_user_input_to_goal" => {
+                "build a website backend that stores tasks and lets users register and log in"
+            }
+            "print_project_scope" => {
+                r#"{"is_crud_required": true, "is_user_login_and_logout": true, "is_external_urls_required": false}"#
+            }
+            "print_change_plan" => {
+                r#"{"project_description": "build a website backend that stores tasks with a due date and lets users register and log in", "change": "add an optional due_date field to tasks", "rerun_architect": false}"#
+            }
+            "print_site_urls" | "print_replacement_site_urls" => "[]",
+            "print_backend_webserver_code"
+            | "print_improved_webserver_code"
+            | "print_amended_webserver_code"
+            | "print_fixed_code" => include_str!("../code_template.rs"),
+            "print_rest_api_endpoints" => MOCK_API_ENDPOINTS,
+            "print_sql_migration" => {
+                "CREATE TABLE IF NOT EXISTS tasks (id INTEGER PRIMARY KEY, name TEXT NOT NULL, complete INTEGER NOT NULL);\nCREATE TABLE IF NOT EXISTS users (id INTEGER PRIMARY KEY, username TEXT NOT NULL UNIQUE, password TEXT NOT NULL);"
+            }
+            "print_amended_migration" => "ALTER TABLE tasks ADD COLUMN due_date TEXT;",
+            "print_seed_data" => {
+                "INSERT OR IGNORE INTO tasks (id, name, complete) VALUES (1, 'Water the plants', 0);\nINSERT OR IGNORE INTO users (id, username, password) VALUES (1, 'demo', 'changeme');"
+            }
+            _ => {
+                return Err(LLMError::Config(format!(
+                    "mock has no response for {}",
+                    ai_function
+                )))
+            }
+        };
+
+        Ok(LLMResponse {
+            content: content.to_string(),
+            model: "mock".to_string(),
+            usage: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::agents::agent_traits::{ProjectScope, RouteObject};
+
+    #[tokio::test]
+    async fn tests_mock_provider_answers_decode() {
+        let params: CompletionParams = CompletionParams::default();
+        let scope: LLMResponse = MockProvider
+            .complete("print_project_scope", vec![], &params)
+            .await
+            .unwrap();
+        let scope: ProjectScope = serde_json::from_str(&scope.content).unwrap();
+        assert!(!scope.is_external_urls_required);
+
+        let endpoints: LLMResponse = MockProvider
+            .complete("print_rest_api_endpoints", vec![], &params)
+            .await
+            .unwrap();
+        let endpoints: Vec<RouteObject> = serde_json::from_str(&endpoints.content).unwrap();
+        assert_eq!(endpoints.len(), 7);
+
+        let unknown: Result<LLMResponse, LLMError> =
+            MockProvider.complete("print_poem", vec![], &params).await;
+        assert!(unknown.is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use dotenv::dotenv;
+use reqwest::header::HeaderMap;
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+// Failure of a single LLM request, classified so the retry policy knows what to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum LLMError {
+    RateLimited {
+        retry_after: Option<Duration>,
+        body: String,
+    },
+    Server {
+        status: u16,
+        body: String,
+    },
+    Timeout(String),
+    Connection(String),
+    // Invalid key, bad request, exhausted quota - retrying will not help
+    Client {
+        status: u16,
+        body: String,
+    },
+    Decode(String),
+    Config(String),
+    // Offline provider has no recorded response for the request
+    NotRecorded(String),
+}
+
+impl LLMError {
+    // Classify a non-success HTTP response
+    pub fn from_status(status: u16, headers: &HeaderMap, body: String) -> Self {
+        match status {
+            429 if body.contains("insufficient_quota") => Self::Client { status, body },
+            429 => Self::RateLimited {
+                retry_after: retry_after(headers),
+                body,
+            },
+            408 => Self::Timeout(format!("Request timed out (status 408): {}", body)),
+            500..=599 => Self::Server { status, body },
+            _ => Self::Client { status, body },
+        }
+    }
+
+    pub fn from_reqwest(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout(e.to_string())
+        } else if e.is_decode() {
+            Self::Decode(e.to_string())
+        } else {
+            // Connect failures, resets and broken bodies
+            Self::Connection(e.to_string())
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            Self::Client { .. } | Self::Decode(_) | Self::Config(_) | Self::NotRecorded(_)
+        )
+    }
+}
+
+impl fmt::Display for LLMError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RateLimited {
+                retry_after: Some(retry_after),
+                body,
+            } => write!(
+                f,
+                "Rate limited by OpenAI for {:.1}s: {}",
+                retry_after.as_secs_f32(),
+                body
+            ),
+            Self::RateLimited { body, .. } => write!(f, "Rate limited by OpenAI: {}", body),
+            Self::Server { status, body } => write!(f, "OpenAI server error {}: {}", status, body),
+            Self::Timeout(msg) => write!(f, "Timed out: {}", msg),
+            Self::Connection(msg) => write!(f, "Connection failed: {}", msg),
+            Self::Client { status, body } => {
+                write!(f, "OpenAI rejected the request {}: {}", status, body)
+            }
+            Self::Decode(msg) => write!(f, "Could not decode OpenAI response: {}", msg),
+            Self::Config(msg) => write!(f, "Invalid OpenAI configuration: {}", msg),
+            Self::NotRecorded(msg) => write!(f, "No recorded response: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LLMError {}
+
+// Retry-After is given in seconds, OpenAI also sends retry-after-ms
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| -> Option<f64> {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+    };
+    // Negative, NaN and infinite waits are ignored, one too long for a Duration saturates
+    let wait = |secs: f64| -> Option<Duration> {
+        (secs.is_finite() && secs >= 0.0)
+            .then(|| Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX))
+    };
+    header("retry-after-ms")
+        .and_then(|ms| wait(ms / 1000.0))
+        .or_else(|| header("retry-after").and_then(wait))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub request_timeout: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(120),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // LLM_MAX_ATTEMPTS, LLM_RETRY_BASE_MS, LLM_RETRY_MAX_MS and LLM_REQUEST_TIMEOUT_SECS
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let var = |name: &str| -> Option<u64> { env::var(name).ok()?.parse().ok() };
+        let default: RetryPolicy = RetryPolicy::default();
+        Self {
+            max_attempts: var("LLM_MAX_ATTEMPTS").map_or(default.max_attempts, |n| n.max(1) as u32),
+            base_delay: var("LLM_RETRY_BASE_MS").map_or(default.base_delay, Duration::from_millis),
+            max_delay: var("LLM_RETRY_MAX_MS").map_or(default.max_delay, Duration::from_millis),
+            request_timeout: var("LLM_REQUEST_TIMEOUT_SECS")
+                .map_or(default.request_timeout, Duration::from_secs),
+            jitter: default.jitter,
+        }
+    }
+
+    // Wait before the next attempt, or None if the error should be returned.
+    // attempt counts from 1 for the request that just failed.
+    pub fn next_delay(&self, attempt: u32, error: &LLMError) -> Option<Duration> {
+        if attempt >= self.max_attempts || !error.is_retryable() {
+            return None;
+        }
+
+        // The server knows best when a rate limit lifts, a wait past max_delay is reported instead
+        if let LLMError::RateLimited {
+            retry_after: Some(retry_after),
+            ..
+        } = error
+        {
+            return Some(*retry_after).filter(|retry_after| *retry_after <= self.max_delay);
+        }
+
+        // Exponential backoff, jittered between half and the full delay
+        let backoff: Duration = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        if self.jitter {
+            Some(backoff.mul_f64(0.5 + jitter_fraction() / 2.0))
+        } else {
+            Some(backoff)
+        }
+    }
+}
+
+// Random value in [0, 1) without pulling in a rand dependency
+fn jitter_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() % 10_000) as f64 / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn tests_classify_status() {
+        let mut headers: HeaderMap = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("7"));
+
+        let rate_limited: LLMError = LLMError::from_status(429, &headers, "slow down".to_string());
+        assert_eq!(
+            rate_limited,
+            LLMError::RateLimited {
+                retry_after: Some(Duration::from_secs(7)),
+                body: "slow down".to_string()
+            }
+        );
+        assert!(rate_limited.is_retryable());
+
+        let no_quota: LLMError = LLMError::from_status(
+            429,
+            &HeaderMap::new(),
+            "{\"error\":{\"code\":\"insufficient_quota\"}}".to_string(),
+        );
+        assert!(!no_quota.is_retryable());
+
+        assert!(LLMError::from_status(503, &HeaderMap::new(), String::new()).is_retryable());
+        assert!(!LLMError::from_status(401, &HeaderMap::new(), String::new()).is_retryable());
+    }
+
+    #[test]
+    fn tests_retry_after_ignores_invalid_waits() {
+        let retry_after_of = |name: &'static str, value: &'static str| -> Option<Duration> {
+            let mut headers: HeaderMap = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_static(value));
+            retry_after(&headers)
+        };
+
+        assert_eq!(
+            retry_after_of("retry-after-ms", "1500"),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(retry_after_of("retry-after", "-1"), None);
+        assert_eq!(retry_after_of("retry-after", "NaN"), None);
+        assert_eq!(retry_after_of("retry-after", "inf"), None);
+        assert_eq!(retry_after_of("retry-after", "1e300"), Some(Duration::MAX));
+        assert_eq!(
+            RetryPolicy::default().next_delay(
+                1,
+                &LLMError::RateLimited {
+                    retry_after: Some(Duration::MAX),
+                    body: String::new(),
+                }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn tests_retry_policy_delays() {
+        let policy: RetryPolicy = RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(250),
+            request_timeout: Duration::from_secs(1),
+            jitter: false,
+        };
+        let server_error: LLMError = LLMError::Server {
+            status: 500,
+            body: String::new(),
+        };
+
+        assert_eq!(
+            policy.next_delay(1, &server_error),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            policy.next_delay(2, &server_error),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            policy.next_delay(3, &server_error),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(policy.next_delay(4, &server_error), None);
+
+        let rate_limited: LLMError = LLMError::RateLimited {
+            retry_after: Some(Duration::from_secs(60)),
+            body: String::new(),
+        };
+        assert_eq!(policy.next_delay(1, &rate_limited), None);
+        assert!(rate_limited.to_string().contains("for 60.0s"));
+        let rate_limited: LLMError = LLMError::RateLimited {
+            retry_after: Some(Duration::from_millis(200)),
+            body: String::new(),
+        };
+        assert_eq!(
+            policy.next_delay(1, &rate_limited),
+            Some(Duration::from_millis(200))
+        );
+
+        let jittered: RetryPolicy = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+        let delay: Duration = jittered.next_delay(2, &server_error).unwrap();
+        assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+    }
+}
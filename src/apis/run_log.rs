@@ -0,0 +1,56 @@
+use crate::models::general::llm::{APIUsage, CompletionParams};
+use dotenv::dotenv;
+use serde::Serialize;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_RUN_LOG_PATH: &str = "run_log.jsonl";
+
+// One line per LLM call so a run can be reproduced and compared later
+#[derive(Debug, Serialize, Clone)]
+pub struct LLMCallRecord {
+    pub timestamp: u64,
+    pub agent: String,
+    pub ai_function: String,
+    pub params: CompletionParams,
+    pub prompt_version: String,
+    pub response_model: String,
+    pub usage: Option<APIUsage>,
+    pub cached: bool,
+}
+
+impl LLMCallRecord {
+    pub fn new(
+        agent: &str,
+        ai_function: &str,
+        params: &CompletionParams,
+        prompt_version: &str,
+        response_model: &str,
+        usage: Option<APIUsage>,
+        cached: bool,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            agent: agent.to_string(),
+            ai_function: ai_function.to_string(),
+            params: params.clone(),
+            prompt_version: prompt_version.to_string(),
+            response_model: response_model.to_string(),
+            usage,
+            cached,
+        }
+    }
+}
+
+// Append to RUN_LOG_PATH, defaults to run_log.jsonl
+pub fn append_run_log(record: &LLMCallRecord) -> io::Result<()> {
+    dotenv().ok();
+
+    let path: String = env::var("RUN_LOG_PATH").unwrap_or(DEFAULT_RUN_LOG_PATH.to_string());
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
@@ -0,0 +1,294 @@
+use crate::models::general::llm::APIUsage;
+use dotenv::dotenv;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+
+// USD per 1K tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub prompt_per_1k: f64,
+    pub completion_per_1k: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceTable {
+    // Matched by longest model name prefix, so "gpt-4-0613" uses "gpt-4"
+    prices: Vec<(String, ModelPrice)>,
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        let mut table: PriceTable = PriceTable { prices: vec![] };
+        table.set_price("gpt-4", 0.03, 0.06);
+        table.set_price("gpt-4-32k", 0.06, 0.12);
+        table.set_price("gpt-4-turbo", 0.01, 0.03);
+        table.set_price("gpt-4-1106-preview", 0.01, 0.03);
+        table.set_price("gpt-4-0125-preview", 0.01, 0.03);
+        table.set_price("gpt-4o", 0.005, 0.015);
+        table.set_price("gpt-4o-mini", 0.00015, 0.0006);
+        table.set_price("gpt-3.5-turbo", 0.0005, 0.0015);
+        table
+    }
+}
+
+impl PriceTable {
+    pub fn set_price(&mut self, model: &str, prompt_per_1k: f64, completion_per_1k: f64) {
+        let price: ModelPrice = ModelPrice {
+            prompt_per_1k,
+            completion_per_1k,
+        };
+        match self.prices.iter_mut().find(|(name, _)| name == model) {
+            Some(entry) => entry.1 = price,
+            None => self.prices.push((model.to_string(), price)),
+        }
+    }
+
+    pub fn price_for(&self, model: &str) -> Option<ModelPrice> {
+        self.prices
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| *price)
+    }
+
+    pub fn cost(&self, model: &str, usage: &APIUsage) -> f64 {
+        match self.price_for(model) {
+            Some(price) => {
+                usage.prompt_tokens as f64 / 1000.0 * price.prompt_per_1k
+                    + usage.completion_tokens as f64 / 1000.0 * price.completion_per_1k
+            }
+            None => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageTotals {
+    pub calls: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &APIUsage, cost_usd: f64) {
+        self.calls += 1;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+        self.cost_usd += cost_usd;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Budget {
+    pub max_tokens: Option<u64>,
+    pub max_cost_usd: Option<f64>,
+}
+
+impl Budget {
+    // BUDGET_MAX_TOKENS and BUDGET_MAX_USD, either or both may be set
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        Self {
+            max_tokens: env::var("BUDGET_MAX_TOKENS")
+                .ok()
+                .and_then(|tokens| tokens.parse().ok()),
+            max_cost_usd: env::var("BUDGET_MAX_USD")
+                .ok()
+                .and_then(|usd| usd.parse().ok()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetExceeded {
+    pub spent: UsageTotals,
+    pub budget: Budget,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "LLM budget exceeded: spent {} tokens (${:.4}) against a limit of {} tokens / {}",
+            self.spent.total_tokens(),
+            self.spent.cost_usd,
+            self.budget
+                .max_tokens
+                .map_or("unlimited".to_string(), |tokens| tokens.to_string()),
+            self.budget
+                .max_cost_usd
+                .map_or("unlimited".to_string(), |usd| format!("${:.4}", usd)),
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTracker {
+    pub per_agent: BTreeMap<String, UsageTotals>,
+    pub per_function: BTreeMap<String, UsageTotals>,
+    pub total: UsageTotals,
+    pub unpriced_models: Vec<String>,
+    budget: Budget,
+    prices: PriceTable,
+}
+
+impl UsageTracker {
+    pub fn new(budget: Budget, prices: PriceTable) -> Self {
+        Self {
+            budget,
+            prices,
+            ..Default::default()
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        agent_position: &str,
+        ai_function: &str,
+        model: &str,
+        usage: &APIUsage,
+    ) {
+        if self.prices.price_for(model).is_none()
+            && !self.unpriced_models.iter().any(|m| m == model)
+        {
+            self.unpriced_models.push(model.to_string());
+        }
+        let cost_usd: f64 = self.prices.cost(model, usage);
+
+        self.total.add(usage, cost_usd);
+        self.per_agent
+            .entry(agent_position.to_string())
+            .or_default()
+            .add(usage, cost_usd);
+        self.per_function
+            .entry(ai_function.to_string())
+            .or_default()
+            .add(usage, cost_usd);
+    }
+
+    // Called before every LLM request so a run stops once the limit is reached
+    pub fn check_budget(&self) -> Result<(), BudgetExceeded> {
+        let over_tokens: bool = self
+            .budget
+            .max_tokens
+            .is_some_and(|max| self.total.total_tokens() >= max);
+        let over_cost: bool = self
+            .budget
+            .max_cost_usd
+            .is_some_and(|max| self.total.cost_usd >= max);
+
+        if over_tokens || over_cost {
+            Err(BudgetExceeded {
+                spent: self.total,
+                budget: self.budget,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let mut lines: Vec<String> = vec!["LLM usage summary".to_string()];
+        let mut push_section = |title: &str, rows: &BTreeMap<String, UsageTotals>| {
+            lines.push(format!("  By {}:", title));
+            for (name, totals) in rows {
+                lines.push(format!("    {:<32} {}", name, format_totals(totals)));
+            }
+        };
+        push_section("agent", &self.per_agent);
+        push_section("ai_function", &self.per_function);
+        lines.push(format!("  {:<34} {}", "Total", format_totals(&self.total)));
+        if !self.unpriced_models.is_empty() {
+            lines.push(format!(
+                "  No price known for: {} (counted as $0)",
+                self.unpriced_models.join(", ")
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+fn format_totals(totals: &UsageTotals) -> String {
+    format!(
+        "{:>3} calls {:>8} prompt {:>8} completion  ${:.4}",
+        totals.calls, totals.prompt_tokens, totals.completion_tokens, totals.cost_usd
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> APIUsage {
+        APIUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    #[test]
+    fn tests_price_table_prefix_match() {
+        let prices: PriceTable = PriceTable::default();
+        assert_eq!(prices.price_for("gpt-4-0613").unwrap().prompt_per_1k, 0.03);
+        assert_eq!(
+            prices.price_for("gpt-4o-mini-2024").unwrap().prompt_per_1k,
+            0.00015
+        );
+        assert!(prices.price_for("llama-3").is_none());
+
+        let cost: f64 = prices.cost("gpt-4", &usage(1000, 500));
+        assert!((cost - 0.06).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tests_usage_tracker_totals_and_budget() {
+        let budget: Budget = Budget {
+            max_tokens: Some(2000),
+            max_cost_usd: None,
+        };
+        let mut tracker: UsageTracker = UsageTracker::new(budget, PriceTable::default());
+
+        tracker.record(
+            "Solutions Architect",
+            "print_project_scope",
+            "gpt-4",
+            &usage(800, 100),
+        );
+        assert!(tracker.check_budget().is_ok());
+
+        tracker.record(
+            "Backend Developer",
+            "print_fixed_code",
+            "gpt-4",
+            &usage(900, 300),
+        );
+        tracker.record(
+            "Backend Developer",
+            "print_fixed_code",
+            "local-model",
+            &usage(10, 10),
+        );
+
+        assert_eq!(tracker.per_agent["Backend Developer"].calls, 2);
+        assert_eq!(tracker.per_function["print_fixed_code"].prompt_tokens, 910);
+        assert_eq!(tracker.total.total_tokens(), 2120);
+        assert_eq!(tracker.unpriced_models, vec!["local-model".to_string()]);
+
+        let exceeded: BudgetExceeded = tracker.check_budget().unwrap_err();
+        assert_eq!(exceeded.spent.total_tokens(), 2120);
+
+        let summary: String = tracker.summary();
+        dbg!(&summary);
+        assert!(summary.contains("print_project_scope"));
+    }
+}
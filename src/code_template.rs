@@ -160,6 +160,12 @@ async fn main() -> std::io::Result<()> {
 
     let data = web::Data::new(AppState { db: Mutex::new(db) });
 
+    //The port is read from PORT so the server can be tested next to other services
+    let port: u16 = std::env::var("PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(8080);
+
     HttpServer::new(move || {
         App::new()
             .wrap(
@@ -182,7 +188,7 @@ async fn main() -> std::io::Result<()> {
             .route("/register", web::post().to(register)) //because web is a smart pointer
             .route("/login", web::post().to(login))
     })
-    .bind("127.0.0.1:8080")?
+    .bind(("127.0.0.1", port))?
     .run()
     .await
 }
@@ -0,0 +1,899 @@
+use crate::helpers::clarify::Clarifier;
+use crate::helpers::events::AgentEvent;
+use crate::helpers::review::{ReviewDecision, ReviewRequest, Reviewer};
+use crate::helpers::run_context::RunControl;
+use crate::models::agent_basic::basic_agent::AgentState;
+use crate::models::agents::agent_traits::FactSheet;
+use crate::pipeline::{PipelineBuilder, PipelineError, PipelineOutput};
+use async_trait::async_trait;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Span, Spans};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use similar::{ChangeTag, TextDiff};
+use std::collections::VecDeque;
+use std::io::{self, Stdout};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+const MAX_LOG_LINES: usize = 200;
+const DIFF_CONTEXT_LINES: usize = 3;
+const TICK: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentRow {
+    pub name: String,
+    pub state: Option<AgentState>,
+    pub outcome: Option<Result<(), String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointRow {
+    pub route: String,
+    pub status_code: Option<u16>,
+    pub passed: bool,
+    pub error: Option<String>,
+}
+
+// Latest code change, as unified diff lines with a little context
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeDiff {
+    pub path: String,
+    pub lines: Vec<(ChangeTag, String)>,
+}
+
+impl CodeDiff {
+    pub fn new(path: &str, previous: &str, contents: &str) -> Self {
+        let diff = TextDiff::from_lines(previous, contents);
+        let mut lines: Vec<(ChangeTag, String)> = vec![];
+        for (i, group) in diff.grouped_ops(DIFF_CONTEXT_LINES).iter().enumerate() {
+            if i > 0 {
+                lines.push((ChangeTag::Equal, "...".to_string()));
+            }
+            for op in group {
+                for change in diff.iter_changes(op) {
+                    lines.push((change.tag(), change.value().trim_end().to_string()));
+                }
+            }
+        }
+        Self {
+            path: path.to_string(),
+            lines,
+        }
+    }
+}
+
+// Everything the dashboard shows, built up from the events of a run
+#[derive(Debug, Default)]
+pub struct DashboardState {
+    pub agents: Vec<AgentRow>,
+    pub llm_calls: VecDeque<String>,
+    pub build_output: Option<(bool, String)>,
+    pub endpoints: Vec<EndpointRow>,
+    pub factsheet: Option<FactSheet>,
+    pub diff: Option<CodeDiff>,
+    pub diff_scroll: u16,
+    pub pending_review: Option<ReviewRequest>,
+    // Text typed so far while writing feedback for the pending review
+    pub feedback_input: Option<String>,
+    // Clarifying questions waiting for answers, answered in order from the input line
+    pub questions: Vec<String>,
+    pub answers: Vec<String>,
+    pub answer_input: String,
+    pub last_message: Option<String>,
+    pub paused: bool,
+    pub outcome: Option<Result<(), String>>,
+}
+
+impl DashboardState {
+    pub fn apply(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::AgentStarted { agent, .. } => {
+                self.agent_row(agent).outcome = None;
+                self.endpoints.clear();
+            }
+            AgentEvent::StateChanged { agent, to, .. } => {
+                self.agent_row(agent).state = Some(to.clone())
+            }
+            AgentEvent::AgentFinished { agent, error } => {
+                self.agent_row(agent).outcome = Some(error.clone().map_or(Ok(()), Err))
+            }
+            AgentEvent::Message { agent, message, .. } => {
+                self.last_message = Some(format!("{}: {}", agent, message))
+            }
+            AgentEvent::LLMCallStarted { agent, ai_function } => {
+                self.log_llm_call(format!("{} -> {}", agent, ai_function))
+            }
+            AgentEvent::LLMCallFinished {
+                agent,
+                ai_function,
+                model,
+                usage,
+                cached,
+                elapsed_ms,
+                error,
+            } => {
+                let detail: String = match (error, cached) {
+                    (Some(e), _) => format!("failed: {}", e),
+                    (None, true) => "cached".to_string(),
+                    (None, false) => format!(
+                        "{} ms, {}, {} tokens",
+                        elapsed_ms,
+                        model.as_deref().unwrap_or("unknown model"),
+                        usage.map_or(0, |usage| usage.total_tokens)
+                    ),
+                };
+                self.log_llm_call(format!("{} <- {} ({})", agent, ai_function, detail));
+            }
+            AgentEvent::BuildResult {
+                success, output, ..
+            } => self.build_output = Some((*success, output.clone())),
+            AgentEvent::BugFound { bug_count, .. } => {
+                self.last_message = Some(format!("Build failed, bug {} sent back", bug_count))
+            }
+            AgentEvent::EndpointTestResult {
+                route,
+                status_code,
+                passed,
+                error,
+                ..
+            } => self.endpoints.push(EndpointRow {
+                route: route.clone(),
+                status_code: *status_code,
+                passed: *passed,
+                error: error.clone(),
+            }),
+            AgentEvent::PersistenceChecked {
+                route,
+                passed,
+                error,
+                ..
+            } => self.endpoints.push(EndpointRow {
+                route: format!("{} after restart", route),
+                status_code: None,
+                passed: *passed,
+                error: error.clone(),
+            }),
+            AgentEvent::ClarificationRequested { questions, .. } => {
+                self.questions = questions.clone();
+                self.answers.clear();
+                self.answer_input.clear();
+            }
+            AgentEvent::FactSheetUpdated { factsheet } => self.factsheet = Some(*factsheet.clone()),
+            AgentEvent::CodeWritten {
+                path,
+                previous,
+                contents,
+            } => {
+                self.diff = Some(CodeDiff::new(
+                    path,
+                    previous.as_deref().unwrap_or(""),
+                    contents,
+                ));
+                self.diff_scroll = 0;
+            }
+            AgentEvent::ReviewRequested {
+                agent,
+                gate,
+                summary,
+            } => {
+                self.pending_review = Some(ReviewRequest {
+                    gate: *gate,
+                    agent: agent.clone(),
+                    summary: summary.clone(),
+                })
+            }
+            AgentEvent::ReviewDecided { .. } => {
+                self.pending_review = None;
+                self.feedback_input = None;
+            }
+        }
+    }
+
+    pub fn is_answering_questions(&self) -> bool {
+        self.answers.len() < self.questions.len()
+    }
+
+    // Enter moves to the next question, Esc leaves the rest open. Returns the answers once done.
+    pub fn type_answer(&mut self, key: KeyCode) -> Option<Vec<String>> {
+        match key {
+            KeyCode::Char(c) => self.answer_input.push(c),
+            KeyCode::Backspace => {
+                self.answer_input.pop();
+            }
+            KeyCode::Enter => self.answers.push(std::mem::take(&mut self.answer_input)),
+            KeyCode::Esc => {
+                self.answers.push(std::mem::take(&mut self.answer_input));
+                self.answers.resize(self.questions.len(), String::new());
+            }
+            _ => {}
+        }
+        if self.is_answering_questions() {
+            return None;
+        }
+        self.questions.clear();
+        Some(std::mem::take(&mut self.answers))
+    }
+
+    fn agent_row(&mut self, agent: &str) -> &mut AgentRow {
+        match self.agents.iter().position(|row| row.name == agent) {
+            Some(i) => &mut self.agents[i],
+            None => {
+                self.agents.push(AgentRow {
+                    name: agent.to_string(),
+                    state: None,
+                    outcome: None,
+                });
+                self.agents.last_mut().unwrap()
+            }
+        }
+    }
+
+    fn log_llm_call(&mut self, line: String) {
+        if self.llm_calls.len() == MAX_LOG_LINES {
+            self.llm_calls.pop_front();
+        }
+        self.llm_calls.push_back(line);
+    }
+}
+
+// Holds what a run is waiting for until keys answer it
+struct AnswerSlot<T> {
+    pending: Arc<Mutex<Option<oneshot::Sender<T>>>>,
+}
+
+impl<T> Clone for AnswerSlot<T> {
+    fn clone(&self) -> Self {
+        Self {
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<T> Default for AnswerSlot<T> {
+    fn default() -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<T> AnswerSlot<T> {
+    // False when nothing was waiting for an answer
+    fn answer(&self, answer: T) -> bool {
+        match self.pending.lock().unwrap().take() {
+            Some(sender) => sender.send(answer).is_ok(),
+            None => false,
+        }
+    }
+
+    // None when the dashboard went away before answering
+    async fn wait(&self) -> Option<T> {
+        let (sender, receiver) = oneshot::channel::<T>();
+        *self.pending.lock().unwrap() = Some(sender);
+        receiver.await.ok()
+    }
+
+    #[cfg(test)]
+    fn is_waiting(&self) -> bool {
+        self.pending.lock().unwrap().is_some()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct DashboardReviewer {
+    slot: AnswerSlot<ReviewDecision>,
+}
+
+impl DashboardReviewer {
+    pub fn answer(&self, decision: ReviewDecision) -> bool {
+        self.slot.answer(decision)
+    }
+}
+
+#[async_trait]
+impl Reviewer for DashboardReviewer {
+    async fn review(&self, _request: &ReviewRequest) -> ReviewDecision {
+        self.slot.wait().await.unwrap_or(ReviewDecision::Reject)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct DashboardClarifier {
+    slot: AnswerSlot<Vec<String>>,
+}
+
+#[async_trait]
+impl Clarifier for DashboardClarifier {
+    async fn answer(&self, _questions: &[String]) -> Vec<String> {
+        self.slot.wait().await.unwrap_or_default()
+    }
+}
+
+// Run a project behind the full screen dashboard, clarifying questions are answered in it too
+// Keys: a approve, r reject, f type feedback, p pause or resume, x abort, q quit once finished,
+// up/down scroll the diff
+pub async fn run_dashboard(
+    builder: PipelineBuilder,
+    user_request: &str,
+    ask_clarifying_questions: bool,
+) -> Result<PipelineOutput, PipelineError> {
+    let reviewer: DashboardReviewer = DashboardReviewer::default();
+    let clarifier: DashboardClarifier = DashboardClarifier::default();
+    let control: RunControl = RunControl::default();
+    let mut builder: PipelineBuilder = builder
+        .print_to_terminal(false)
+        .reviewer(reviewer.clone())
+        .control(control.clone());
+    if ask_clarifying_questions {
+        builder = builder.clarifier(clarifier.clone());
+    }
+    let (builder, events) = builder.event_channel();
+
+    let user_request: String = user_request.to_string();
+    let run: JoinHandle<Result<PipelineOutput, PipelineError>> =
+        tokio::spawn(async move { builder.build().run(&user_request).await });
+
+    let terminal_error = |e: io::Error| PipelineError {
+        message: format!("Dashboard terminal error: {}", e),
+        factsheet: None,
+    };
+    let mut terminal: Terminal<CrosstermBackend<Stdout>> =
+        enter_dashboard().map_err(terminal_error)?;
+    let dashboard_res: io::Result<Result<PipelineOutput, PipelineError>> =
+        dashboard_loop(&mut terminal, run, events, &reviewer, &clarifier, &control).await;
+    leave_dashboard(&mut terminal).map_err(terminal_error)?;
+
+    dashboard_res.map_err(terminal_error)?
+}
+
+fn enter_dashboard() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode()?;
+    let mut stdout: Stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(stdout))
+}
+
+fn leave_dashboard(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()
+}
+
+async fn dashboard_loop<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mut run: JoinHandle<Result<PipelineOutput, PipelineError>>,
+    mut events: UnboundedReceiver<AgentEvent>,
+    reviewer: &DashboardReviewer,
+    clarifier: &DashboardClarifier,
+    control: &RunControl,
+) -> io::Result<Result<PipelineOutput, PipelineError>> {
+    let mut state: DashboardState = DashboardState::default();
+    let mut result: Option<Result<PipelineOutput, PipelineError>> = None;
+
+    loop {
+        while let Ok(event) = events.try_recv() {
+            state.apply(&event);
+        }
+        if result.is_none() && run.is_finished() {
+            let run_res: Result<PipelineOutput, PipelineError> =
+                (&mut run).await.unwrap_or_else(|e| {
+                    Err(PipelineError {
+                        message: format!("Project run failed: {}", e),
+                        factsheet: state.factsheet.clone().map(Box::new),
+                    })
+                });
+            state.outcome = Some(match &run_res {
+                Ok(_) => Ok(()),
+                Err(e) => Err(e.message.clone()),
+            });
+            result = Some(run_res);
+        }
+        state.paused = control.is_paused();
+
+        terminal.draw(|frame| render(frame, &state))?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let key: KeyEvent = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        if state.is_answering_questions() {
+            if let Some(answers) = state.type_answer(key.code) {
+                clarifier.slot.answer(answers);
+            }
+            continue;
+        }
+
+        // While writing feedback every key is text, Enter sends it and Esc cancels
+        if let Some(feedback) = state.feedback_input.as_mut() {
+            match key.code {
+                KeyCode::Char(c) => feedback.push(c),
+                KeyCode::Backspace => {
+                    feedback.pop();
+                }
+                KeyCode::Enter if !feedback.trim().is_empty() => {
+                    reviewer.answer(ReviewDecision::Feedback(feedback.trim().to_string()));
+                    state.feedback_input = None;
+                }
+                KeyCode::Esc => state.feedback_input = None,
+                _ => {}
+            }
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('a') => {
+                reviewer.answer(ReviewDecision::Approve);
+            }
+            KeyCode::Char('r') => {
+                reviewer.answer(ReviewDecision::Reject);
+            }
+            KeyCode::Char('f') if state.pending_review.is_some() => {
+                state.feedback_input = Some(String::new())
+            }
+            KeyCode::Char('p') if control.is_paused() => control.resume(),
+            KeyCode::Char('p') => control.pause(),
+            KeyCode::Up => state.diff_scroll = state.diff_scroll.saturating_sub(1),
+            KeyCode::Down => state.diff_scroll = state.diff_scroll.saturating_add(1),
+            KeyCode::Char('q') | KeyCode::Char('x') | KeyCode::Esc => match result {
+                Some(run_res) => return Ok(run_res),
+                None if key.code == KeyCode::Char('q') => {
+                    state.last_message = Some("Still running, press x to abort".to_string())
+                }
+                None => {
+                    // Wait for the cancelled run to be dropped, which stops its backend server
+                    run.abort();
+                    let _ = (&mut run).await;
+                    return Ok(Err(PipelineError {
+                        message: "Project aborted from the dashboard".to_string(),
+                        factsheet: state.factsheet.clone().map(Box::new),
+                    }));
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+fn pane(title: &str) -> Block<'static> {
+    Block::default().borders(Borders::ALL).title(Span::styled(
+        title.to_string(),
+        Style::default().add_modifier(Modifier::BOLD),
+    ))
+}
+
+// The last lines that fit into a pane
+fn tail(lines: Vec<Spans>, area: Rect) -> Vec<Spans> {
+    let height: usize = area.height.saturating_sub(2) as usize;
+    let skip: usize = lines.len().saturating_sub(height);
+    lines.into_iter().skip(skip).collect()
+}
+
+pub fn render<B: Backend>(frame: &mut Frame<B>, state: &DashboardState) {
+    let rows: Vec<Rect> = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(10), Constraint::Length(4)])
+        .split(frame.size())
+        .to_vec();
+    let columns: Vec<Rect> = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(rows[0])
+        .to_vec();
+    let left: Vec<Rect> = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(5)])
+        .split(columns[0])
+        .to_vec();
+    let right: Vec<Rect> = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(columns[1])
+        .to_vec();
+    let right_top: Vec<Rect> = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(right[0])
+        .to_vec();
+    let checks: Vec<Rect> = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(right_top[1])
+        .to_vec();
+
+    render_agents(frame, state, left[0]);
+    render_factsheet(frame, state, left[1]);
+    render_llm_calls(frame, state, right_top[0]);
+    render_build_output(frame, state, checks[0]);
+    render_endpoints(frame, state, checks[1]);
+    render_diff(frame, state, right[1]);
+    render_status(frame, state, rows[1]);
+}
+
+fn render_agents<B: Backend>(frame: &mut Frame<B>, state: &DashboardState, area: Rect) {
+    let items: Vec<ListItem> = state
+        .agents
+        .iter()
+        .map(|row| {
+            let (status, color) = match &row.outcome {
+                None => ("running", Color::Cyan),
+                Some(Ok(())) => ("done", Color::Green),
+                Some(Err(_)) => ("failed", Color::Red),
+            };
+            let agent_state: String = row
+                .state
+                .as_ref()
+                .map_or("-".to_string(), |state| format!("{:?}", state));
+            ListItem::new(Spans::from(vec![
+                Span::raw(format!("{:<22}", row.name)),
+                Span::raw(format!("{:<12}", agent_state)),
+                Span::styled(status, Style::default().fg(color)),
+            ]))
+        })
+        .collect();
+    frame.render_widget(List::new(items).block(pane("Agents")), area);
+}
+
+fn render_factsheet<B: Backend>(frame: &mut Frame<B>, state: &DashboardState, area: Rect) {
+    let text: String = match &state.factsheet {
+        Some(factsheet) => {
+            // The code itself is in the diff pane
+            let mut value: serde_json::Value = serde_json::to_value(factsheet).unwrap_or_default();
+            if let Some(code) = value.get("backend_code").and_then(|code| code.as_str()) {
+                value["backend_code"] = format!("<{} lines>", code.lines().count()).into();
+            }
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        }
+        None => "Waiting for the project goal...".to_string(),
+    };
+    frame.render_widget(
+        Paragraph::new(text)
+            .block(pane("Factsheet"))
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+fn render_llm_calls<B: Backend>(frame: &mut Frame<B>, state: &DashboardState, area: Rect) {
+    let lines: Vec<Spans> = state
+        .llm_calls
+        .iter()
+        .map(|line| {
+            let color: Color = if line.contains("failed:") {
+                Color::Red
+            } else {
+                Color::Cyan
+            };
+            Spans::from(Span::styled(line.clone(), Style::default().fg(color)))
+        })
+        .collect();
+    frame.render_widget(
+        Paragraph::new(tail(lines, area)).block(pane("LLM calls")),
+        area,
+    );
+}
+
+fn render_build_output<B: Backend>(frame: &mut Frame<B>, state: &DashboardState, area: Rect) {
+    let (title, lines): (&str, Vec<Spans>) = match &state.build_output {
+        Some((success, output)) => (
+            if *success {
+                "Build output: ok"
+            } else {
+                "Build output: failed"
+            },
+            output
+                .lines()
+                .map(|line| Spans::from(line.to_string()))
+                .collect(),
+        ),
+        None => ("Build output", vec![]),
+    };
+    frame.render_widget(Paragraph::new(tail(lines, area)).block(pane(title)), area);
+}
+
+fn render_endpoints<B: Backend>(frame: &mut Frame<B>, state: &DashboardState, area: Rect) {
+    let items: Vec<ListItem> = state
+        .endpoints
+        .iter()
+        .map(|row| {
+            let (result, color) = match (&row.error, row.passed) {
+                (Some(e), _) => (e.clone(), Color::Red),
+                (None, true) => ("ok".to_string(), Color::Green),
+                (None, false) => (
+                    row.status_code
+                        .map_or("failed".to_string(), |code| code.to_string()),
+                    Color::Red,
+                ),
+            };
+            ListItem::new(Spans::from(vec![
+                Span::raw(format!("{} ", row.route)),
+                Span::styled(result, Style::default().fg(color)),
+            ]))
+        })
+        .collect();
+    frame.render_widget(List::new(items).block(pane("Endpoint tests")), area);
+}
+
+fn render_diff<B: Backend>(frame: &mut Frame<B>, state: &DashboardState, area: Rect) {
+    let (title, lines): (String, Vec<Spans>) = match &state.diff {
+        Some(diff) => (
+            format!("Latest change: {}", diff.path),
+            diff.lines
+                .iter()
+                .map(|(tag, line)| {
+                    let (sign, color) = match tag {
+                        ChangeTag::Insert => ("+", Color::Green),
+                        ChangeTag::Delete => ("-", Color::Red),
+                        ChangeTag::Equal => (" ", Color::Reset),
+                    };
+                    Spans::from(Span::styled(
+                        format!("{}{}", sign, line),
+                        Style::default().fg(color),
+                    ))
+                })
+                .collect(),
+        ),
+        None => ("Latest change".to_string(), vec![]),
+    };
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(pane(&title))
+            .scroll((state.diff_scroll, 0)),
+        area,
+    );
+}
+
+fn render_status<B: Backend>(frame: &mut Frame<B>, state: &DashboardState, area: Rect) {
+    if state.is_answering_questions() {
+        let question: Spans = Spans::from(Span::styled(
+            format!(
+                "Question {} of {}: {}",
+                state.answers.len() + 1,
+                state.questions.len(),
+                state.questions[state.answers.len()]
+            ),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+        let answer: Spans = Spans::from(format!(
+            "> {}_  (Enter next question, Esc leave the rest to the agents)",
+            state.answer_input
+        ));
+        frame.render_widget(
+            Paragraph::new(vec![question, answer]).block(pane("Clarifying questions")),
+            area,
+        );
+        return;
+    }
+
+    let headline: Spans = match (&state.outcome, &state.pending_review) {
+        (Some(Ok(())), _) => Spans::from(Span::styled(
+            "Project finished, press q to quit",
+            Style::default().fg(Color::Green),
+        )),
+        (Some(Err(e)), _) => Spans::from(Span::styled(
+            format!("Project stopped: {}, press q to quit", e),
+            Style::default().fg(Color::Red),
+        )),
+        (None, Some(_)) if state.feedback_input.is_some() => Spans::from(vec![
+            Span::styled("Feedback: ", Style::default().fg(Color::Yellow)),
+            Span::raw(format!(
+                "{}_",
+                state.feedback_input.as_deref().unwrap_or_default()
+            )),
+        ]),
+        (None, Some(review)) => Spans::from(Span::styled(
+            format!(
+                "{} review from {}: {} (a approve, r reject, f feedback)",
+                review.gate.title(),
+                review.agent,
+                review.summary.lines().next().unwrap_or_default()
+            ),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        (None, None) if state.paused => Spans::from(Span::styled(
+            "Paused before the next step, press p to resume",
+            Style::default().fg(Color::Yellow),
+        )),
+        (None, None) => Spans::from(state.last_message.clone().unwrap_or_default()),
+    };
+    let keys: Spans = Spans::from(
+        "a approve  r reject  f feedback  p pause/resume  x abort  q quit  up/down scroll diff",
+    );
+    frame.render_widget(
+        Paragraph::new(vec![headline, keys]).block(pane("Status")),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::review::ReviewGate;
+    use ratatui::backend::TestBackend;
+
+    fn factsheet() -> FactSheet {
+        FactSheet {
+            project_description: "Build a todo app".to_string(),
+            requirements: None,
+            project_scope: None,
+            external_urls: None,
+            external_url_probes: None,
+            backend_template: None,
+            database_migration: None,
+            change_request: None,
+            backend_code: Some("fn main() {}\n".to_string()),
+            api_endpoint_schema: None,
+        }
+    }
+
+    #[test]
+    fn tests_dashboard_state_applies_events() {
+        let mut state: DashboardState = DashboardState::default();
+        state.apply(&AgentEvent::AgentStarted {
+            agent: "Backend Developer".to_string(),
+            objective: "Develops backend code".to_string(),
+        });
+        state.apply(&AgentEvent::StateChanged {
+            agent: "Backend Developer".to_string(),
+            from: AgentState::Working,
+            to: AgentState::UnitTesting,
+        });
+        state.apply(&AgentEvent::CodeWritten {
+            path: "src/main.rs".to_string(),
+            previous: Some("fn main() {\n    println!(\"hi\");\n}\n".to_string()),
+            contents: "fn main() {\n    println!(\"todo\");\n}\n".to_string(),
+        });
+        state.apply(&AgentEvent::ReviewRequested {
+            agent: "Backend Developer".to_string(),
+            gate: ReviewGate::RunServer,
+            summary: "cargo build and run in web_template".to_string(),
+        });
+        state.apply(&AgentEvent::FactSheetUpdated {
+            factsheet: Box::new(factsheet()),
+        });
+
+        assert_eq!(
+            state.agents,
+            vec![AgentRow {
+                name: "Backend Developer".to_string(),
+                state: Some(AgentState::UnitTesting),
+                outcome: None,
+            }]
+        );
+        assert_eq!(
+            state.pending_review.as_ref().map(|review| review.gate),
+            Some(ReviewGate::RunServer)
+        );
+        assert_eq!(
+            state.diff.as_ref().unwrap().lines,
+            vec![
+                (ChangeTag::Equal, "fn main() {".to_string()),
+                (ChangeTag::Delete, "    println!(\"hi\");".to_string()),
+                (ChangeTag::Insert, "    println!(\"todo\");".to_string()),
+                (ChangeTag::Equal, "}".to_string()),
+            ]
+        );
+
+        state.apply(&AgentEvent::ReviewDecided {
+            agent: "Backend Developer".to_string(),
+            gate: ReviewGate::RunServer,
+            decision: ReviewDecision::Reject,
+        });
+        state.apply(&AgentEvent::AgentFinished {
+            agent: "Backend Developer".to_string(),
+            error: Some("Generated code was not approved to run".to_string()),
+        });
+        assert_eq!(state.pending_review, None);
+        assert!(matches!(&state.agents[0].outcome, Some(Err(_))));
+    }
+
+    #[tokio::test]
+    async fn tests_dashboard_reviewer_waits_for_answer() {
+        let reviewer: DashboardReviewer = DashboardReviewer::default();
+        assert!(!reviewer.answer(ReviewDecision::Approve));
+
+        let waiting: DashboardReviewer = reviewer.clone();
+        let decision: JoinHandle<ReviewDecision> = tokio::spawn(async move {
+            waiting
+                .review(&ReviewRequest {
+                    gate: ReviewGate::Scope,
+                    agent: "Solutions Architect".to_string(),
+                    summary: "{}".to_string(),
+                })
+                .await
+        });
+        while !reviewer.slot.is_waiting() {
+            tokio::task::yield_now().await;
+        }
+        assert!(reviewer.answer(ReviewDecision::Feedback("Add auth".to_string())));
+        assert_eq!(
+            decision.await.unwrap(),
+            ReviewDecision::Feedback("Add auth".to_string())
+        );
+    }
+
+    #[test]
+    fn tests_dashboard_answers_clarifying_questions() {
+        let mut state: DashboardState = DashboardState::default();
+        state.apply(&AgentEvent::ClarificationRequested {
+            agent: "Project Manager".to_string(),
+            questions: vec![
+                "Multiple users?".to_string(),
+                "Which currency?".to_string(),
+                "Any deadline?".to_string(),
+            ],
+        });
+        assert!(state.is_answering_questions());
+
+        for key in [KeyCode::Char('n'), KeyCode::Char('o'), KeyCode::Backspace] {
+            assert_eq!(state.type_answer(key), None);
+        }
+        assert_eq!(state.type_answer(KeyCode::Enter), None);
+        assert_eq!(state.answers, vec!["n".to_string()]);
+        assert_eq!(state.type_answer(KeyCode::Char('$')), None);
+
+        assert_eq!(
+            state.type_answer(KeyCode::Esc),
+            Some(vec!["n".to_string(), "$".to_string(), String::new()])
+        );
+        assert!(!state.is_answering_questions());
+    }
+
+    #[test]
+    fn tests_dashboard_renders_panes() {
+        let mut state: DashboardState = DashboardState::default();
+        state.apply(&AgentEvent::FactSheetUpdated {
+            factsheet: Box::new(factsheet()),
+        });
+        state.apply(&AgentEvent::LLMCallStarted {
+            agent: "Solutions Architect".to_string(),
+            ai_function: "print_project_scope".to_string(),
+        });
+        state.apply(&AgentEvent::ReviewRequested {
+            agent: "Backend Developer".to_string(),
+            gate: ReviewGate::RunServer,
+            summary: "cargo build and run in web_template".to_string(),
+        });
+
+        let mut terminal: Terminal<TestBackend> = Terminal::new(TestBackend::new(140, 40)).unwrap();
+        terminal.draw(|frame| render(frame, &state)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let screen: String = buffer
+            .content()
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect();
+        for title in [
+            "Agents",
+            "Factsheet",
+            "LLM calls",
+            "Build output",
+            "Endpoint tests",
+            "Latest change",
+        ] {
+            assert!(screen.contains(title), "missing pane {}", title);
+        }
+        assert!(screen.contains("Solutions Architect -> print_project_scope"));
+        assert!(screen.contains("Build and run the server review from Backend Developer"));
+        assert!(screen.contains("<1 lines>"));
+    }
+}
@@ -0,0 +1,329 @@
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::api_schema::{HttpMethod, Shape};
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Client, RequestBuilder};
+use serde_json::{Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const AUTH_CHECK_PASSWORD: &str = "auth-check-password";
+const REGISTER_ROUTES: [&str; 3] = ["/register", "/signup", "/sign-up"];
+const LOGIN_ROUTES: [&str; 3] = ["/login", "/signin", "/sign-in"];
+const LOGOUT_ROUTES: [&str; 3] = ["/logout", "/signout", "/sign-out"];
+
+// Routes the check signs in with, and the static GET routes that need a session
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthRoutes {
+    pub register: RouteObject,
+    pub login: RouteObject,
+    pub logout: Option<RouteObject>,
+    pub protected: Vec<String>,
+}
+
+// One request of the check, published like an endpoint test
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthCheck {
+    pub route: String,
+    pub status_code: Option<u16>,
+    pub passed: bool,
+    pub error: Option<String>,
+}
+
+impl AuthCheck {
+    fn status(route: String, status_code: u16, passed: bool) -> Self {
+        Self {
+            route,
+            status_code: Some(status_code),
+            passed,
+            error: None,
+        }
+    }
+
+    fn failed(route: String, error: String) -> Self {
+        Self {
+            route,
+            status_code: None,
+            passed: false,
+            error: Some(error),
+        }
+    }
+
+    // What went wrong, for the developer to fix
+    pub fn failure(&self) -> Option<String> {
+        match (self.passed, &self.error, self.status_code) {
+            (true, _, _) => None,
+            (false, Some(error), _) => Some(format!("{} failed: {}", self.route, error)),
+            (false, None, Some(status_code)) => {
+                Some(format!("{} returned {}", self.route, status_code))
+            }
+            (false, None, None) => Some(format!("{} failed", self.route)),
+        }
+    }
+}
+
+fn find_post(routes: &[RouteObject], names: &[&str]) -> Option<RouteObject> {
+    routes
+        .iter()
+        .find(|route_object| {
+            let path: &str = route_object.route.as_str().trim_end_matches('/');
+            route_object.method == HttpMethod::Post && names.iter().any(|name| path.ends_with(name))
+        })
+        .cloned()
+}
+
+pub fn auth_routes(routes: &[RouteObject]) -> Option<AuthRoutes> {
+    let protected: Vec<String> = routes
+        .iter()
+        .filter(|route_object| {
+            route_object.requires_auth
+                && route_object.method == HttpMethod::Get
+                && !route_object.route.is_dynamic()
+        })
+        .map(|route_object| route_object.route.to_string())
+        .collect();
+    if protected.is_empty() {
+        return None;
+    }
+
+    Some(AuthRoutes {
+        register: find_post(routes, &REGISTER_ROUTES)?,
+        login: find_post(routes, &LOGIN_ROUTES)?,
+        logout: find_post(routes, &LOGOUT_ROUTES),
+        protected,
+    })
+}
+
+// Sign up body for a new user, every string field but the password carries the username
+pub fn new_credentials(register_body: Option<&Shape>) -> Value {
+    let nanos: u128 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let username: String = format!("auth_check_{}@example.com", nanos);
+
+    let mut fields: Map<String, Value> =
+        match register_body.map(|shape| shape.sample(&username, (nanos % 1_000_000_000) as u32)) {
+            Some(Value::Object(fields)) => fields,
+            _ => Map::new(),
+        };
+    fields
+        .entry("username")
+        .or_insert(Value::from(username.as_str()));
+    for (name, value) in fields.iter_mut() {
+        if name.to_lowercase().contains("password") {
+            *value = Value::from(AUTH_CHECK_PASSWORD);
+        }
+    }
+    fields.insert("password".to_string(), Value::from(AUTH_CHECK_PASSWORD));
+    Value::Object(fields)
+}
+
+// Session token of a login response, i.e. {"token": "..."}
+pub fn token_from_response(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    ["token", "access_token", "session_token"]
+        .iter()
+        .find_map(|key| value.get(key)?.as_str())
+        .map(|token| token.to_string())
+}
+
+pub fn with_token(request: RequestBuilder, token: Option<&str>) -> RequestBuilder {
+    match token {
+        Some(token) => request.header(AUTHORIZATION, format!("Bearer {}", token)),
+        None => request,
+    }
+}
+
+async fn get_status(client: &Client, url: &str, token: Option<&str>) -> Result<u16, String> {
+    let response: reqwest::Response = with_token(client.get(url), token)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(response.status().as_u16())
+}
+
+pub async fn sign_up(
+    client: &Client,
+    base_url: &str,
+    auth: &AuthRoutes,
+    credentials: &Value,
+) -> Result<(), String> {
+    let url: String = format!("{}{}", base_url, auth.register.route);
+    let response: reqwest::Response = client
+        .post(&url)
+        .json(credentials)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    match response.status().is_success() {
+        true => Ok(()),
+        false => Err(format!(
+            "POST {} returned {}",
+            auth.register.route,
+            response.status().as_u16()
+        )),
+    }
+}
+
+pub async fn log_in(
+    client: &Client,
+    base_url: &str,
+    auth: &AuthRoutes,
+    credentials: &Value,
+) -> Result<String, String> {
+    let url: String = format!("{}{}", base_url, auth.login.route);
+    let response: reqwest::Response = client
+        .post(&url)
+        .json(credentials)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status: u16 = response.status().as_u16();
+    let body: String = response.text().await.map_err(|e| e.to_string())?;
+    if status != 200 {
+        return Err(format!("POST {} returned {}", auth.login.route, status));
+    }
+    token_from_response(&body).ok_or(format!(
+        "POST {} did not answer with a {{\"token\": ...}} body",
+        auth.login.route
+    ))
+}
+
+// Fresh token for the signed up user, none when the api has no login
+pub async fn session_token(
+    client: &Client,
+    base_url: &str,
+    auth: Option<(&AuthRoutes, &Value)>,
+) -> Result<Option<String>, String> {
+    match auth {
+        Some((routes, credentials)) => log_in(client, base_url, routes, credentials)
+            .await
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+// Protected routes must turn anonymous requests away and serve a signed in user
+pub async fn check_protected_routes(
+    client: &Client,
+    base_url: &str,
+    auth: &AuthRoutes,
+    credentials: &Value,
+) -> Vec<AuthCheck> {
+    if let Err(e) = sign_up(client, base_url, auth, credentials).await {
+        return vec![AuthCheck::failed(auth.register.route.to_string(), e)];
+    }
+    let token: String = match log_in(client, base_url, auth, credentials).await {
+        Ok(token) => token,
+        Err(e) => return vec![AuthCheck::failed(auth.login.route.to_string(), e)],
+    };
+
+    let mut checks: Vec<AuthCheck> = vec![];
+    for route in &auth.protected {
+        let url: String = format!("{}{}", base_url, route);
+        for (label, token) in [
+            ("without a token", None),
+            ("with a token", Some(token.as_str())),
+        ] {
+            let route_label: String = format!("{} {}", route, label);
+            checks.push(match get_status(client, &url, token).await {
+                Ok(status_code) => {
+                    let passed: bool = match token {
+                        Some(_) => status_code == 200,
+                        None => status_code == 401 || status_code == 403,
+                    };
+                    AuthCheck::status(route_label, status_code, passed)
+                }
+                Err(e) => AuthCheck::failed(route_label, e),
+            });
+        }
+    }
+
+    // A token is no use after logging out
+    if let Some(logout) = &auth.logout {
+        let logout_url: String = format!("{}{}", base_url, logout.route);
+        let logout_res: Result<u16, String> = with_token(client.post(&logout_url), Some(&token))
+            .send()
+            .await
+            .map(|response| response.status().as_u16())
+            .map_err(|e| e.to_string());
+        checks.push(match logout_res {
+            Ok(status_code) => {
+                AuthCheck::status(logout.route.to_string(), status_code, status_code == 200)
+            }
+            Err(e) => AuthCheck::failed(logout.route.to_string(), e),
+        });
+
+        let route: &String = &auth.protected[0];
+        let route_label: String = format!("{} after logout", route);
+        let url: String = format!("{}{}", base_url, route);
+        checks.push(match get_status(client, &url, Some(&token)).await {
+            Ok(status_code) => AuthCheck::status(
+                route_label,
+                status_code,
+                status_code == 401 || status_code == 403,
+            ),
+            Err(e) => AuthCheck::failed(route_label, e),
+        });
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::stub_server::{StubResponse, StubServer};
+
+    const ROUTES: &str = r#"[
+      {"route": "/register", "is_route_dynamic": "false", "method": "post", "request_body": {"id": "number", "username": "string", "password": "string"}, "response": "None"},
+      {"route": "/login", "is_route_dynamic": "false", "method": "post", "request_body": {"username": "string", "password": "string"}, "response": {"token": "string"}},
+      {"route": "/logout", "is_route_dynamic": "false", "method": "post", "request_body": "None", "response": "None", "requires_auth": "true"},
+      {"route": "/task", "is_route_dynamic": "false", "method": "get", "request_body": "None", "response": "None", "requires_auth": "true"},
+      {"route": "/health", "is_route_dynamic": "false", "method": "get", "request_body": "None", "response": "None"}
+    ]"#;
+
+    #[tokio::test]
+    async fn tests_check_protected_routes() {
+        let routes: Vec<RouteObject> = serde_json::from_str(ROUTES).unwrap();
+        let auth: AuthRoutes = auth_routes(&routes).unwrap();
+        assert_eq!(auth.protected, vec!["/task".to_string()]);
+
+        let credentials: Value = new_credentials(auth.register.request_body.as_ref());
+        assert_eq!(credentials["password"], AUTH_CHECK_PASSWORD);
+        assert!(credentials["username"]
+            .as_str()
+            .unwrap()
+            .starts_with("auth_check_"));
+
+        let server: StubServer = StubServer::start(vec![
+            ("/register", vec![StubResponse::new(200, "")]),
+            (
+                "/login",
+                vec![StubResponse::json(200, r#"{"token": "abc"}"#)],
+            ),
+            (
+                "/task",
+                vec![
+                    StubResponse::new(401, ""),
+                    StubResponse::json(200, "[]"),
+                    StubResponse::new(401, ""),
+                ],
+            ),
+            ("/logout", vec![StubResponse::new(200, "")]),
+        ])
+        .await;
+
+        let checks: Vec<AuthCheck> =
+            check_protected_routes(&Client::new(), &server.url(""), &auth, &credentials).await;
+        let routes: Vec<&str> = checks.iter().map(|check| check.route.as_str()).collect();
+        assert_eq!(
+            routes,
+            vec![
+                "/task without a token",
+                "/task with a token",
+                "/logout",
+                "/task after logout"
+            ]
+        );
+        assert!(checks.iter().all(|check| check.passed), "{:?}", checks);
+        assert_eq!(token_from_response("Logged in successfully!"), None);
+    }
+}
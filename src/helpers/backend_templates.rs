@@ -0,0 +1,561 @@
+use crate::helpers::run_context::{current_backend_storage, current_backend_template};
+use crate::models::agents::agent_traits::{FactSheet, ProjectScope};
+use dotenv::dotenv;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framework {
+    ActixWeb,
+    Axum,
+}
+
+impl Framework {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ActixWeb => "actix-web",
+            Self::Axum => "axum",
+        }
+    }
+
+    pub fn auth_module(&self) -> AuthModule {
+        match self {
+            Self::ActixWeb => ACTIX_AUTH,
+            Self::Axum => AXUM_AUTH,
+        }
+    }
+
+    // How the framework writes a route with a path param
+    pub fn route_syntax(&self) -> &'static str {
+        match self {
+            Self::ActixWeb => "/task/{id}",
+            Self::Axum => "/task/:id",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storage {
+    JsonFile,
+    Sqlite,
+}
+
+impl Storage {
+    pub const ALL: [Storage; 2] = [Storage::JsonFile, Storage::Sqlite];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::JsonFile => "json",
+            Self::Sqlite => "sqlite",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::JsonFile => "JSON file database.json",
+            Self::Sqlite => {
+                "SQLite file database.sqlite, the tables come from the SQL files in migrations/ and the server applies them at startup"
+            }
+        }
+    }
+}
+
+// Password hashing, session tokens and the middleware guarding protected routes,
+// written into the generated project as src/auth.rs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthModule {
+    pub allowed_crates: &'static [&'static str],
+    pub dependencies: &'static [&'static str],
+    pub source: &'static str,
+}
+
+const AUTH_CRATES: [&str; 3] = ["argon2", "rand_core", "uuid"];
+
+const AUTH_DEPENDENCIES: [&str; 3] = [
+    r#"argon2 = "0.5""#,
+    r#"rand_core = { version = "0.6", features = ["getrandom"] }"#,
+    r#"uuid = { version = "1", features = ["v4"] }"#,
+];
+
+pub const ACTIX_AUTH: AuthModule = AuthModule {
+    allowed_crates: &AUTH_CRATES,
+    dependencies: &AUTH_DEPENDENCIES,
+    source: include_str!("../templates/auth_actix.rs"),
+};
+
+pub const AXUM_AUTH: AuthModule = AuthModule {
+    allowed_crates: &AUTH_CRATES,
+    dependencies: &AUTH_DEPENDENCIES,
+    source: include_str!("../templates/auth_axum.rs"),
+};
+
+// What the generated code must do with src/auth.rs, the endpoint tests rely on it
+pub const AUTH_CONTRACT: &str = "src/auth.rs is already in the project, declare it with `mod auth;` and do not change it. Store passwords hashed with auth::hash_password and check them with auth::verify_password. POST /register takes a username and password, POST /login answers {\"token\": \"<session token>\"}, POST /logout is auth::logout. Every other route sits behind auth::require_session, so clients send Authorization: Bearer <token>.";
+
+// How the backend developer builds and starts the generated server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestHarness {
+    pub build: &'static [&'static str],
+    pub run: &'static [&'static str],
+    // Time the server gets to start listening before its endpoints are tested
+    pub startup_secs: u64,
+}
+
+const CARGO_HARNESS: TestHarness = TestHarness {
+    build: &["cargo", "build"],
+    run: &["cargo", "run"],
+    startup_secs: 5,
+};
+
+// Reference server the LLM writes the backend from, with what the generated code may rely on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackendTemplate {
+    pub name: &'static str,
+    pub framework: Framework,
+    pub storage: Storage,
+    // The only crates the generated code may use
+    pub allowed_crates: &'static [&'static str],
+    // Cargo.toml lines the generated project needs under [dependencies]
+    pub dependencies: &'static [&'static str],
+    pub port_env_var: &'static str,
+    pub default_port: u16,
+    pub harness: TestHarness,
+    pub source: &'static str,
+    // Set when the project has user login and logout
+    pub auth: Option<AuthModule>,
+}
+
+impl BackendTemplate {
+    pub fn with_auth(&self) -> BackendTemplate {
+        BackendTemplate {
+            auth: Some(self.framework.auth_module()),
+            ..*self
+        }
+    }
+
+    pub fn allowed_crates(&self) -> Vec<&'static str> {
+        let auth_crates: &[&str] = self.auth.map_or(&[], |auth| auth.allowed_crates);
+        [self.allowed_crates, auth_crates].concat()
+    }
+
+    pub fn dependencies(&self) -> Vec<&'static str> {
+        let auth_dependencies: &[&str] = self.auth.map_or(&[], |auth| auth.dependencies);
+        [self.dependencies, auth_dependencies].concat()
+    }
+
+    // Framework notes for every LLM call that writes or reads the backend code
+    pub fn prompt_context(&self) -> String {
+        let mut context: String = format!(
+            "FRAMEWORK: {} \n STORAGE: {} \n ALLOWED_CRATES: {} \n ROUTE_SYNTAX: {} \n PORT: read from the {} env var, {} by default \n",
+            self.framework.name(),
+            self.storage.description(),
+            self.allowed_crates().join(", "),
+            self.framework.route_syntax(),
+            self.port_env_var,
+            self.default_port
+        );
+        if self.auth.is_some() {
+            context = format!("{} AUTH: {} \n", context, AUTH_CONTRACT);
+        }
+        context
+    }
+
+    // The template of the same framework with this storage, an error when there is none
+    pub fn with_storage(&self, storage: Storage) -> Result<BackendTemplate, String> {
+        if self.storage == storage {
+            return Ok(*self);
+        }
+        BACKEND_TEMPLATES
+            .into_iter()
+            .find(|template| template.framework == self.framework && template.storage == storage)
+            .ok_or(format!(
+                "The {} templates do not support {} storage",
+                self.framework.name(),
+                storage.name()
+            ))
+    }
+
+    // Template dependencies a Cargo.toml does not declare yet
+    pub fn missing_dependencies(&self, cargo_toml: &str) -> Vec<&'static str> {
+        self.dependencies()
+            .into_iter()
+            .filter(|dependency| {
+                let krate: &str = dependency.split('=').next().unwrap_or("").trim();
+                !cargo_toml
+                    .lines()
+                    .any(|line| line.split('=').next().map(|key| key.trim()) == Some(krate))
+            })
+            .collect()
+    }
+}
+
+const COMMON_DEPENDENCIES: [&str; 5] = [
+    r#"async-trait = "0.1""#,
+    r#"reqwest = { version = "0.11", features = ["json"] }"#,
+    r#"serde = { version = "1", features = ["derive"] }"#,
+    r#"serde_json = "1""#,
+    r#"tokio = { version = "1", features = ["full"] }"#,
+];
+
+pub const ACTIX_JSON: BackendTemplate = BackendTemplate {
+    name: "actix_json",
+    framework: Framework::ActixWeb,
+    storage: Storage::JsonFile,
+    allowed_crates: &[
+        "reqwest",
+        "serde",
+        "serde_json",
+        "tokio",
+        "actix-web",
+        "async-trait",
+        "actix-cors",
+    ],
+    dependencies: &[
+        COMMON_DEPENDENCIES[0],
+        COMMON_DEPENDENCIES[1],
+        COMMON_DEPENDENCIES[2],
+        COMMON_DEPENDENCIES[3],
+        COMMON_DEPENDENCIES[4],
+        r#"actix-web = "4""#,
+        r#"actix-cors = "0.6""#,
+    ],
+    port_env_var: "PORT",
+    default_port: 8080,
+    harness: CARGO_HARNESS,
+    source: include_str!("../code_template.rs"),
+    auth: None,
+};
+
+pub const AXUM_JSON: BackendTemplate = BackendTemplate {
+    name: "axum_json",
+    framework: Framework::Axum,
+    storage: Storage::JsonFile,
+    allowed_crates: &[
+        "reqwest",
+        "serde",
+        "serde_json",
+        "tokio",
+        "axum",
+        "async-trait",
+        "tower-http",
+    ],
+    dependencies: &[
+        COMMON_DEPENDENCIES[0],
+        COMMON_DEPENDENCIES[1],
+        COMMON_DEPENDENCIES[2],
+        COMMON_DEPENDENCIES[3],
+        COMMON_DEPENDENCIES[4],
+        r#"axum = "0.6""#,
+        r#"tower-http = { version = "0.4", features = ["cors"] }"#,
+    ],
+    port_env_var: "PORT",
+    default_port: 8080,
+    harness: CARGO_HARNESS,
+    source: include_str!("../templates/axum_json.rs"),
+    auth: None,
+};
+
+pub const ACTIX_SQLITE: BackendTemplate = BackendTemplate {
+    name: "actix_sqlite",
+    framework: Framework::ActixWeb,
+    storage: Storage::Sqlite,
+    allowed_crates: &[
+        "reqwest",
+        "serde",
+        "serde_json",
+        "tokio",
+        "actix-web",
+        "async-trait",
+        "actix-cors",
+        "rusqlite",
+    ],
+    dependencies: &[
+        COMMON_DEPENDENCIES[0],
+        COMMON_DEPENDENCIES[1],
+        COMMON_DEPENDENCIES[2],
+        COMMON_DEPENDENCIES[3],
+        COMMON_DEPENDENCIES[4],
+        r#"actix-web = "4""#,
+        r#"actix-cors = "0.6""#,
+        r#"rusqlite = { version = "0.29", features = ["bundled"] }"#,
+    ],
+    port_env_var: "PORT",
+    default_port: 8080,
+    harness: CARGO_HARNESS,
+    source: include_str!("../templates/actix_sqlite.rs"),
+    auth: None,
+};
+
+pub const BACKEND_TEMPLATES: [BackendTemplate; 3] = [ACTIX_JSON, AXUM_JSON, ACTIX_SQLITE];
+
+pub fn backend_template_by_name(name: &str) -> Option<BackendTemplate> {
+    BACKEND_TEMPLATES
+        .into_iter()
+        .find(|template| template.name == name)
+}
+
+// Accounts need storage that survives restarts, API proxies suit axum, the rest starts from actix
+pub fn select_backend_template(scope: Option<&ProjectScope>) -> BackendTemplate {
+    match scope {
+        Some(scope) if scope.is_crud_required && scope.is_user_login_and_logout => ACTIX_SQLITE,
+        Some(scope) if scope.is_external_urls_required && !scope.is_crud_required => AXUM_JSON,
+        _ => ACTIX_JSON,
+    }
+}
+
+// BACKEND_TEMPLATE, e.g. "axum_json", forces a template. "auto" picks one from the project scope.
+pub fn parse_backend_template(name: &str) -> Result<Option<BackendTemplate>, String> {
+    match name.trim() {
+        "auto" | "" => Ok(None),
+        name => backend_template_by_name(name).map(Some).ok_or(format!(
+            "Unknown backend template '{}' in BACKEND_TEMPLATE, expected one of {} or auto",
+            name,
+            BACKEND_TEMPLATES
+                .iter()
+                .map(|template| template.name)
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )),
+    }
+}
+
+pub fn backend_template_from_env() -> Result<Option<BackendTemplate>, String> {
+    dotenv().ok();
+
+    match env::var("BACKEND_TEMPLATE") {
+        Ok(name) => parse_backend_template(&name),
+        Err(_) => Ok(None),
+    }
+}
+
+// BACKEND_STORAGE, "json" or "sqlite". "auto" keeps the storage of the template.
+pub fn parse_backend_storage(name: &str) -> Result<Option<Storage>, String> {
+    match name.trim() {
+        "auto" | "" => Ok(None),
+        name => Storage::ALL
+            .into_iter()
+            .find(|storage| storage.name() == name)
+            .map(Some)
+            .ok_or(format!(
+                "Unknown backend storage '{}' in BACKEND_STORAGE, expected json, sqlite or auto",
+                name
+            )),
+    }
+}
+
+pub fn backend_storage_from_env() -> Result<Option<Storage>, String> {
+    dotenv().ok();
+
+    match env::var("BACKEND_STORAGE") {
+        Ok(name) => parse_backend_storage(&name),
+        Err(_) => Ok(None),
+    }
+}
+
+// The factsheet keeps the choice, so later steps and resumed runs stay on one framework
+pub fn resolve_backend_template(factsheet: &mut FactSheet) -> Result<BackendTemplate, String> {
+    let recorded: Option<BackendTemplate> = factsheet
+        .backend_template
+        .as_deref()
+        .and_then(backend_template_by_name);
+    let template: BackendTemplate = match (recorded, current_backend_template()) {
+        (Some(template), _) => template,
+        // A forced template keeps its framework, the storage has to exist for it
+        (None, Some(template)) => match current_backend_storage() {
+            Some(storage) => template.with_storage(storage)?,
+            None => template,
+        },
+        // The scope only suggests a framework, the storage may pick another one
+        (None, None) => {
+            let template: BackendTemplate =
+                select_backend_template(factsheet.project_scope.as_ref());
+            match current_backend_storage() {
+                Some(storage) => template.with_storage(storage).or_else(|e| {
+                    BACKEND_TEMPLATES
+                        .into_iter()
+                        .find(|template| template.storage == storage)
+                        .ok_or(e)
+                })?,
+                None => template,
+            }
+        }
+    };
+    factsheet.backend_template = Some(template.name.to_string());
+
+    // Login and logout bring in the auth module of the framework
+    match factsheet.project_scope {
+        Some(scope) if scope.is_user_login_and_logout => Ok(template.with_auth()),
+        _ => Ok(template),
+    }
+}
+
+// Add what the template needs to the generated project's Cargo.toml, returns the added lines
+pub fn add_missing_dependencies(
+    template: &BackendTemplate,
+    project_dir: &Path,
+) -> Result<Vec<&'static str>, String> {
+    let cargo_path: PathBuf = project_dir.join("Cargo.toml");
+    let cargo_toml: String = fs::read_to_string(&cargo_path)
+        .map_err(|e| format!("Could not read {}: {}", cargo_path.display(), e))?;
+    let missing: Vec<&'static str> = template.missing_dependencies(&cargo_toml);
+    if missing.is_empty() {
+        return Ok(missing);
+    }
+
+    let mut lines: Vec<String> = cargo_toml.lines().map(|line| line.to_string()).collect();
+    let added: Vec<String> = missing.iter().map(|line| line.to_string()).collect();
+    match lines
+        .iter()
+        .position(|line| line.trim() == "[dependencies]")
+    {
+        Some(section) => {
+            lines.splice(section + 1..section + 1, added);
+        }
+        None => {
+            lines.push(String::new());
+            lines.push("[dependencies]".to_string());
+            lines.extend(added);
+        }
+    }
+    fs::write(&cargo_path, lines.join("\n") + "\n")
+        .map_err(|e| format!("Could not write {}: {}", cargo_path.display(), e))?;
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::run_context::{with_run_context, RunContext};
+
+    fn scope(crud: bool, login: bool, external_urls: bool) -> ProjectScope {
+        ProjectScope {
+            is_crud_required: crud,
+            is_user_login_and_logout: login,
+            is_external_urls_required: external_urls,
+        }
+    }
+
+    #[test]
+    fn tests_select_backend_template() {
+        assert_eq!(select_backend_template(None), ACTIX_JSON);
+        assert_eq!(
+            select_backend_template(Some(&scope(true, true, false))),
+            ACTIX_SQLITE
+        );
+        assert_eq!(
+            select_backend_template(Some(&scope(false, false, true))),
+            AXUM_JSON
+        );
+        assert_eq!(
+            select_backend_template(Some(&scope(true, false, true))),
+            ACTIX_JSON
+        );
+
+        assert!(AXUM_JSON.with_storage(Storage::Sqlite).is_err());
+        assert_eq!(ACTIX_JSON.with_storage(Storage::Sqlite), Ok(ACTIX_SQLITE));
+        assert_eq!(ACTIX_SQLITE.with_storage(Storage::JsonFile), Ok(ACTIX_JSON));
+
+        assert_eq!(
+            parse_backend_template("axum_json").unwrap(),
+            Some(AXUM_JSON)
+        );
+        assert_eq!(
+            parse_backend_storage("sqlite").unwrap(),
+            Some(Storage::Sqlite)
+        );
+        assert!(parse_backend_storage("postgres").is_err());
+        assert_eq!(parse_backend_template("auto").unwrap(), None);
+        assert!(parse_backend_template("rocket").is_err());
+    }
+
+    #[test]
+    fn tests_login_scope_adds_auth_module() {
+        let mut factsheet: FactSheet = serde_json::from_str(
+            r#"{
+              "project_description": "Notes app with accounts",
+              "project_scope": {
+                "is_crud_required": false,
+                "is_user_login_and_logout": true,
+                "is_external_urls_required": false
+              }
+            }"#,
+        )
+        .unwrap();
+
+        let template: BackendTemplate = resolve_backend_template(&mut factsheet).unwrap();
+        assert_eq!(template, ACTIX_JSON.with_auth());
+        assert_eq!(factsheet.backend_template.as_deref(), Some("actix_json"));
+        assert!(template.prompt_context().contains(AUTH_CONTRACT));
+        assert!(template.allowed_crates().contains(&"argon2"));
+        assert!(!ACTIX_JSON.prompt_context().contains("AUTH:"));
+    }
+
+    #[tokio::test]
+    async fn tests_storage_keeps_forced_framework() {
+        let resolve = |backend_template: Option<BackendTemplate>| {
+            let context: RunContext = RunContext {
+                backend_template,
+                backend_storage: Some(Storage::Sqlite),
+                print_to_terminal: false,
+                ..RunContext::default()
+            };
+            let mut factsheet: FactSheet = serde_json::from_str(
+                r#"{
+                  "project_description": "Weather proxy",
+                  "project_scope": {
+                    "is_crud_required": false,
+                    "is_user_login_and_logout": false,
+                    "is_external_urls_required": true
+                  }
+                }"#,
+            )
+            .unwrap();
+            with_run_context(
+                context,
+                async move { resolve_backend_template(&mut factsheet) },
+            )
+        };
+
+        // Only actix has SQLite, a forced axum template is not swapped for it
+        assert!(resolve(Some(AXUM_JSON)).await.is_err());
+        assert_eq!(resolve(None).await, Ok(ACTIX_SQLITE));
+    }
+
+    #[test]
+    fn tests_templates_cover_their_framework() {
+        for template in BACKEND_TEMPLATES {
+            assert!(template.source.contains(template.framework.route_syntax()));
+            assert!(template.source.contains(template.port_env_var));
+            let template: BackendTemplate = template.with_auth();
+            assert!(template.allowed_crates().iter().all(|krate| template
+                .dependencies()
+                .iter()
+                .any(|dependency| dependency.starts_with(&format!("{} =", krate)))));
+        }
+    }
+
+    #[test]
+    fn tests_add_missing_dependencies() {
+        let project_dir: PathBuf =
+            env::temp_dir().join(format!("template_deps_{}", std::process::id()));
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(
+            project_dir.join("Cargo.toml"),
+            "[package]\nname = \"web\"\n\n[dependencies]\nserde_json = \"1.0\"\nactix-web = \"4.3\"\n",
+        )
+        .unwrap();
+
+        let added: Vec<&str> = add_missing_dependencies(&ACTIX_SQLITE, &project_dir).unwrap();
+        assert_eq!(added.len(), ACTIX_SQLITE.dependencies.len() - 2);
+        assert!(added.iter().any(|line| line.starts_with("rusqlite")));
+
+        let cargo_toml: String = fs::read_to_string(project_dir.join("Cargo.toml")).unwrap();
+        assert!(cargo_toml.contains("actix-web = \"4.3\""));
+        assert!(ACTIX_SQLITE.missing_dependencies(&cargo_toml).is_empty());
+        assert!(add_missing_dependencies(&ACTIX_SQLITE, &project_dir)
+            .unwrap()
+            .is_empty());
+        let _ = fs::remove_dir_all(&project_dir);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::helpers::command_line::get_user_response;
+use async_trait::async_trait;
+use dotenv::dotenv;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Clarifying questions beyond this are dropped
+pub const MAX_CLARIFYING_QUESTIONS: usize = 5;
+
+// Recorded for questions the user left open, the requirements then list an assumption
+pub const NO_ANSWER: &str = "No answer, use your best judgement";
+
+// Answers the clarifying questions about a request, in the order asked
+#[async_trait]
+pub trait Clarifier: Send + Sync {
+    async fn answer(&self, questions: &[String]) -> Vec<String>;
+}
+
+// Asks each question on the terminal
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerminalClarifier;
+
+#[async_trait]
+impl Clarifier for TerminalClarifier {
+    async fn answer(&self, questions: &[String]) -> Vec<String> {
+        let questions: Vec<String> = questions.to_vec();
+        tokio::task::spawn_blocking(move || {
+            questions
+                .iter()
+                .map(|question| get_user_response(question))
+                .collect()
+        })
+        .await
+        .unwrap_or_default()
+    }
+}
+
+// Non-interactive runs read the answers from a JSON list, e.g. ["yes", "only admins", ""]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnswersFile {
+    answers: Vec<String>,
+}
+
+impl AnswersFile {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents: String = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read answers {}: {}", path.display(), e))?;
+        let answers: Vec<String> = serde_json::from_str(&contents).map_err(|e| {
+            format!(
+                "Answers {} must be a JSON list of strings: {}",
+                path.display(),
+                e
+            )
+        })?;
+        Ok(Self { answers })
+    }
+}
+
+#[async_trait]
+impl Clarifier for AnswersFile {
+    async fn answer(&self, questions: &[String]) -> Vec<String> {
+        self.answers.iter().take(questions.len()).cloned().collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClarificationMode {
+    Off,
+    Interactive,
+    AnswersFile(PathBuf),
+}
+
+// CLARIFICATION_ANSWERS=<file> answers from a file, CLARIFY=off skips the questions
+pub fn clarification_mode_from_env() -> Result<ClarificationMode, String> {
+    dotenv().ok();
+
+    if let Ok(path) = env::var("CLARIFICATION_ANSWERS") {
+        return Ok(ClarificationMode::AnswersFile(PathBuf::from(path)));
+    }
+    match env::var("CLARIFY").as_deref() {
+        Err(_) | Ok("on") => Ok(ClarificationMode::Interactive),
+        Ok("off") => Ok(ClarificationMode::Off),
+        Ok(other) => Err(format!("Unknown CLARIFY {}, expected on or off", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tests_answers_file() {
+        let path: PathBuf = env::temp_dir().join(format!("answers_{}.json", std::process::id()));
+        fs::write(&path, r#"["yes", "only admins", "daily"]"#).unwrap();
+
+        let answers: AnswersFile = AnswersFile::load(&path).unwrap();
+        let questions: Vec<String> =
+            vec!["Multiple users?".to_string(), "Who can edit?".to_string()];
+        assert_eq!(
+            answers.answer(&questions).await,
+            vec!["yes".to_string(), "only admins".to_string()]
+        );
+
+        fs::write(&path, r#"{"Multiple users?": "yes"}"#).unwrap();
+        assert!(AnswersFile::load(&path).is_err());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,10 +1,14 @@
+use crate::helpers::events::{publish, AgentEvent};
+use crate::helpers::review::{ReviewDecision, ReviewGate, ReviewRequest};
 use crossterm::{
     style::{Color, ResetColor, SetForegroundColor},
     ExecutableCommand,
 };
-use std::io::{stdin, stdout};
+use serde::Serialize;
+use std::io::{stdin, stdout, Write};
+use std::time::Duration;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Serialize)]
 pub enum PrintCommand {
     AICall,
     UnitTest,
@@ -12,7 +16,16 @@ pub enum PrintCommand {
 }
 
 impl PrintCommand {
+    // Published to the run's observers, the terminal is one of them
     pub fn print_agent_message(&self, agent_pos: &str, agent_statement: &str) {
+        publish(AgentEvent::Message {
+            agent: agent_pos.to_string(),
+            kind: self.clone(),
+            message: agent_statement.to_string(),
+        });
+    }
+
+    pub fn print_to_terminal(&self, agent_pos: &str, agent_statement: &str) {
         let mut stdout: std::io::Stdout = stdout();
 
         // Decide on the print color
@@ -35,13 +48,42 @@ impl PrintCommand {
     }
 }
 
+// Print a streamed token as soon as it arrives
+pub fn print_stream_token(token: &str) {
+    let mut stdout: std::io::Stdout = stdout();
+    stdout.execute(SetForegroundColor(Color::DarkCyan)).unwrap();
+    print!("{}", token);
+    stdout.execute(ResetColor).unwrap();
+    stdout.flush().unwrap();
+}
+
+// Overwrite a single progress line while a completion streams in
+pub fn print_stream_progress(agent_pos: &str, received_chunks: usize, elapsed: Duration) {
+    let mut stdout: std::io::Stdout = stdout();
+    stdout.execute(SetForegroundColor(Color::Green)).unwrap();
+    print!("\rAgent: {}: ", agent_pos);
+    stdout.execute(SetForegroundColor(Color::DarkCyan)).unwrap();
+    print!(
+        "receiving... {} chunks in {:.1}s",
+        received_chunks,
+        elapsed.as_secs_f32()
+    );
+    stdout.execute(ResetColor).unwrap();
+    stdout.flush().unwrap();
+}
+
+// Finish the streamed output line
+pub fn print_stream_end() {
+    println!();
+}
+
 // Get user request
 pub fn get_user_response(question: &str) -> String {
     let mut stdout: std::io::Stdout = stdout();
 
     // Print the question in a specific color
     stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
-    println!("");
+    println!();
     println!("{}", question);
 
     // Reset Color
@@ -54,27 +96,34 @@ pub fn get_user_response(question: &str) -> String {
         .expect("Failed to read response");
 
     // Trim whitespace and return
-    return user_response.trim().to_string();
+    user_response.trim().to_string()
 }
 
-// Get user response that code is safe to execute
-pub fn confirm_safe_code() -> bool {
+// Get the user's decision at a review gate, with free-text feedback as the third option
+pub fn get_review_decision(request: &ReviewRequest) -> ReviewDecision {
     let mut stdout: std::io::Stdout = stdout();
     loop {
         // Print the question in specified color
         stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
-        println!("");
-        print!("WARNING: You are about to run code written entirely by AI. ");
-        println!("Review your code and confirm you wish to continue.");
+        println!();
+        if request.gate == ReviewGate::RunServer {
+            print!("WARNING: You are about to run code written entirely by AI. ");
+            println!("Review your code and confirm you wish to continue.");
+        } else {
+            println!("Review from {}: {}", request.agent, request.gate.title());
+        }
 
         // Reset Color
         stdout.execute(ResetColor).unwrap();
+        println!("{}", request.summary);
 
         // Present Options with different colors
         stdout.execute(SetForegroundColor(Color::Green)).unwrap();
         println!("[1] All good");
         stdout.execute(SetForegroundColor(Color::DarkRed)).unwrap();
         println!("[2] Lets stop this project");
+        stdout.execute(SetForegroundColor(Color::Yellow)).unwrap();
+        println!("[3] Send feedback to the agent");
 
         // Reset Color
         stdout.execute(ResetColor).unwrap();
@@ -90,10 +139,16 @@ pub fn confirm_safe_code() -> bool {
 
         // Match response
         match human_response.as_str() {
-            "1" | "ok" | "y" => return true,
-            "2" | "no" | "n" => return false,
+            "1" | "ok" | "y" => return ReviewDecision::Approve,
+            "2" | "no" | "n" => return ReviewDecision::Reject,
+            "3" | "f" => {
+                let feedback: String = get_user_response("What should the agent change?");
+                if !feedback.is_empty() {
+                    return ReviewDecision::Feedback(feedback);
+                }
+            }
             _ => {
-                println!("Invalid input. Please select '1' or '2'")
+                println!("Invalid input. Please select '1', '2' or '3'")
             }
         }
     }
@@ -108,4 +163,4 @@ mod tests {
         PrintCommand::AICall
             .print_agent_message("Managing Agent", "Testing testing, processing something");
     }
-}
\ No newline at end of file
+}
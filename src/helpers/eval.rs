@@ -0,0 +1,575 @@
+use crate::ai_functions::ai_function_by_name;
+use crate::apis::llm_config::LLMConfig;
+use crate::apis::provider::{CassetteProvider, LLMProvider, OpenAIProvider};
+use crate::helpers::general::copy_project;
+use crate::helpers::openapi::openapi_path;
+use crate::helpers::prompt_library::{PromptLibrary, RenderedPrompt};
+use crate::models::general::api_schema::PathTemplate;
+use crate::models::general::llm::{CompletionParams, LLMResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Agent position used to resolve model parameters for eval runs
+const EVAL_AGENT_POSITION: &str = "Eval";
+
+// A property the ai_function output must have
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum Expectation {
+    // Output parses as JSON
+    ValidJson,
+    // JSON value at a dotted path, i.e. "is_crud_required" or "0.method"
+    Equals {
+        path: String,
+        value: Value,
+    },
+    // An endpoint with this route (and method, if given) is listed
+    RouteExists {
+        route: String,
+        #[serde(default)]
+        method: Option<String>,
+    },
+    Contains {
+        text: String,
+    },
+    // Output builds as the main.rs of the compile project
+    Compiles,
+}
+
+impl Expectation {
+    fn describe(&self) -> String {
+        match self {
+            Self::ValidJson => "valid json".to_string(),
+            Self::Equals { path, value } => format!("{} == {}", path, value),
+            Self::RouteExists { route, method } => match method {
+                Some(method) => format!("route {} {} exists", method.to_uppercase(), route),
+                None => format!("route {} exists", route),
+            },
+            Self::Contains { text } => format!("contains {:?}", text),
+            Self::Compiles => "compiles".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub ai_function: String,
+    // Inline input, or a file relative to the scenario
+    #[serde(default)]
+    pub input: String,
+    #[serde(default)]
+    pub input_file: Option<String>,
+    pub expect: Vec<Expectation>,
+}
+
+// Every *.json scenario in the suite directory, sorted by file name
+pub fn load_suite(dir: &Path) -> Result<Vec<Scenario>, String> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("{}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+        .collect();
+    paths.sort();
+
+    let mut scenarios: Vec<Scenario> = vec![];
+    for path in paths {
+        let raw: String =
+            fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut scenario: Scenario =
+            serde_json::from_str(&raw).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if ai_function_by_name(&scenario.ai_function).is_none() {
+            return Err(format!(
+                "{}: no ai_function named {}",
+                path.display(),
+                scenario.ai_function
+            ));
+        }
+        if let Some(input_file) = &scenario.input_file {
+            let input_path: PathBuf = dir.join(input_file);
+            scenario.input = fs::read_to_string(&input_path)
+                .map_err(|e| format!("{}: {}", input_path.display(), e))?;
+        }
+        scenarios.push(scenario);
+    }
+    Ok(scenarios)
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CheckResult {
+    pub check: String,
+    pub passed: bool,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ScenarioResult {
+    pub scenario: String,
+    pub prompt_version: String,
+    pub passed: usize,
+    pub total: usize,
+    pub checks: Vec<CheckResult>,
+    pub error: Option<String>,
+}
+
+// One prompt library run against the whole suite
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct VariantReport {
+    pub label: String,
+    pub score: f64,
+    pub results: Vec<ScenarioResult>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct EvalReport {
+    pub provider: String,
+    pub variants: Vec<VariantReport>,
+}
+
+impl EvalReport {
+    // Scenario by variant score table
+    pub fn to_table(&self) -> String {
+        let mut lines: Vec<String> = vec![format!("Eval report ({})", self.provider)];
+        let mut header: String = format!("  {:<36}", "scenario");
+        for variant in &self.variants {
+            header.push_str(&format!(" {:>24}", variant.label));
+        }
+        lines.push(header);
+
+        let scenario_names: Vec<&String> = self
+            .variants
+            .first()
+            .map(|variant| {
+                variant
+                    .results
+                    .iter()
+                    .map(|result| &result.scenario)
+                    .collect()
+            })
+            .unwrap_or_default();
+        for (i, name) in scenario_names.iter().enumerate() {
+            let mut row: String = format!("  {:<36}", name);
+            for variant in &self.variants {
+                let result: &ScenarioResult = &variant.results[i];
+                let cell: String = match &result.error {
+                    Some(_) => "error".to_string(),
+                    None => format!("{}/{}", result.passed, result.total),
+                };
+                row.push_str(&format!(" {:>24}", cell));
+            }
+            lines.push(row);
+        }
+
+        let mut score_row: String = format!("  {:<36}", "score");
+        for variant in &self.variants {
+            score_row.push_str(&format!(" {:>23.1}%", variant.score * 100.0));
+        }
+        lines.push(score_row);
+
+        // Spell out what failed so a regression is easy to trace
+        for variant in &self.variants {
+            for result in &variant.results {
+                if let Some(error) = &result.error {
+                    lines.push(format!(
+                        "  [{}] {}: {}",
+                        variant.label, result.scenario, error
+                    ));
+                }
+                for check in result.checks.iter().filter(|check| !check.passed) {
+                    lines.push(format!(
+                        "  [{}] {} ({}): failed {}{}",
+                        variant.label,
+                        result.scenario,
+                        result.prompt_version,
+                        check.check,
+                        check
+                            .detail
+                            .as_ref()
+                            .map_or(String::new(), |detail| format!(" - {}", detail))
+                    ));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+// Run every scenario once per prompt library variant
+pub async fn run_suite(
+    scenarios: &Vec<Scenario>,
+    variants: &Vec<(String, PromptLibrary)>,
+    provider: &dyn LLMProvider,
+    config: &LLMConfig,
+    compile_project: Option<&Path>,
+) -> EvalReport {
+    let mut reports: Vec<VariantReport> = vec![];
+    for (label, library) in variants {
+        let mut results: Vec<ScenarioResult> = vec![];
+        for scenario in scenarios {
+            let ai_func: fn(&str) -> &'static str = match ai_function_by_name(&scenario.ai_function)
+            {
+                Some(ai_func) => ai_func,
+                None => continue,
+            };
+            let prompt: RenderedPrompt = library.render(
+                &scenario.ai_function,
+                ai_func(&scenario.input),
+                &scenario.input,
+            );
+            let params: CompletionParams =
+                config.resolve(EVAL_AGENT_POSITION, &scenario.ai_function);
+
+            let mut result: ScenarioResult = ScenarioResult {
+                scenario: scenario.name.clone(),
+                prompt_version: prompt.version.clone(),
+                passed: 0,
+                total: scenario.expect.len(),
+                checks: vec![],
+                error: None,
+            };
+            match provider
+                .complete(&scenario.ai_function, vec![prompt.message], &params)
+                .await
+            {
+                Ok(llm_response) => {
+                    result.checks =
+                        evaluate_output(&llm_response, &scenario.expect, compile_project);
+                    result.passed = result.checks.iter().filter(|check| check.passed).count();
+                }
+                Err(e) => result.error = Some(e.to_string()),
+            }
+            results.push(result);
+        }
+
+        let total: usize = results.iter().map(|result| result.total).sum();
+        let passed: usize = results.iter().map(|result| result.passed).sum();
+        reports.push(VariantReport {
+            label: label.clone(),
+            score: if total > 0 {
+                passed as f64 / total as f64
+            } else {
+                0.0
+            },
+            results,
+        });
+    }
+
+    EvalReport {
+        provider: provider.name(),
+        variants: reports,
+    }
+}
+
+pub fn evaluate_output(
+    llm_response: &LLMResponse,
+    expectations: &[Expectation],
+    compile_project: Option<&Path>,
+) -> Vec<CheckResult> {
+    let parsed: Result<Value, serde_json::Error> = serde_json::from_str(&llm_response.content);
+    expectations
+        .iter()
+        .map(|expectation| {
+            let outcome: Result<(), String> = match expectation {
+                Expectation::ValidJson => parsed.as_ref().map(|_| ()).map_err(|e| e.to_string()),
+                Expectation::Equals { path, value } => match &parsed {
+                    Ok(json) => match json_at_path(json, path) {
+                        Some(found) if found == value => Ok(()),
+                        Some(found) => Err(format!("found {}", found)),
+                        None => Err("path not found".to_string()),
+                    },
+                    Err(e) => Err(format!("output is not json: {}", e)),
+                },
+                Expectation::RouteExists { route, method } => match &parsed {
+                    Ok(json) => check_route_exists(json, route, method.as_deref()),
+                    Err(e) => Err(format!("output is not json: {}", e)),
+                },
+                Expectation::Contains { text } => {
+                    if llm_response.content.contains(text.as_str()) {
+                        Ok(())
+                    } else {
+                        Err("text not found".to_string())
+                    }
+                }
+                Expectation::Compiles => match compile_project {
+                    Some(project) => compile_check(project, &llm_response.content),
+                    None => Err("no compile project configured".to_string()),
+                },
+            };
+            CheckResult {
+                check: expectation.describe(),
+                passed: outcome.is_ok(),
+                detail: outcome.err(),
+            }
+        })
+        .collect()
+}
+
+// Dotted path lookup, numeric parts index into arrays
+fn json_at_path<'a>(json: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|part| !part.is_empty())
+        .try_fold(json, |current, part| match current {
+            Value::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => current.get(part),
+        })
+}
+
+// Routes compare with actix regex constraints removed, i.e. "/task/{id:\\d+}" matches "/task/{id}"
+fn check_route_exists(json: &Value, route: &str, method: Option<&str>) -> Result<(), String> {
+    let expected: String = openapi_path(&PathTemplate::parse(route));
+    let endpoints: &Vec<Value> = json
+        .as_array()
+        .ok_or("output is not a list of endpoints".to_string())?;
+    let routes: Vec<(String, String)> = endpoints
+        .iter()
+        .filter_map(|endpoint| {
+            let route: &str = endpoint.get("route")?.as_str()?;
+            let method: &str = endpoint.get("method")?.as_str().unwrap_or_default();
+            Some((
+                openapi_path(&PathTemplate::parse(route)),
+                method.to_lowercase(),
+            ))
+        })
+        .collect();
+
+    let found: bool = routes.iter().any(|(route, found_method)| {
+        *route == expected && method.is_none_or(|method| method.to_lowercase() == *found_method)
+    });
+    if found {
+        Ok(())
+    } else {
+        let listed: Vec<String> = routes
+            .iter()
+            .map(|(route, method)| format!("{} {}", method.to_uppercase(), route))
+            .collect();
+        Err(format!("listed: {}", listed.join(", ")))
+    }
+}
+
+// Cargo check the code as main.rs of a throwaway copy of the project, so the project itself is
+// never touched. The project's target directory is shared to keep its dependencies built.
+fn compile_check(project: &Path, code: &str) -> Result<(), String> {
+    static CHECK_COUNT: AtomicU64 = AtomicU64::new(0);
+    let check_dir: PathBuf = env::temp_dir().join(format!(
+        "eval_compile_{}_{}",
+        std::process::id(),
+        CHECK_COUNT.fetch_add(1, Ordering::SeqCst)
+    ));
+    let _ = fs::remove_dir_all(&check_dir);
+    copy_project(project, &check_dir).map_err(|e| format!("{}: {}", project.display(), e))?;
+    let main_path: PathBuf = check_dir.join("src").join("main.rs");
+    let written: std::io::Result<()> =
+        fs::create_dir_all(check_dir.join("src")).and_then(|_| fs::write(&main_path, code));
+
+    let output: Result<std::process::Output, String> = match written {
+        Ok(()) => Command::new("cargo")
+            .arg("check")
+            .arg("--quiet")
+            .current_dir(&check_dir)
+            .env("CARGO_TARGET_DIR", project.join("target"))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(format!("{}: {}", main_path.display(), e)),
+    };
+    let _ = fs::remove_dir_all(&check_dir);
+
+    let output: std::process::Output = output?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        // First error is enough to point at the problem
+        let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+        let first_error: String = stderr
+            .lines()
+            .find(|line| line.starts_with("error"))
+            .unwrap_or("cargo check failed")
+            .to_string();
+        Err(first_error)
+    }
+}
+
+// Handle `eval <suite_dir> [--prompts builtin|<dir>]... [--cassettes <dir> [--record]]
+// [--compile-project <dir>] [--report <file>]` from the command line
+pub async fn eval_command(args: &[String]) -> Result<String, String> {
+    let usage: &str = "Usage: eval <suite_dir> [--prompts builtin|<dir>]... [--cassettes <dir> [--record]] [--compile-project <dir>] [--report <file>]";
+    let mut suite_dir: Option<PathBuf> = None;
+    let mut prompt_sources: Vec<String> = vec![];
+    let mut cassettes: Option<PathBuf> = None;
+    let mut record: bool = false;
+    let mut compile_project: Option<PathBuf> = None;
+    let mut report_path: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().ok_or(usage.to_string());
+        match arg.as_str() {
+            "--prompts" => prompt_sources.push(value()?),
+            "--cassettes" => cassettes = Some(PathBuf::from(value()?)),
+            "--record" => record = true,
+            "--compile-project" => compile_project = Some(PathBuf::from(value()?)),
+            "--report" => report_path = Some(PathBuf::from(value()?)),
+            _ if suite_dir.is_none() && !arg.starts_with("--") => {
+                suite_dir = Some(PathBuf::from(arg))
+            }
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let suite_dir: PathBuf = suite_dir.ok_or(usage.to_string())?;
+    if prompt_sources.is_empty() {
+        prompt_sources.push("builtin".to_string());
+    }
+
+    let scenarios: Vec<Scenario> = load_suite(&suite_dir)?;
+    let mut variants: Vec<(String, PromptLibrary)> = vec![];
+    for source in prompt_sources {
+        let library: PromptLibrary = match source.as_str() {
+            "builtin" => PromptLibrary::builtin(),
+            dir => PromptLibrary::load_dir(Path::new(dir))?,
+        };
+        variants.push((source, library));
+    }
+
+    let provider: Box<dyn LLMProvider> = match (cassettes, record) {
+        (Some(dir), true) => Box::new(CassetteProvider::record(dir, Box::new(OpenAIProvider))),
+        (Some(dir), false) => Box::new(CassetteProvider::replay(dir)),
+        (None, _) => Box::new(OpenAIProvider),
+    };
+
+    let report: EvalReport = run_suite(
+        &scenarios,
+        &variants,
+        provider.as_ref(),
+        &LLMConfig::from_env()?,
+        compile_project.as_deref(),
+    )
+    .await;
+
+    if let Some(report_path) = report_path {
+        let json: String = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        fs::write(&report_path, json).map_err(|e| format!("{}: {}", report_path.display(), e))?;
+    }
+    Ok(report.to_table())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai_functions::aifunc_architect::print_project_scope;
+    use crate::apis::cache::{cache_key, ResponseCache};
+    use std::time::Duration;
+
+    fn response(content: &str) -> LLMResponse {
+        LLMResponse {
+            content: content.to_string(),
+            model: "gpt-4".to_string(),
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn tests_evaluate_output() {
+        let endpoints: LLMResponse = response(
+            r#"[{"is_route_dynamic": "true", "method": "get", "request_body": "None", "response": {"id": "number"}, "route": "/task/{id:\\d+}"}]"#,
+        );
+        let checks: Vec<CheckResult> = evaluate_output(
+            &endpoints,
+            &[
+                Expectation::ValidJson,
+                Expectation::RouteExists {
+                    route: "/task/{id}".to_string(),
+                    method: Some("GET".to_string()),
+                },
+                Expectation::RouteExists {
+                    route: "/task/{id}".to_string(),
+                    method: Some("delete".to_string()),
+                },
+                Expectation::Equals {
+                    path: "0.method".to_string(),
+                    value: Value::from("get"),
+                },
+                Expectation::Compiles,
+            ],
+            None,
+        );
+
+        let passed: Vec<bool> = checks.iter().map(|check| check.passed).collect();
+        assert_eq!(passed, vec![true, true, false, true, false]);
+        assert_eq!(checks[2].detail.as_deref(), Some("listed: GET /task/{id}"));
+    }
+
+    #[tokio::test]
+    async fn tests_run_suite_with_cassettes() {
+        let suite: Vec<Scenario> = load_suite(Path::new("evals")).unwrap();
+        let scope: &Scenario = suite
+            .iter()
+            .find(|scenario| scenario.name == "todo app scope")
+            .unwrap();
+
+        // Record a cassette for the built-in prompt only
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("eval_cassettes_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let library: PromptLibrary = PromptLibrary::builtin();
+        let prompt: RenderedPrompt = library.render(
+            &scope.ai_function,
+            print_project_scope(&scope.input),
+            &scope.input,
+        );
+        let config: LLMConfig = LLMConfig::default();
+        let params: CompletionParams = config.resolve(EVAL_AGENT_POSITION, &scope.ai_function);
+        ResponseCache::new(dir.clone(), Duration::MAX, vec![])
+            .put(
+                &cache_key(&params, &vec![prompt.message]),
+                &scope.ai_function,
+                &response(r#"{"is_crud_required": true, "is_user_login_and_logout": false, "is_external_urls_required": false}"#),
+            )
+            .unwrap();
+
+        let report: EvalReport = run_suite(
+            &vec![scope.clone()],
+            &vec![("builtin".to_string(), library)],
+            &CassetteProvider::replay(dir.clone()),
+            &config,
+            None,
+        )
+        .await;
+
+        let result: &ScenarioResult = &report.variants[0].results[0];
+        assert!(result.error.is_none());
+        assert_eq!(result.passed, result.total);
+        assert_eq!(report.variants[0].score, 1.0);
+        dbg!(report.to_table());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn tests_compile_check_leaves_project_untouched() {
+        let project: PathBuf = env::temp_dir().join(format!("eval_project_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project);
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"eval_project\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        fs::write(project.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+
+        assert_eq!(
+            compile_check(&project, "fn main() { let _x: u8 = 1; }"),
+            Ok(())
+        );
+        assert!(compile_check(&project, "fn main() { let _x: u8 = \"no\"; }").is_err());
+        assert_eq!(
+            fs::read_to_string(project.join("src").join("main.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+        let _ = fs::remove_dir_all(&project);
+    }
+}
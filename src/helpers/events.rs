@@ -0,0 +1,307 @@
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::review::{ReviewDecision, ReviewGate};
+use crate::helpers::run_context::current_observers;
+use crate::models::agent_basic::basic_agent::AgentState;
+use crate::models::agents::agent_traits::FactSheet;
+use crate::models::general::llm::APIUsage;
+use dotenv::dotenv;
+use serde::Serialize;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedSender;
+
+const DEFAULT_EVENT_LOG_PATH: &str = "events.jsonl";
+
+// Everything an embedding tool may want to follow while agents work
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AgentEvent {
+    AgentStarted {
+        agent: String,
+        objective: String,
+    },
+    StateChanged {
+        agent: String,
+        from: AgentState,
+        to: AgentState,
+    },
+    Message {
+        agent: String,
+        kind: PrintCommand,
+        message: String,
+    },
+    #[serde(rename = "llm_call_started")]
+    LLMCallStarted {
+        agent: String,
+        ai_function: String,
+    },
+    #[serde(rename = "llm_call_finished")]
+    LLMCallFinished {
+        agent: String,
+        ai_function: String,
+        model: Option<String>,
+        usage: Option<APIUsage>,
+        cached: bool,
+        elapsed_ms: u64,
+        error: Option<String>,
+    },
+    // Output is what cargo build wrote to stderr
+    BuildResult {
+        agent: String,
+        success: bool,
+        output: String,
+    },
+    EndpointTestResult {
+        agent: String,
+        route: String,
+        status_code: Option<u16>,
+        passed: bool,
+        error: Option<String>,
+    },
+    // Data posted to the route was read back after the server restarted
+    PersistenceChecked {
+        agent: String,
+        route: String,
+        passed: bool,
+        error: Option<String>,
+    },
+    BugFound {
+        agent: String,
+        bug_count: u8,
+        errors: String,
+    },
+    AgentFinished {
+        agent: String,
+        error: Option<String>,
+    },
+    ClarificationRequested {
+        agent: String,
+        questions: Vec<String>,
+    },
+    // Boxed, the factsheet dwarfs every other event
+    FactSheetUpdated {
+        factsheet: Box<FactSheet>,
+    },
+    // Previous is None when the file did not exist yet
+    CodeWritten {
+        path: String,
+        previous: Option<String>,
+        contents: String,
+    },
+    ReviewRequested {
+        agent: String,
+        gate: ReviewGate,
+        summary: String,
+    },
+    ReviewDecided {
+        agent: String,
+        gate: ReviewGate,
+        decision: ReviewDecision,
+    },
+}
+
+// Subscriber to the events of a run
+pub trait AgentObserver: Send + Sync {
+    fn on_event(&self, event: &AgentEvent);
+}
+
+impl<F: Fn(&AgentEvent) + Send + Sync> AgentObserver for F {
+    fn on_event(&self, event: &AgentEvent) {
+        self(event)
+    }
+}
+
+// Hand an event to every observer of the current run
+pub fn publish(event: AgentEvent) {
+    for observer in current_observers() {
+        observer.on_event(&event);
+    }
+}
+
+// The colored agent messages of the CLI
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerminalObserver;
+
+impl AgentObserver for TerminalObserver {
+    fn on_event(&self, event: &AgentEvent) {
+        match event {
+            AgentEvent::Message {
+                agent,
+                kind,
+                message,
+            } => kind.print_to_terminal(agent, message),
+            AgentEvent::LLMCallStarted { agent, ai_function } => {
+                PrintCommand::AICall.print_to_terminal(agent, ai_function)
+            }
+            AgentEvent::LLMCallFinished {
+                agent,
+                ai_function,
+                cached: true,
+                ..
+            } => {
+                PrintCommand::AICall.print_to_terminal(agent, &format!("{} (cached)", ai_function))
+            }
+            AgentEvent::BuildResult {
+                agent,
+                success: true,
+                ..
+            } => PrintCommand::UnitTest.print_to_terminal(
+                agent,
+                "Backend Code Unit Testing: Test server build successful...",
+            ),
+            AgentEvent::BugFound {
+                agent, bug_count, ..
+            } => PrintCommand::Issue.print_to_terminal(
+                agent,
+                &format!(
+                    "Backend Code Unit Testing: Build failed, sending bug {} back for fixing",
+                    bug_count
+                ),
+            ),
+            AgentEvent::EndpointTestResult {
+                agent,
+                route,
+                status_code: Some(_),
+                passed: false,
+                ..
+            } => PrintCommand::Issue.print_to_terminal(
+                agent,
+                &format!("WARNING: Failed to call backend url endpoint {}", route),
+            ),
+            AgentEvent::EndpointTestResult {
+                agent,
+                error: Some(e),
+                ..
+            } => PrintCommand::Issue
+                .print_to_terminal(agent, &format!("Error checking backend {}", e)),
+            AgentEvent::PersistenceChecked {
+                agent,
+                route,
+                passed: true,
+                ..
+            } => PrintCommand::UnitTest.print_to_terminal(
+                agent,
+                &format!("Data posted to {} survived a server restart", route),
+            ),
+            AgentEvent::PersistenceChecked {
+                agent,
+                route,
+                error,
+                ..
+            } => PrintCommand::Issue.print_to_terminal(
+                agent,
+                &format!(
+                    "WARNING: Data posted to {} did not survive a server restart: {}",
+                    route,
+                    error.as_deref().unwrap_or("unknown error")
+                ),
+            ),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    timestamp: u64,
+    #[serde(flatten)]
+    event: &'a AgentEvent,
+}
+
+// One JSON object per line, e.g. {"timestamp":1700000000,"event":"agent_started",...}
+pub struct JsonLinesObserver {
+    file: Mutex<File>,
+}
+
+impl JsonLinesObserver {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file: File = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    // Append to EVENT_LOG_PATH, defaults to events.jsonl
+    pub fn from_env() -> io::Result<Self> {
+        dotenv().ok();
+
+        let path: String = env::var("EVENT_LOG_PATH").unwrap_or(DEFAULT_EVENT_LOG_PATH.to_string());
+        Self::create(Path::new(&path))
+    }
+}
+
+impl AgentObserver for JsonLinesObserver {
+    fn on_event(&self, event: &AgentEvent) {
+        let record: EventRecord = EventRecord {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            event,
+        };
+        let line: String = serde_json::to_string(&record).expect("Failed to serialize event");
+
+        // An event log failure is reported but never stops the run
+        if let Err(e) = writeln!(self.file.lock().unwrap(), "{}", line) {
+            eprintln!("Could not write event log: {}", e);
+        }
+    }
+}
+
+// Forwards events to a channel, e.g. to stream them to another task
+pub struct ChannelObserver {
+    sender: UnboundedSender<AgentEvent>,
+}
+
+impl ChannelObserver {
+    pub fn new(sender: UnboundedSender<AgentEvent>) -> Self {
+        Self { sender }
+    }
+}
+
+impl AgentObserver for ChannelObserver {
+    fn on_event(&self, event: &AgentEvent) {
+        // The receiver going away only means nobody is listening any more
+        let _ = self.sender.send(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn tests_json_lines_observer() {
+        let path: PathBuf = env::temp_dir().join(format!("events_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let observer: JsonLinesObserver = JsonLinesObserver::create(&path).unwrap();
+        observer.on_event(&AgentEvent::StateChanged {
+            agent: "Backend Developer".to_string(),
+            from: AgentState::Working,
+            to: AgentState::UnitTesting,
+        });
+        observer.on_event(&AgentEvent::LLMCallStarted {
+            agent: "Solutions Architect".to_string(),
+            ai_function: "print_site_urls".to_string(),
+        });
+
+        let contents: String = fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "state_changed");
+        assert_eq!(lines[0]["to"], "UnitTesting");
+        assert_eq!(lines[1]["event"], "llm_call_started");
+        assert_eq!(lines[1]["ai_function"], "print_site_urls");
+        assert!(lines[1]["timestamp"].as_u64().unwrap() > 0);
+        let _ = fs::remove_file(&path);
+    }
+}
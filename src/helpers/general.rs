@@ -1,39 +1,371 @@
-use crate::apis::call_request::call_gpt;
-use crate::helpers::command_line::PrintCommand;
-use crate::models::general::llm::Message;
+use crate::apis::cache::{cache_key, ResponseCache};
+use crate::apis::provider::LLMProvider;
+use crate::apis::retry::LLMError;
+use crate::apis::run_log::{append_run_log, LLMCallRecord};
+use crate::apis::usage::{BudgetExceeded, UsageTracker};
+use crate::helpers::backend_templates::BackendTemplate;
+use crate::helpers::command_line::{
+    print_stream_end, print_stream_progress, print_stream_token, PrintCommand,
+};
+use crate::helpers::events::{publish, AgentEvent};
+use crate::helpers::openapi::{routes_to_openapi, validate_openapi};
+use crate::helpers::prompt_library::{PromptLibrary, RenderedPrompt};
+use crate::helpers::run_context::{
+    current_llm_config, current_prompt_library, current_provider, current_response_cache,
+    current_usage_tracker, current_workspace, prints_to_terminal, wait_while_paused,
+};
+use crate::helpers::ts_client::routes_to_typescript;
+use crate::models::agent_basic::basic_agent::BasicAgent;
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{FactSheet, RouteObject, UrlProbe};
+use crate::models::general::api_schema::Shape;
+use crate::models::general::llm::{APIUsage, CompletionParams, LLMResponse, Message};
+use crate::models::general::memory::{
+    compact_memory, estimate_message_tokens, estimate_tokens, memory_prompt, memory_response,
+    recall_follow_up, recall_memory, MemoryPolicy,
+};
+use dotenv::dotenv;
+use reqwest::header::CONTENT_TYPE;
 use reqwest::Client;
 use serde::de::DeserializeOwned;
+use std::env;
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-const CODE_TEMPLATE_PATH: &str =
-    "placeholder";
+const WEB_SERVER_PROJECT_PATH: &str = "placeholder";
 
-pub const WEB_SERVER_PROJECT_PATH: &str = "placeholder";
+const EXEC_MAIN_PATH: &str = "placeholder";
 
-pub const EXEC_MAIN_PATH: &str =
-    "placeholder";
+const API_SCHEMA_PATH: &str = "placeholder";
 
-const API_SCHEMA_PATH: &str =
-    "placeholder";
+// Where a run reads the code template and writes the generated project
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workspace {
+    // A custom reference server, without one the backend template's own source is used
+    pub code_template_path: Option<PathBuf>,
+    pub web_server_project_path: PathBuf,
+    pub exec_main_path: PathBuf,
+    pub api_schema_path: PathBuf,
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self {
+            code_template_path: None,
+            web_server_project_path: PathBuf::from(WEB_SERVER_PROJECT_PATH),
+            exec_main_path: PathBuf::from(EXEC_MAIN_PATH),
+            api_schema_path: PathBuf::from(API_SCHEMA_PATH),
+        }
+    }
+}
+
+impl Workspace {
+    // Cargo project at project_dir, schemas are written to project_dir/schemas
+    pub fn for_project(project_dir: &Path, code_template_path: Option<&Path>) -> Self {
+        Self {
+            code_template_path: code_template_path.map(Path::to_path_buf),
+            web_server_project_path: project_dir.to_path_buf(),
+            exec_main_path: project_dir.join("src").join("main.rs"),
+            api_schema_path: project_dir.join("schemas").join("api_schema.json"),
+        }
+    }
+
+    // PROJECT_DIR points a run at an imported project instead of the default one,
+    // CODE_TEMPLATE at a reference server of the user's own
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let workspace: Workspace = match env::var("PROJECT_DIR") {
+            Ok(project_dir) => Self::for_project(Path::new(&project_dir), None),
+            Err(_) => Self::default(),
+        };
+        Self {
+            code_template_path: env::var("CODE_TEMPLATE").ok().map(PathBuf::from),
+            ..workspace
+        }
+    }
+
+    pub fn openapi_path(&self) -> PathBuf {
+        self.api_schema_path.with_file_name("openapi.json")
+    }
+
+    pub fn typescript_client_path(&self) -> PathBuf {
+        self.api_schema_path.with_file_name("client.ts")
+    }
+
+    // Written next to main.rs when the project has user login and logout
+    pub fn auth_module_path(&self) -> PathBuf {
+        self.exec_main_path.with_file_name("auth.rs")
+    }
+
+    // SQL files the generated backend applies at startup
+    pub fn migrations_dir(&self) -> PathBuf {
+        self.web_server_project_path.join("migrations")
+    }
+
+    // Factsheet of the last finished run, what an amend run starts from
+    pub fn factsheet_path(&self) -> PathBuf {
+        self.api_schema_path.with_file_name("factsheet.json")
+    }
+
+    pub fn checkpoint_path(&self) -> PathBuf {
+        self.api_schema_path
+            .with_file_name("factsheet_checkpoint.json")
+    }
+
+    // Files a run has produced so far
+    pub fn artifacts(&self) -> Vec<PathBuf> {
+        let mut migrations: Vec<PathBuf> = fs::read_dir(self.migrations_dir())
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .collect()
+            })
+            .unwrap_or_default();
+        migrations.sort();
 
-// Extend ai function to encourage specific output
+        [
+            self.exec_main_path.clone(),
+            self.auth_module_path(),
+            self.api_schema_path.clone(),
+            self.openapi_path(),
+            self.typescript_client_path(),
+        ]
+        .into_iter()
+        .chain(migrations)
+        .filter(|path| path.is_file())
+        .collect()
+    }
+}
+
+// Extend ai function to encourage specific output, using the built-in prompt template.
+// Agent calls go through the prompt library so template overrides apply.
 pub fn extend_ai_function(ai_func: fn(&str) -> &'static str, func_input: &str) -> Message {
     let ai_function_str: &str = ai_func(func_input);
 
     // Extend the string to encourage only printing the output
-    let msg: String = format!(
-        "FUNCTION: {}
-  INSTRUCTION: You are a function printer. You ONLY print the results of functions.
-  Nothing else. No commentary. Here is the input to the function: {}.
-  Print out what the function will return.",
-        ai_function_str, func_input
+    PromptLibrary::builtin()
+        .render("", ai_function_str, func_input)
+        .message
+}
+
+// Reasons an ai_function call can stop the agent
+#[derive(Debug)]
+pub enum AITaskError {
+    Budget(BudgetExceeded),
+    Request(LLMError),
+    Decode(serde_json::Error),
+}
+
+impl fmt::Display for AITaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Budget(e) => write!(f, "{}", e),
+            Self::Request(e) => write!(f, "Failed to call OpenAI: {}", e),
+            Self::Decode(e) => write!(f, "Failed to decode ai response from serde_json: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AITaskError {}
+
+// How streamed completions are shown while they arrive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamDisplay {
+    Off,
+    Progress,
+    Tokens,
+}
+
+impl StreamDisplay {
+    // LLM_STREAM=off|progress|tokens, progress by default
+    pub fn from_env() -> Self {
+        match env::var("LLM_STREAM")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "off" | "0" | "false" => Self::Off,
+            "tokens" => Self::Tokens,
+            _ => Self::Progress,
+        }
+    }
+}
+
+// Cancel a stream that has gone quiet for LLM_IDLE_TIMEOUT_SECS
+fn stream_idle_timeout() -> Duration {
+    let secs: u64 = env::var("LLM_IDLE_TIMEOUT_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(60);
+    Duration::from_secs(secs)
+}
+
+// Single LLM call to the run's provider, streamed to the terminal unless streaming is turned off
+async fn call_llm(
+    messages: Vec<Message>,
+    params: &CompletionParams,
+    agent_position: &str,
+    agent_operation: &str,
+) -> Result<LLMResponse, LLMError> {
+    let provider: Arc<dyn LLMProvider> = current_provider();
+    let display: StreamDisplay = match prints_to_terminal() {
+        true => StreamDisplay::from_env(),
+        false => StreamDisplay::Off,
+    };
+    if display == StreamDisplay::Off {
+        return provider.complete(agent_operation, messages, params).await;
+    }
+
+    let started: Instant = Instant::now();
+    // Providers batch tokens into chunks, so this counts chunks rather than tokens
+    let mut received_chunks: usize = 0;
+    let mut on_token = |token: &str| {
+        received_chunks += 1;
+        match display {
+            StreamDisplay::Tokens => print_stream_token(token),
+            _ => print_stream_progress(agent_position, received_chunks, started.elapsed()),
+        }
+    };
+    let llm_response: Result<LLMResponse, LLMError> = provider
+        .complete_stream(
+            agent_operation,
+            messages,
+            params,
+            stream_idle_timeout(),
+            &mut on_token,
+        )
+        .await;
+    print_stream_end();
+    llm_response
+}
+
+// A run log failure is reported but never stops the run
+fn log_llm_call(record: LLMCallRecord) {
+    if let Err(e) = append_run_log(&record) {
+        PrintCommand::Issue
+            .print_agent_message(&record.agent, &format!("Could not write run log: {}", e));
+    }
+}
+
+// Performs call to LLM GPT with a prepared conversation
+async fn ai_conversation_request(
+    messages: Vec<Message>,
+    agent_position: &str,
+    agent_operation: &str,
+    prompt_version: &str,
+) -> Result<String, AITaskError> {
+    // A paused run holds here before its next call
+    wait_while_paused().await;
+
+    // Stop before spending past the budget
+    let usage_tracker: Arc<Mutex<UsageTracker>> = current_usage_tracker();
+    usage_tracker
+        .lock()
+        .unwrap()
+        .check_budget()
+        .map_err(AITaskError::Budget)?;
+
+    // Model and sampling parameters for this agent and ai_function
+    let params: CompletionParams = current_llm_config().resolve(agent_position, agent_operation);
+
+    // Serve repeated prompts from the cache when enabled
+    let cache: Option<Arc<ResponseCache>> =
+        current_response_cache().filter(|cache| !cache.is_bypassed(agent_operation));
+    let key: String = cache_key(&params, &messages);
+    if let Some(cached) = cache.as_ref().and_then(|cache| cache.get(&key)) {
+        publish(AgentEvent::LLMCallFinished {
+            agent: agent_position.to_string(),
+            ai_function: agent_operation.to_string(),
+            model: Some(cached.model.clone()),
+            usage: cached.usage,
+            cached: true,
+            elapsed_ms: 0,
+            error: None,
+        });
+        log_llm_call(LLMCallRecord::new(
+            agent_position,
+            agent_operation,
+            &params,
+            prompt_version,
+            &cached.model,
+            cached.usage,
+            true,
+        ));
+        return Ok(cached.content);
+    }
+
+    // Report current status
+    publish(AgentEvent::LLMCallStarted {
+        agent: agent_position.to_string(),
+        ai_function: agent_operation.to_string(),
+    });
+
+    // Get LLM response, transient failures are retried by the request layer
+    let started: Instant = Instant::now();
+    let llm_res: Result<LLMResponse, LLMError> =
+        call_llm(messages.clone(), &params, agent_position, agent_operation).await;
+    publish(AgentEvent::LLMCallFinished {
+        agent: agent_position.to_string(),
+        ai_function: agent_operation.to_string(),
+        model: llm_res.as_ref().ok().map(|res| res.model.clone()),
+        usage: llm_res.as_ref().ok().and_then(|res| res.usage),
+        cached: false,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        error: llm_res.as_ref().err().map(|e| e.to_string()),
+    });
+    let llm_response: LLMResponse = llm_res.map_err(AITaskError::Request)?;
+    log_llm_call(LLMCallRecord::new(
+        agent_position,
+        agent_operation,
+        &params,
+        prompt_version,
+        &llm_response.model,
+        llm_response.usage,
+        false,
+    ));
+
+    if let Some(cache) = cache {
+        if let Err(e) = cache.put(&key, agent_operation, &llm_response) {
+            PrintCommand::Issue
+                .print_agent_message(agent_position, &format!("Could not cache response: {}", e));
+        }
+    }
+
+    // Account for tokens, estimating if the provider did not report usage
+    let usage: APIUsage = llm_response.usage.unwrap_or_else(|| {
+        let prompt_tokens: u64 = estimate_message_tokens(&messages) as u64;
+        let completion_tokens: u64 = estimate_tokens(&llm_response.content) as u64;
+        APIUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    });
+    usage_tracker.lock().unwrap().record(
+        agent_position,
+        agent_operation,
+        &llm_response.model,
+        &usage,
     );
 
-    // Return message
-    Message {
-        role: "system".to_string(),
-        content: msg,
+    Ok(llm_response.content)
+}
+
+// Append and clear any feedback given at a review gate
+fn with_reviewer_feedback(msg_context: String, pending_feedback: &mut Vec<String>) -> String {
+    if pending_feedback.is_empty() {
+        return msg_context;
     }
+    let feedback: Vec<String> = std::mem::take(pending_feedback);
+    format!(
+        "{} \n REVIEWER_FEEDBACK (the human reviewer asked for these changes): {}",
+        msg_context,
+        feedback.join(" \n ")
+    )
 }
 
 // Performs call to LLM GPT
@@ -42,24 +374,18 @@ pub async fn ai_task_request(
     agent_position: &str,
     agent_operation: &str,
     function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> String {
-    // Extend AI function
-    let extended_msg: Message = extend_ai_function(function_pass, &msg_context);
-
-    // Print current status
-    PrintCommand::AICall.print_agent_message(agent_position, agent_operation);
-
-    // Get LLM response
-    let llm_response_res: Result<String, Box<dyn std::error::Error + Send>> =
-        call_gpt(vec![extended_msg.clone()]).await;
+) -> Result<String, AITaskError> {
+    // Extend AI function, a template from the prompt library may replace the built-in prompt
+    let prompt: RenderedPrompt =
+        current_prompt_library().render(agent_operation, function_pass(&msg_context), &msg_context);
 
-    // Return Success or try again
-    match llm_response_res {
-        Ok(llm_resp) => llm_resp,
-        Err(_) => call_gpt(vec![extended_msg.clone()])
-            .await
-            .expect("Failed twice to call OpenAI"),
-    }
+    ai_conversation_request(
+        vec![prompt.message],
+        agent_position,
+        agent_operation,
+        &prompt.version,
+    )
+    .await
 }
 
 // Performs call to LLM GPT - Decoded
@@ -68,12 +394,76 @@ pub async fn ai_task_request_decoded<T: DeserializeOwned>(
     agent_position: &str,
     agent_operation: &str,
     function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> T {
+) -> Result<T, AITaskError> {
+    let llm_response: String =
+        ai_task_request(msg_context, agent_position, agent_operation, function_pass).await?;
+    serde_json::from_str(llm_response.as_str()).map_err(AITaskError::Decode)
+}
+
+// Performs call to LLM GPT - Records the exchange in agent memory.
+// Earlier exchanges for the recall operations are sent along as history.
+pub async fn ai_task_request_with_memory(
+    msg_context: String,
+    agent: &mut BasicAgent,
+    agent_operation: &str,
+    function_pass: for<'a> fn(&'a str) -> &'static str,
+    recall_operations: &[&str],
+) -> Result<String, AITaskError> {
+    let policy: MemoryPolicy = MemoryPolicy::default();
+
+    // Reviewer feedback from the last review gate steers this call
+    let msg_context: String = with_reviewer_feedback(msg_context, &mut agent.pending_feedback);
+
+    // Extend AI function, a template from the prompt library may replace the built-in prompt
+    let prompt: RenderedPrompt =
+        current_prompt_library().render(agent_operation, function_pass(&msg_context), &msg_context);
+    let extended_msg: Message = prompt.message;
+
+    // The system prompt comes first, then relevant history that fits in the context window
+    let follow_up: Message = recall_follow_up();
+    let recalled: Vec<Message> = recall_memory(
+        agent.get_memory(),
+        recall_operations,
+        policy
+            .history_budget(&extended_msg)
+            .saturating_sub(estimate_message_tokens(std::slice::from_ref(&follow_up))),
+        &policy,
+    );
+    let mut messages: Vec<Message> = vec![extended_msg];
+    if !recalled.is_empty() {
+        messages.extend(recalled);
+        messages.push(follow_up);
+    }
+
     let llm_response: String =
-        ai_task_request(msg_context, agent_position, agent_operation, function_pass).await;
-    let decoded_response: T = serde_json::from_str(llm_response.as_str())
-        .expect("Failed to decode ai response from serde_json");
-    return decoded_response;
+        ai_conversation_request(messages, &agent.position, agent_operation, &prompt.version)
+            .await?;
+
+    // Remember this exchange
+    agent.add_memory(memory_prompt(agent_operation, &msg_context));
+    agent.add_memory(memory_response(&llm_response));
+    compact_memory(&mut agent.memory, &policy);
+
+    Ok(llm_response)
+}
+
+// Performs call to LLM GPT - Records the exchange in agent memory - Decoded
+pub async fn ai_task_request_decoded_with_memory<T: DeserializeOwned>(
+    msg_context: String,
+    agent: &mut BasicAgent,
+    agent_operation: &str,
+    function_pass: for<'a> fn(&'a str) -> &'static str,
+    recall_operations: &[&str],
+) -> Result<T, AITaskError> {
+    let llm_response: String = ai_task_request_with_memory(
+        msg_context,
+        agent,
+        agent_operation,
+        function_pass,
+        recall_operations,
+    )
+    .await?;
+    serde_json::from_str(llm_response.as_str()).map_err(AITaskError::Decode)
 }
 
 // Check whether request url is valid
@@ -82,34 +472,289 @@ pub async fn check_status_code(client: &Client, url: &str) -> Result<u16, reqwes
     Ok(response.status().as_u16())
 }
 
-// Get Code Template
-pub fn read_code_template_contents() -> String {
-    let path: String = String::from(CODE_TEMPLATE_PATH);
-    fs::read_to_string(path).expect("Failed to read code template")
+const URL_BODY_SAMPLE_CHARS: usize = 500;
+// Bodies are read up to this size, a larger payload is not parsed for its shape
+const URL_PROBE_MAX_BYTES: usize = 1024 * 1024;
+const LOGIN_URL_MARKERS: [&str; 8] = [
+    "/login",
+    "/signin",
+    "/sign-in",
+    "/sso",
+    "/oauth",
+    "/auth/",
+    "/authorize",
+    "accounts.google.com",
+];
+const API_KEY_MARKERS: [&str; 7] = [
+    "api key",
+    "apikey",
+    "api_key",
+    "access key",
+    "access_key",
+    "missing token",
+    "invalid token",
+];
+const ERROR_MARKERS: [&str; 5] = ["error", "missing", "invalid", "required", "unauthorized"];
+
+// Probe external url and record what it actually returns
+pub async fn probe_url(client: &Client, url: &str) -> Result<UrlProbe, reqwest::Error> {
+    let started: Instant = Instant::now();
+    let response: reqwest::Response = client.get(url).send().await?;
+
+    let status_code: u16 = response.status().as_u16();
+    let final_url: String = response.url().to_string();
+    let content_type: Option<String> = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+
+    let mut response: reqwest::Response = response;
+    let mut bytes: Vec<u8> = vec![];
+    let mut is_truncated: bool = false;
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > URL_PROBE_MAX_BYTES {
+            bytes.truncate(URL_PROBE_MAX_BYTES);
+            is_truncated = true;
+            break;
+        }
+    }
+    let body: String = String::from_utf8_lossy(&bytes).to_string();
+    let latency_ms: u64 = started.elapsed().as_millis() as u64;
+
+    // Only objects and arrays count as a usable JSON payload
+    let json_body: Option<serde_json::Value> = match is_truncated {
+        true => None,
+        false => serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .filter(|value| value.is_object() || value.is_array()),
+    };
+
+    Ok(UrlProbe {
+        url: url.to_string(),
+        final_url,
+        status_code,
+        content_type,
+        latency_ms,
+        body_sample: body.chars().take(URL_BODY_SAMPLE_CHARS).collect(),
+        is_json: json_body.is_some(),
+        json_shape: json_body.as_ref().and_then(Shape::from_value),
+        rejection: None,
+    })
+}
+
+// Reason a probed url is unusable by the backend, if any
+pub fn url_probe_rejection(probe: &UrlProbe) -> Option<String> {
+    let final_url: String = probe.final_url.to_lowercase();
+    let body: String = probe.body_sample.to_lowercase();
+
+    if probe.final_url != probe.url && LOGIN_URL_MARKERS.iter().any(|m| final_url.contains(m)) {
+        return Some(format!("redirects to a login page ({})", probe.final_url));
+    }
+    if probe.status_code == 401 || probe.status_code == 403 {
+        return Some(format!(
+            "status {}: requires authentication or an API key",
+            probe.status_code
+        ));
+    }
+    if probe.status_code != 200 {
+        return Some(format!("status {}", probe.status_code));
+    }
+    if API_KEY_MARKERS.iter().any(|m| body.contains(m))
+        && ERROR_MARKERS.iter().any(|m| body.contains(m))
+    {
+        return Some("response asks for an API key".to_string());
+    }
+    if !probe.is_json && body.contains("type=\"password\"") {
+        return Some("returns a login form".to_string());
+    }
+    None
+}
+
+// Get Code Template, a template file set in the workspace replaces the built in reference code
+pub fn read_code_template_contents(template: &BackendTemplate) -> String {
+    match current_workspace().code_template_path {
+        Some(path) => fs::read_to_string(path).expect("Failed to read code template"),
+        None => template.source.to_string(),
+    }
 }
 
 // Get Exec Main
 pub fn read_exec_main_contents() -> String {
-    let path: String = String::from(EXEC_MAIN_PATH);
+    let path: PathBuf = current_workspace().exec_main_path;
     fs::read_to_string(path).expect("Failed to read code template")
 }
 
+// Next numbered migration, e.g. "0002_amend.sql" once "0001_init.sql" is there
+pub fn next_migration_name(label: &str) -> String {
+    let last: u32 = fs::read_dir(current_workspace().migrations_dir())
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name: String = entry.file_name().to_string_lossy().to_string();
+                    name.split('_').next()?.parse::<u32>().ok()
+                })
+                .max()
+                .unwrap_or(0)
+        })
+        .unwrap_or(0);
+    format!("{:04}_{}.sql", last + 1, label)
+}
+
+// Save a SQL file of the generated backend, i.e. "0001_init.sql" or "seed.sql"
+pub fn save_migration(file_name: &str, sql: &str) {
+    let dir: PathBuf = current_workspace().migrations_dir();
+    fs::create_dir_all(&dir).expect("Failed to create migrations directory");
+    let path: PathBuf = dir.join(file_name);
+    let previous: Option<String> = fs::read_to_string(&path).ok();
+    fs::write(&path, sql).expect("Failed to write migration");
+    publish(AgentEvent::CodeWritten {
+        path: path.to_string_lossy().to_string(),
+        previous,
+        contents: sql.to_string(),
+    });
+}
+
+// Save the auth module of the backend template as src/auth.rs, an auth.rs already in the
+// project, e.g. an imported project's own, is never replaced
+pub fn save_auth_module(contents: &str) {
+    let path: PathBuf = current_workspace().auth_module_path();
+    if path.exists() {
+        return;
+    }
+    fs::write(&path, contents).expect("Failed to write auth.rs file");
+    publish(AgentEvent::CodeWritten {
+        path: path.to_string_lossy().to_string(),
+        previous: None,
+        contents: contents.to_string(),
+    });
+}
+
 // Save New Backend Code
 pub fn save_backend_code(contents: &String) {
-    let path: String = String::from(EXEC_MAIN_PATH);
-    fs::write(path, contents).expect("Failed to write main.rs file");
+    let path: PathBuf = current_workspace().exec_main_path;
+    let previous: Option<String> = fs::read_to_string(&path).ok();
+    fs::write(&path, contents).expect("Failed to write main.rs file");
+    publish(AgentEvent::CodeWritten {
+        path: path.to_string_lossy().to_string(),
+        previous,
+        contents: contents.clone(),
+    });
 }
 
 // Save JSON API Endpoint Schema
 pub fn save_api_endpoints(api_endpoints: &String) {
-    let path: String = String::from(API_SCHEMA_PATH);
+    let path: PathBuf = current_workspace().api_schema_path;
     fs::write(path, api_endpoints).expect("Failed to write API Endpoints to file");
 }
 
+// Convert API Endpoints to OpenAPI 3.1, validate and save next to the API Endpoint Schema
+pub fn save_openapi_schema(api_endpoints: &Vec<RouteObject>) -> Result<(), Vec<String>> {
+    let openapi_doc: serde_json::Value = routes_to_openapi(api_endpoints, "Web Server API")?;
+    validate_openapi(&openapi_doc)?;
+
+    let path: PathBuf = current_workspace().openapi_path();
+    let contents: String =
+        serde_json::to_string_pretty(&openapi_doc).expect("Failed to serialize OpenAPI schema");
+    fs::write(path, contents).expect("Failed to write OpenAPI schema to file");
+    Ok(())
+}
+
+// Generate typed TypeScript client and save next to the API Endpoint Schema
+pub fn save_typescript_client(api_endpoints: &Vec<RouteObject>) {
+    let path: PathBuf = current_workspace().typescript_client_path();
+    let contents: String = routes_to_typescript(api_endpoints);
+    fs::write(path, contents).expect("Failed to write TypeScript client to file");
+}
+
+// Save the factsheet of a finished run for later change requests
+pub fn save_factsheet(factsheet: &FactSheet) -> PathBuf {
+    let path: PathBuf = current_workspace().factsheet_path();
+    let contents: String =
+        serde_json::to_string_pretty(factsheet).expect("Failed to serialize factsheet");
+    fs::write(&path, contents).expect("Failed to write factsheet to file");
+    path
+}
+
+pub fn load_factsheet(path: &Path) -> Result<FactSheet, String> {
+    let contents: String = fs::read_to_string(path).map_err(|e| {
+        format!(
+            "No finished project to amend, could not read {}: {}",
+            path.display(),
+            e
+        )
+    })?;
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("Factsheet {} is not valid: {}", path.display(), e))
+}
+
+// Save the factsheet so a stopped run keeps its partial results
+pub fn save_factsheet_checkpoint(factsheet: &FactSheet) -> PathBuf {
+    let path: PathBuf = current_workspace().checkpoint_path();
+    let contents: String =
+        serde_json::to_string_pretty(factsheet).expect("Failed to serialize factsheet");
+    fs::write(&path, contents).expect("Failed to write factsheet checkpoint to file");
+    path
+}
+
+// Copy a cargo project without its build output or git history
+pub fn copy_project(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == "target" || name == ".git" {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            copy_project(&entry.path(), &to.join(&name))?;
+        } else {
+            fs::copy(entry.path(), to.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
+    use crate::apis::provider::MockProvider;
+    use crate::apis::usage::Budget;
+    use crate::helpers::backend_templates::AXUM_JSON;
+    use crate::helpers::run_context::{new_usage_tracker, with_run_context, RunContext};
+    use crate::helpers::stub_server::{StubResponse, StubServer};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    // Keeps the messages of the last call
+    #[derive(Default)]
+    struct RecordingProvider {
+        messages: Mutex<Vec<Message>>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for RecordingProvider {
+        fn name(&self) -> String {
+            "recording".to_string()
+        }
+
+        async fn complete(
+            &self,
+            _ai_function: &str,
+            messages: Vec<Message>,
+            _params: &CompletionParams,
+        ) -> Result<LLMResponse, LLMError> {
+            *self.messages.lock().unwrap() = messages;
+            Ok(LLMResponse {
+                content: "fixed code 2".to_string(),
+                model: "recording".to_string(),
+                usage: None,
+            })
+        }
+    }
 
     #[test]
     fn tests_extending_ai_function() {
@@ -119,6 +764,156 @@ mod tests {
         assert_eq!(extended_msg.role, "system".to_string());
     }
 
+    #[tokio::test]
+    async fn tests_recalled_memory_follows_system_prompt() {
+        let provider: Arc<RecordingProvider> = Arc::new(RecordingProvider::default());
+        let mut agent: BasicAgent =
+            BasicAgent::new("Writes code".to_string(), "Backend Developer".to_string());
+        agent.add_memory(memory_prompt("print_fixed_code", "broken code 1"));
+        agent.add_memory(memory_response("fixed code 1"));
+
+        let context: RunContext = RunContext {
+            provider: provider.clone(),
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+        let res: String = with_run_context(
+            context,
+            ai_task_request_with_memory(
+                "broken code 2".to_string(),
+                &mut agent,
+                "print_fixed_code",
+                convert_user_input_to_goal,
+                &["print_fixed_code"],
+            ),
+        )
+        .await
+        .unwrap();
+
+        let messages: Vec<Message> = provider.messages.lock().unwrap().clone();
+        assert_eq!(res, "fixed code 2");
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[0].content.contains("broken code 2"));
+        assert!(messages[1].content.contains("broken code 1"));
+        assert_eq!(messages[2].content, "fixed code 1");
+        assert_eq!(messages[3].content, recall_follow_up().content);
+    }
+
+    #[test]
+    fn tests_reviewer_feedback_is_used_once() {
+        let mut pending_feedback: Vec<String> = vec!["Add a /health route".to_string()];
+        let msg_context: String = with_reviewer_feedback(
+            "PROJECT_DESCRIPTION: todo app".to_string(),
+            &mut pending_feedback,
+        );
+        assert!(msg_context.starts_with("PROJECT_DESCRIPTION: todo app"));
+        assert!(msg_context.contains("REVIEWER_FEEDBACK"));
+        assert!(msg_context.contains("Add a /health route"));
+        assert!(pending_feedback.is_empty());
+
+        let msg_context: String = with_reviewer_feedback("next".to_string(), &mut pending_feedback);
+        assert_eq!(msg_context, "next");
+    }
+
+    #[tokio::test]
+    async fn tests_code_template_follows_backend_template() {
+        let context: RunContext = RunContext {
+            workspace: Workspace::default(),
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+        let code_template: String =
+            with_run_context(context, async { read_code_template_contents(&AXUM_JSON) }).await;
+        assert_eq!(code_template, AXUM_JSON.source);
+
+        // A template path set by the user replaces the reference code
+        let path: PathBuf =
+            env::temp_dir().join(format!("code_template_{}.rs", std::process::id()));
+        fs::write(&path, "// custom server").unwrap();
+        let context: RunContext = RunContext {
+            workspace: Workspace {
+                code_template_path: Some(path.clone()),
+                ..Workspace::default()
+            },
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+        let code_template: String =
+            with_run_context(context, async { read_code_template_contents(&AXUM_JSON) }).await;
+        assert_eq!(code_template, "// custom server");
+        let _ = fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn tests_usage_is_tracked_per_run() {
+        let run = |usage: Arc<Mutex<UsageTracker>>| {
+            let context: RunContext = RunContext {
+                provider: Arc::new(RecordingProvider::default()),
+                usage,
+                print_to_terminal: false,
+                ..RunContext::default()
+            };
+            with_run_context(
+                context,
+                ai_task_request(
+                    "todo app".to_string(),
+                    "Project Manager",
+                    "convert_user_input_to_goal",
+                    convert_user_input_to_goal,
+                ),
+            )
+        };
+        let first: Arc<Mutex<UsageTracker>> = new_usage_tracker(Budget::default());
+        let second: Arc<Mutex<UsageTracker>> = new_usage_tracker(Budget {
+            max_tokens: Some(1),
+            max_cost_usd: None,
+        });
+
+        run(first.clone()).await.unwrap();
+        run(second.clone()).await.unwrap();
+        assert_eq!(first.lock().unwrap().total.calls, 1);
+        assert_eq!(second.lock().unwrap().total.calls, 1);
+
+        // Only the run that spent its budget is stopped, with an error rather than a panic
+        assert!(matches!(
+            run(second.clone()).await,
+            Err(AITaskError::Budget(_))
+        ));
+        assert_eq!(second.lock().unwrap().total.calls, 1);
+        assert!(run(first.clone()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn tests_ai_task_request_is_remembered() {
+        let mut agent: BasicAgent = BasicAgent::new(
+            "Manage agents who are building an excellent website for the user".to_string(),
+            "Managing Agent".to_string(),
+        );
+        let context: RunContext = RunContext {
+            provider: Arc::new(MockProvider),
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+
+        let res: String = with_run_context(
+            context,
+            ai_task_request_with_memory(
+                "Build me a todo app".to_string(),
+                &mut agent,
+                "convert_user_input_to_goal",
+                convert_user_input_to_goal,
+                &[],
+            ),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(agent.memory.len(), 2);
+        assert!(agent.memory[0].content.contains("Build me a todo app"));
+        assert_eq!(agent.memory[1].content, res);
+    }
+
     #[tokio::test]
     async fn tests_ai_task_request() {
         let ai_func_param: String =
@@ -130,8 +925,84 @@ mod tests {
             "Defining user requirements",
             convert_user_input_to_goal,
         )
-        .await;
+        .await
+        .expect("Failed to call OpenAI");
 
         assert!(res.len() > 20);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn tests_probe_url_records_json_shape() {
+        let server: StubServer = StubServer::start(vec![(
+            "/prices",
+            vec![StubResponse::json(
+                200,
+                r#"[{"symbol": "BTC", "price": 65000.5}]"#,
+            )],
+        )])
+        .await;
+        let client: Client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+
+        let probe: UrlProbe = probe_url(&client, &server.url("/prices")).await.unwrap();
+
+        assert_eq!(probe.status_code, 200);
+        assert!(probe.is_json);
+        assert_eq!(probe.content_type.as_deref(), Some("application/json"));
+        assert!(matches!(probe.json_shape, Some(Shape::Array(_))));
+        assert_eq!(url_probe_rejection(&probe), None);
+    }
+
+    #[tokio::test]
+    async fn tests_probe_url_rejections() {
+        let server: StubServer = StubServer::start(vec![
+            (
+                "/private",
+                vec![StubResponse::redirect("/login?next=/private")],
+            ),
+            (
+                "/login",
+                vec![StubResponse::new(
+                    200,
+                    "<form><input type=\"password\"></form>",
+                )],
+            ),
+            (
+                "/keyed",
+                vec![StubResponse::json(200, r#"{"error": "Missing API key"}"#)],
+            ),
+            ("/forbidden", vec![StubResponse::json(403, "{}")]),
+        ])
+        .await;
+        let client: Client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+
+        for path in ["/private", "/keyed", "/forbidden"] {
+            let probe: UrlProbe = probe_url(&client, &server.url(path)).await.unwrap();
+            let rejection: Option<String> = url_probe_rejection(&probe);
+            dbg!(path, &rejection);
+            assert!(rejection.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_probe_url_caps_body_size() {
+        let large_body: String = format!("[{}]", vec!["1"; URL_PROBE_MAX_BYTES].join(","));
+        let server: StubServer =
+            StubServer::start(vec![("/large", vec![StubResponse::json(200, &large_body)])]).await;
+        let client: Client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+
+        let probe: UrlProbe = probe_url(&client, &server.url("/large")).await.unwrap();
+
+        assert_eq!(probe.status_code, 200);
+        assert!(!probe.is_json);
+        assert_eq!(probe.body_sample.len(), URL_BODY_SAMPLE_CHARS);
+    }
+}
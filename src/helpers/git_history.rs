@@ -0,0 +1,354 @@
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::events::{AgentEvent, AgentObserver};
+use dotenv::dotenv;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Build output and run data stay out of the history
+const PROJECT_GITIGNORE: &str = "/target\n/database.json\n/database.sqlite\n/events.jsonl\n";
+
+// Used when git has no identity configured
+const COMMITTER_NAME: &str = "auto_gippity";
+const COMMITTER_EMAIL: &str = "auto_gippity@localhost";
+
+// Lines of compiler output quoted in a bug fix commit
+const MAX_BUG_LINES: usize = 20;
+
+// Who commit failures are reported as
+const GIT_HISTORY_AGENT: &str = "Git History";
+
+// e.g. "1760870400-4242", unique per process and second
+pub fn new_run_id() -> String {
+    let secs: u64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    format!("{}-{}", secs, std::process::id())
+}
+
+// GIT_HISTORY=off stops committing the generated project
+pub fn git_history_from_env() -> Result<bool, String> {
+    dotenv().ok();
+
+    match env::var("GIT_HISTORY").as_deref() {
+        Err(_) | Ok("on") => Ok(true),
+        Ok("off") => Ok(false),
+        Ok(other) => Err(format!("Unknown GIT_HISTORY {}, expected on or off", other)),
+    }
+}
+
+#[derive(Debug, Default)]
+struct Step {
+    // Agent and ai_function of the last finished LLM call
+    ai_call: Option<(String, String)>,
+    // Bug count and compiler output the next fix is for
+    bug: Option<(u8, String)>,
+}
+
+// Commits the generated project whenever an agent writes code into it
+pub struct GitHistory {
+    project_dir: PathBuf,
+    run_id: String,
+    step: Mutex<Step>,
+}
+
+impl GitHistory {
+    // A project with its own repository must be clean, the run then commits to a branch of its own
+    pub fn new(project_dir: &Path, run_id: &str) -> Result<Self, String> {
+        let history: GitHistory = Self {
+            project_dir: project_dir.to_path_buf(),
+            run_id: run_id.to_string(),
+            step: Mutex::new(Step::default()),
+        };
+        if project_dir.join(".git").exists() {
+            let status: Output = history.git(&["status", "--porcelain"])?;
+            if !status.stdout.is_empty() {
+                return Err(format!(
+                    "{} has uncommitted changes, commit or stash them before the run commits to it",
+                    project_dir.display()
+                ));
+            }
+            history.git(&["checkout", "--quiet", "-b", &history.branch()])?;
+        }
+        Ok(history)
+    }
+
+    // e.g. "auto_gippity/1760870400-4242"
+    pub fn branch(&self) -> String {
+        format!("{}/{}", COMMITTER_NAME, self.run_id)
+    }
+
+    fn git(&self, args: &[&str]) -> Result<Output, String> {
+        let output: Output = Command::new("git")
+            .args(args)
+            .current_dir(&self.project_dir)
+            .output()
+            .map_err(|e| format!("Could not run git: {}", e))?;
+        match output.status.success() {
+            true => Ok(output),
+            false => Err(format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        }
+    }
+
+    // A project with its own repository keeps it, otherwise one is created
+    fn ensure_repository(&self) -> Result<(), String> {
+        if self.project_dir.join(".git").exists() {
+            return Ok(());
+        }
+        self.git(&["init", "--quiet"])?;
+        let gitignore: PathBuf = self.project_dir.join(".gitignore");
+        if !gitignore.exists() {
+            fs::write(&gitignore, PROJECT_GITIGNORE)
+                .map_err(|e| format!("Could not write {}: {}", gitignore.display(), e))?;
+        }
+        Ok(())
+    }
+
+    pub fn commit(&self, message: &str) -> Result<bool, String> {
+        self.ensure_repository()?;
+        self.git(&["add", "--all"])?;
+
+        // Nothing staged, e.g. the same code written twice
+        if self.git(&["diff", "--cached", "--quiet"]).is_ok() {
+            return Ok(false);
+        }
+        let has_identity: bool = self.git(&["config", "user.email"]).is_ok();
+        let name: String = format!("user.name={}", COMMITTER_NAME);
+        let email: String = format!("user.email={}", COMMITTER_EMAIL);
+        let mut args: Vec<&str> = match has_identity {
+            true => vec![],
+            false => vec!["-c", &name, "-c", &email],
+        };
+        args.extend(["commit", "--quiet", "-m", message]);
+        self.git(&args)?;
+        Ok(true)
+    }
+
+    // Subject names the ai_function that wrote the code, or the agent that finished
+    fn message(&self, step: &Step, finished_agent: Option<&str>) -> String {
+        let (agent, ai_function): (&str, &str) = match (finished_agent, &step.ai_call) {
+            (Some(agent), _) => (agent, "none"),
+            (None, Some((agent, ai_function))) => (agent, ai_function),
+            (None, None) => ("unknown", "none"),
+        };
+        let mut message: String = match finished_agent {
+            Some(agent) => format!("{}: finished", agent),
+            None => format!("{}: {}", agent, ai_function),
+        };
+        if let Some((bug_count, errors)) = &step.bug {
+            let errors: Vec<&str> = errors.lines().take(MAX_BUG_LINES).collect();
+            message = format!(
+                "{}\n\nFixing bug {}:\n{}",
+                message,
+                bug_count,
+                errors.join("\n")
+            );
+        }
+        format!(
+            "{}\n\nRun-Id: {}\nAgent: {}\nAI-Function: {}\n",
+            message, self.run_id, agent, ai_function
+        )
+    }
+
+    fn commit_step(&self, finished_agent: Option<&str>) {
+        let mut step: MutexGuard<Step> = self.step.lock().unwrap();
+        let message: String = self.message(&step, finished_agent);
+        let commit_res: Result<bool, String> = self.commit(&message);
+
+        // The next commit is only credited to an LLM call made after this one
+        step.ai_call = None;
+        drop(step);
+
+        // A failed commit is published as an issue of the run but never stops it
+        if let Err(e) = commit_res {
+            let err_msg: String = format!("Could not commit {}: {}", self.project_dir.display(), e);
+            PrintCommand::Issue.print_agent_message(GIT_HISTORY_AGENT, &err_msg);
+        }
+    }
+}
+
+impl AgentObserver for GitHistory {
+    fn on_event(&self, event: &AgentEvent) {
+        match event {
+            AgentEvent::LLMCallFinished {
+                agent,
+                ai_function,
+                error: None,
+                ..
+            } => {
+                self.step.lock().unwrap().ai_call = Some((agent.clone(), ai_function.clone()));
+            }
+            AgentEvent::BugFound {
+                bug_count, errors, ..
+            } => self.step.lock().unwrap().bug = Some((*bug_count, errors.clone())),
+            AgentEvent::BuildResult { success: true, .. } => self.step.lock().unwrap().bug = None,
+            AgentEvent::CodeWritten { path, .. }
+                if Path::new(path).starts_with(&self.project_dir) =>
+            {
+                self.commit_step(None)
+            }
+            // Whatever else the agent left in the project
+            AgentEvent::AgentFinished { agent, .. } => self.commit_step(Some(agent)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::run_context::{with_run_context, RunContext};
+    use std::sync::Arc;
+
+    fn git_log(project_dir: &Path) -> String {
+        let output: Output = Command::new("git")
+            .args(["log", "--format=%B%x00"])
+            .current_dir(project_dir)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    #[test]
+    fn tests_git_history_commits_each_step() {
+        let project_dir: PathBuf =
+            env::temp_dir().join(format!("git_history_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        let main_path: PathBuf = project_dir.join("src").join("main.rs");
+        let history: GitHistory = GitHistory::new(&project_dir, "run-7").unwrap();
+
+        let write_code = |contents: &str| {
+            fs::write(&main_path, contents).unwrap();
+            history.on_event(&AgentEvent::CodeWritten {
+                path: main_path.to_string_lossy().to_string(),
+                previous: None,
+                contents: contents.to_string(),
+            });
+        };
+
+        history.on_event(&AgentEvent::LLMCallFinished {
+            agent: "Backend Developer".to_string(),
+            ai_function: "print_improved_webserver_code".to_string(),
+            model: None,
+            usage: None,
+            cached: false,
+            elapsed_ms: 0,
+            error: None,
+        });
+        write_code("fn main() { broken }");
+        history.on_event(&AgentEvent::BugFound {
+            agent: "Backend Developer".to_string(),
+            bug_count: 1,
+            errors: "error[E0425]: cannot find value `broken`".to_string(),
+        });
+        history.on_event(&AgentEvent::LLMCallFinished {
+            agent: "Backend Developer".to_string(),
+            ai_function: "print_fixed_code".to_string(),
+            model: None,
+            usage: None,
+            cached: false,
+            elapsed_ms: 0,
+            error: None,
+        });
+        write_code("fn main() {}");
+        write_code("fn main() {}");
+        // Code written without an LLM call is not credited to the last one
+        write_code("fn main() { println!() }");
+
+        let log: String = git_log(&project_dir);
+        let commits: Vec<&str> = log.split('\0').filter(|c| !c.trim().is_empty()).collect();
+        assert_eq!(commits.len(), 3, "{}", log);
+        assert!(commits[0].contains("AI-Function: none"));
+        assert!(commits[1]
+            .trim_start()
+            .starts_with("Backend Developer: print_fixed_code"));
+        assert!(commits[1].contains("Fixing bug 1:\nerror[E0425]"));
+        assert!(commits[1].contains("Run-Id: run-7"));
+        assert!(commits[2].contains("AI-Function: print_improved_webserver_code"));
+        assert!(!commits[2].contains("Fixing bug"));
+        assert!(project_dir.join(".gitignore").is_file());
+        let _ = fs::remove_dir_all(&project_dir);
+    }
+
+    #[test]
+    fn tests_git_history_uses_a_branch_in_existing_repository() {
+        let project_dir: PathBuf =
+            env::temp_dir().join(format!("git_history_repo_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(&project_dir).unwrap();
+        let main_path: PathBuf = project_dir.join("main.rs");
+        fs::write(&main_path, "fn main() {}").unwrap();
+        let setup: GitHistory = GitHistory::new(&project_dir, "setup").unwrap();
+        setup.commit("user code").unwrap();
+        let head = || {
+            let output: Output = setup.git(&["rev-parse", "--abbrev-ref", "HEAD"]).unwrap();
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        let user_branch: String = head();
+
+        // Uncommitted work of the user is never mixed into the run's commits
+        fs::write(&main_path, "fn main() { todo!() }").unwrap();
+        assert!(GitHistory::new(&project_dir, "run-8").is_err());
+        setup
+            .git(&["checkout", "--quiet", "--", "main.rs"])
+            .unwrap();
+
+        let history: GitHistory = GitHistory::new(&project_dir, "run-8").unwrap();
+        fs::write(&main_path, "fn main() { run() }").unwrap();
+        history.on_event(&AgentEvent::CodeWritten {
+            path: main_path.to_string_lossy().to_string(),
+            previous: None,
+            contents: "fn main() { run() }".to_string(),
+        });
+        assert_eq!(head(), "auto_gippity/run-8");
+        assert!(git_log(&project_dir).contains("Run-Id: run-8"));
+
+        let user_log: Output = setup.git(&["log", "--format=%s", &user_branch]).unwrap();
+        assert_eq!(String::from_utf8_lossy(&user_log.stdout), "user code\n");
+        let _ = fs::remove_dir_all(&project_dir);
+    }
+
+    #[tokio::test]
+    async fn tests_failed_commit_is_published() {
+        let project_dir: PathBuf =
+            env::temp_dir().join(format!("git_history_missing_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        let history: GitHistory = GitHistory::new(&project_dir, "run-9").unwrap();
+        let events: Arc<Mutex<Vec<AgentEvent>>> = Arc::new(Mutex::new(vec![]));
+        let recorder: Arc<Mutex<Vec<AgentEvent>>> = events.clone();
+        let context: RunContext = RunContext {
+            observers: vec![Arc::new(move |event: &AgentEvent| {
+                recorder.lock().unwrap().push(event.clone())
+            })],
+            ..RunContext::default()
+        };
+
+        // The project directory is gone, so git cannot run in it
+        with_run_context(context, async {
+            history.on_event(&AgentEvent::AgentFinished {
+                agent: "Backend Developer".to_string(),
+                error: None,
+            })
+        })
+        .await;
+
+        let events: Vec<AgentEvent> = events.lock().unwrap().clone();
+        assert!(
+            matches!(
+                events.as_slice(),
+                [AgentEvent::Message { agent, kind: PrintCommand::Issue, message }]
+                    if agent == GIT_HISTORY_AGENT && message.starts_with("Could not commit")
+            ),
+            "{:?}",
+            events
+        );
+    }
+}
@@ -1,2 +1,18 @@
+pub mod auth_check;
+pub mod backend_templates;
+pub mod clarify;
 pub mod command_line;
-pub mod general;
\ No newline at end of file
+pub mod eval;
+pub mod events;
+pub mod general;
+pub mod git_history;
+pub mod openapi;
+pub mod persistence;
+pub mod project_import;
+pub mod prompt_library;
+pub mod review;
+pub mod route_extractor;
+pub mod run_context;
+#[cfg(test)]
+pub mod stub_server;
+pub mod ts_client;
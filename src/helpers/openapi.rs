@@ -0,0 +1,383 @@
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::api_schema::{PathTemplate, Shape};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+const OPENAPI_VERSION: &str = "3.1.0";
+const HTTP_METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+// Path item fields that are not operations
+const PATH_ITEM_FIELDS: [&str; 5] = ["$ref", "summary", "description", "servers", "parameters"];
+
+// Convert the extracted endpoint schema into an OpenAPI 3.1 document.
+// A method and path given twice is reported, not overwritten.
+pub fn routes_to_openapi(routes: &Vec<RouteObject>, title: &str) -> Result<Value, Vec<String>> {
+    let mut paths: Map<String, Value> = Map::new();
+    let mut errors: Vec<String> = vec![];
+
+    for route_object in routes {
+        let method: &str = route_object.method.as_str();
+
+        // Path item keyed without any actix regex constraints
+        let path: String = openapi_path(&route_object.route);
+        if paths
+            .get(&path)
+            .is_some_and(|path_item| path_item.get(method).is_some())
+        {
+            errors.push(format!("{} {}: duplicate operation", method, path));
+            continue;
+        }
+
+        // Build operation
+        let mut operation: Map<String, Value> = Map::new();
+        operation.insert(
+            "operationId".to_string(),
+            json!(operation_id(method, &route_object.route)),
+        );
+
+        if route_object.route.is_dynamic() {
+            let parameters: Vec<Value> = route_object
+                .route
+                .params()
+                .iter()
+                .map(|name| {
+                    json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": param_schema(route_object, name)
+                    })
+                })
+                .collect();
+            operation.insert("parameters".to_string(), json!(parameters));
+        }
+
+        if let Some(shape) = &route_object.request_body {
+            operation.insert(
+                "requestBody".to_string(),
+                json!({
+                    "required": true,
+                    "content": { "application/json": { "schema": shape_to_schema(shape) } }
+                }),
+            );
+        }
+
+        let ok_response: Value = match &route_object.response {
+            Some(shape) => json!({
+                "description": "Successful response",
+                "content": { "application/json": { "schema": shape_to_schema(shape) } }
+            }),
+            None => json!({ "description": "Successful response" }),
+        };
+        let mut responses: Value = json!({ "200": ok_response });
+
+        // Protected routes take the session token from login
+        if route_object.requires_auth {
+            responses["401"] = json!({ "description": "Missing or invalid session token" });
+            operation.insert("security".to_string(), json!([{ "bearerAuth": [] }]));
+        }
+        operation.insert("responses".to_string(), responses);
+
+        // Merge into path item
+        let path_item: &mut Value = paths.entry(path).or_insert_with(|| json!({}));
+        path_item[method] = Value::Object(operation);
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut doc: Value = json!({
+        "openapi": OPENAPI_VERSION,
+        "info": { "title": title, "version": "0.1.0" },
+        "paths": paths
+    });
+    if routes.iter().any(|route_object| route_object.requires_auth) {
+        doc["components"] = json!({
+            "securitySchemes": { "bearerAuth": { "type": "http", "scheme": "bearer" } }
+        });
+    }
+    Ok(doc)
+}
+
+// A digits only constraint or a numeric body field of the same name types the param,
+// i.e. "{id:\\d+}" or {"id": "number"}
+fn param_schema(route_object: &RouteObject, param: &str) -> Value {
+    let placeholder: &str = route_object.route.placeholder(param).unwrap_or_default();
+    if ["\\d+}", "[0-9]+}"]
+        .iter()
+        .any(|pattern| placeholder.ends_with(pattern))
+    {
+        return json!({ "type": "integer" });
+    }
+    let field: Option<&Shape> = [&route_object.response, &route_object.request_body]
+        .into_iter()
+        .find_map(|shape| match shape {
+            Some(Shape::Object(fields)) => fields.get(param),
+            Some(Shape::Array(item)) => match item.as_ref() {
+                Shape::Object(fields) => fields.get(param),
+                _ => None,
+            },
+            _ => None,
+        });
+    match field {
+        Some(shape @ (Shape::Number | Shape::Integer | Shape::Bool)) => shape_to_schema(shape),
+        _ => json!({ "type": "string" }),
+    }
+}
+
+// Convert a body shape into a JSON Schema
+fn shape_to_schema(shape: &Shape) -> Value {
+    match shape {
+        Shape::String => json!({ "type": "string" }),
+        Shape::Number => json!({ "type": "number" }),
+        Shape::Integer => json!({ "type": "integer" }),
+        Shape::Bool => json!({ "type": "boolean" }),
+        Shape::Any => json!({}),
+        Shape::Array(item) => json!({ "type": "array", "items": shape_to_schema(item) }),
+        Shape::Object(fields) => {
+            let properties: Map<String, Value> = fields
+                .iter()
+                .map(|(name, field)| (name.clone(), shape_to_schema(field)))
+                .collect();
+            let required: Vec<&String> = fields.keys().collect();
+            json!({
+                "type": "object",
+                "properties": properties,
+                "required": required
+            })
+        }
+    }
+}
+
+// i.e. "/task/{id:\\d+}" -> "/task/{id}"
+pub fn openapi_path(route: &PathTemplate) -> String {
+    let mut path: String = route.as_str().to_string();
+    for param in route.params() {
+        if let Some(placeholder) = route.placeholder(param) {
+            path = path.replace(placeholder, &format!("{{{}}}", param));
+        }
+    }
+    path
+}
+
+// Derive a stable operation id, i.e. get "/task/{id:\\d+}" -> "get_task_by_id"
+pub fn operation_id(method: &str, route: &PathTemplate) -> String {
+    let mut parts: Vec<String> = vec![method.to_string()];
+    for segment in openapi_path(route).split('/').filter(|s| !s.is_empty()) {
+        if segment.starts_with('{') && segment.ends_with('}') {
+            parts.push(format!(
+                "by_{}",
+                segment.trim_matches(|c| c == '{' || c == '}')
+            ));
+        } else {
+            parts.push(segment.replace(|c: char| !c.is_ascii_alphanumeric(), "_"));
+        }
+    }
+    if parts.len() == 1 {
+        parts.push("root".to_string());
+    }
+    parts.join("_")
+}
+
+// Structurally validate an OpenAPI 3.1 document
+pub fn validate_openapi(doc: &Value) -> Result<(), Vec<String>> {
+    let mut errors: Vec<String> = vec![];
+
+    match doc["openapi"].as_str() {
+        Some(version) if version.starts_with("3.1") => {}
+        _ => errors.push("'openapi' must be a 3.1.x version string".to_string()),
+    }
+    if !doc["info"]["title"].is_string() {
+        errors.push("'info.title' must be a string".to_string());
+    }
+    if !doc["info"]["version"].is_string() {
+        errors.push("'info.version' must be a string".to_string());
+    }
+
+    let paths: &Map<String, Value> = match doc["paths"].as_object() {
+        Some(paths) => paths,
+        None => {
+            errors.push("'paths' must be an object".to_string());
+            return Err(errors);
+        }
+    };
+
+    let mut operation_ids: HashSet<String> = HashSet::new();
+    for (path, path_item) in paths {
+        if !path.starts_with('/') {
+            errors.push(format!("path '{}' must start with '/'", path));
+        }
+        let declared_params: Vec<String> = PathTemplate::parse(path).params().clone();
+
+        let operations: &Map<String, Value> = match path_item.as_object() {
+            Some(operations) => operations,
+            None => {
+                errors.push(format!("path item '{}' must be an object", path));
+                continue;
+            }
+        };
+        // Parameters on the path item apply to each of its operations
+        let shared_params: Vec<&Value> = path_item["parameters"]
+            .as_array()
+            .map(|params| params.iter().collect())
+            .unwrap_or_default();
+
+        for (method, operation) in operations {
+            let location: String = format!("{} {}", method, path);
+            if PATH_ITEM_FIELDS.contains(&method.as_str()) {
+                continue;
+            }
+            if !HTTP_METHODS.contains(&method.as_str()) {
+                errors.push(format!("{}: unknown HTTP method", location));
+                continue;
+            }
+            if !operation["responses"].is_object()
+                || operation["responses"].as_object().unwrap().is_empty()
+            {
+                errors.push(format!("{}: at least one response is required", location));
+            }
+            if let Some(op_id) = operation["operationId"].as_str() {
+                if !operation_ids.insert(op_id.to_string()) {
+                    errors.push(format!("{}: duplicate operationId '{}'", location, op_id));
+                }
+            }
+
+            // Every templated segment needs a required path parameter
+            let mut params: Vec<&Value> = operation["parameters"]
+                .as_array()
+                .map(|params| params.iter().collect())
+                .unwrap_or_default();
+            params.extend(shared_params.iter().copied());
+            for name in &declared_params {
+                let is_declared: bool = params.iter().any(|param| {
+                    param["in"] == "path"
+                        && param["name"] == name.as_str()
+                        && param["required"] == true
+                });
+                if !is_declared {
+                    errors.push(format!(
+                        "{}: missing required path parameter '{}'",
+                        location, name
+                    ));
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_routes_to_openapi() {
+        let routes_str: &str = r#"
+      [
+        {
+          "route": "/task/{id}",
+          "is_route_dynamic": "true",
+          "method": "get",
+          "request_body": "None",
+          "response": { "id": "number", "name": "string", "complete": "bool" }
+        },
+        {
+          "route": "/task",
+          "is_route_dynamic": "false",
+          "method": "post",
+          "request_body": { "id": "number", "name": "string", "complete": "bool" },
+          "response": "not_provided",
+          "requires_auth": "true"
+        }
+      ]"#;
+        let routes: Vec<RouteObject> = serde_json::from_str(routes_str).unwrap();
+
+        let doc: Value = routes_to_openapi(&routes, "Test API").unwrap();
+        validate_openapi(&doc).expect("Generated OpenAPI document should be valid");
+
+        let get_op: &Value = &doc["paths"]["/task/{id}"]["get"];
+        assert_eq!(get_op["parameters"][0]["name"], "id");
+        assert_eq!(
+            get_op["responses"]["200"]["content"]["application/json"]["schema"]["properties"]
+                ["complete"]["type"],
+            "boolean"
+        );
+        assert!(doc["paths"]["/task"]["post"]["requestBody"].is_object());
+        assert_eq!(
+            doc["paths"]["/task"]["post"]["security"][0]["bearerAuth"],
+            json!([])
+        );
+        assert!(get_op.get("security").is_none());
+        assert_eq!(
+            doc["components"]["securitySchemes"]["bearerAuth"]["scheme"],
+            "bearer"
+        );
+    }
+
+    #[test]
+    fn tests_validate_openapi_missing_path_param() {
+        let doc: Value = json!({
+            "openapi": "3.1.0",
+            "info": { "title": "Broken", "version": "0.1.0" },
+            "paths": { "/task/{id}": { "get": { "responses": { "200": { "description": "ok" } } } } }
+        });
+        let errors: Vec<String> = validate_openapi(&doc).unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn tests_validate_openapi_path_item_fields() {
+        let doc: Value = json!({
+            "openapi": "3.1.0",
+            "info": { "title": "Shared", "version": "0.1.0" },
+            "paths": { "/task/{id}": {
+                "summary": "One task",
+                "description": "A task by id",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "get": { "responses": { "200": { "description": "ok" } } }
+            } }
+        });
+        assert_eq!(validate_openapi(&doc), Ok(()));
+    }
+
+    #[test]
+    fn tests_path_param_types() {
+        let routes_str: &str = r#"[
+          {"route": "/task/{id}", "is_route_dynamic": "true", "method": "get", "request_body": "None", "response": {"id": "number", "name": "string"}},
+          {"route": "/note/{id:\\d+}", "is_route_dynamic": "true", "method": "get", "request_body": "None", "response": "None"},
+          {"route": "/user/{name}", "is_route_dynamic": "true", "method": "get", "request_body": "None", "response": {"name": "string"}}
+        ]"#;
+        let routes: Vec<RouteObject> = serde_json::from_str(routes_str).unwrap();
+        let doc: Value = routes_to_openapi(&routes, "Test API").unwrap();
+        let param_type = |path: &str| doc["paths"][path]["get"]["parameters"][0]["schema"].clone();
+        assert_eq!(param_type("/task/{id}"), json!({ "type": "number" }));
+        assert_eq!(param_type("/note/{id}"), json!({ "type": "integer" }));
+        assert_eq!(param_type("/user/{name}"), json!({ "type": "string" }));
+        assert_eq!(
+            doc["paths"]["/note/{id}"]["get"]["operationId"],
+            "get_note_by_id"
+        );
+    }
+
+    #[test]
+    fn tests_duplicate_operation_is_reported() {
+        let routes_str: &str = r#"[
+          {"route": "/task/{id}", "method": "get", "request_body": "None", "response": "None"},
+          {"route": "/task", "method": "get", "request_body": "None", "response": "None"},
+          {"route": "/task/{id:\\d+}", "method": "get", "request_body": "None", "response": "None"}
+        ]"#;
+        let routes: Vec<RouteObject> = serde_json::from_str(routes_str).unwrap();
+        assert_eq!(
+            routes_to_openapi(&routes, "Test API"),
+            Err(vec!["get /task/{id}: duplicate operation".to_string()])
+        );
+    }
+}
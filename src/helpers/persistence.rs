@@ -0,0 +1,155 @@
+use crate::helpers::auth_check::with_token;
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::api_schema::HttpMethod;
+use reqwest::Client;
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Written into every string field of the record the check posts
+pub const PERSISTENCE_MARKER: &str = "persistence-check";
+
+// Record written through the api before a restart and expected back after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersistenceProbe {
+    pub route: String,
+    // PERSISTENCE_MARKER with the nonce, records an earlier run left behind do not match
+    pub marker: String,
+    pub body: Value,
+}
+
+// Different for every check, small enough for an i32 id column
+pub fn probe_nonce() -> u32 {
+    let nanos: u128 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    (nanos % 1_000_000_000) as u32
+}
+
+// A static route taking a POST body whose GET lists what was posted, e.g. "/task"
+pub fn persistence_probe(routes: &[RouteObject], nonce: u32) -> Option<PersistenceProbe> {
+    let marker: String = format!("{}-{}", PERSISTENCE_MARKER, nonce);
+    routes
+        .iter()
+        .filter(|create| create.method == HttpMethod::Post && !create.route.is_dynamic())
+        .find_map(|create| {
+            let body: Value = create.request_body.as_ref()?.sample(&marker, nonce);
+            routes
+                .iter()
+                .any(|read| read.method == HttpMethod::Get && read.route == create.route)
+                .then(|| PersistenceProbe {
+                    route: create.route.to_string(),
+                    marker: marker.clone(),
+                    body,
+                })
+        })
+}
+
+// Whether a GET response holds the record, found by its marker or as the exact body
+pub fn contains_probe(response: &str, probe: &PersistenceProbe) -> bool {
+    fn contains_value(value: &Value, wanted: &Value) -> bool {
+        value == wanted
+            || match value {
+                Value::Array(items) => items.iter().any(|item| contains_value(item, wanted)),
+                Value::Object(fields) => fields.values().any(|field| contains_value(field, wanted)),
+                _ => false,
+            }
+    }
+
+    response.contains(&probe.marker)
+        || serde_json::from_str::<Value>(response)
+            .is_ok_and(|value| contains_value(&value, &probe.body))
+}
+
+// Post the record and make sure the server lists it, signed in when the route needs it
+pub async fn write_probe(
+    client: &Client,
+    base_url: &str,
+    probe: &PersistenceProbe,
+    token: Option<&str>,
+) -> Result<(), String> {
+    let url: String = format!("{}{}", base_url, probe.route);
+    let response: reqwest::Response = with_token(client.post(&url), token)
+        .json(&probe.body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "POST {} returned {}",
+            probe.route,
+            response.status().as_u16()
+        ));
+    }
+
+    match read_probe(client, base_url, probe, token).await? {
+        true => Ok(()),
+        false => Err(format!(
+            "GET {} did not list the record just posted",
+            probe.route
+        )),
+    }
+}
+
+pub async fn read_probe(
+    client: &Client,
+    base_url: &str,
+    probe: &PersistenceProbe,
+    token: Option<&str>,
+) -> Result<bool, String> {
+    let url: String = format!("{}{}", base_url, probe.route);
+    let response: String = with_token(client.get(&url), token)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(contains_probe(&response, probe))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::stub_server::{StubResponse, StubServer};
+
+    const ROUTES: &str = r#"[
+      {"route": "/task/{id}", "is_route_dynamic": "true", "method": "get", "request_body": "None", "response": "None"},
+      {"route": "/register", "is_route_dynamic": "false", "method": "post", "request_body": {"username": "string"}, "response": "None"},
+      {"route": "/task", "is_route_dynamic": "false", "method": "post", "request_body": {"id": "number", "name": "string", "complete": "bool"}, "response": "None"},
+      {"route": "/task", "is_route_dynamic": "false", "method": "get", "request_body": "None", "response": "None"}
+    ]"#;
+
+    #[tokio::test]
+    async fn tests_persistence_probe_round_trip() {
+        let routes: Vec<RouteObject> = serde_json::from_str(ROUTES).unwrap();
+        let probe: PersistenceProbe = persistence_probe(&routes, 4242).unwrap();
+        assert_eq!(probe.route, "/task");
+        assert_eq!(
+            probe.body,
+            serde_json::json!({"id": 4242, "name": "persistence-check-4242", "complete": true})
+        );
+
+        // A record from an earlier check does not count for this one
+        let earlier: PersistenceProbe = persistence_probe(&routes, 7).unwrap();
+        assert!(!contains_probe(&format!("[{}]", earlier.body), &probe));
+
+        let server: StubServer = StubServer::start(vec![(
+            "/task",
+            vec![
+                StubResponse::new(200, ""),
+                StubResponse::json(200, &format!("[{}]", probe.body)),
+                StubResponse::json(200, "[]"),
+            ],
+        )])
+        .await;
+        let base_url: String = server.url("");
+        let client: Client = Client::new();
+
+        assert_eq!(write_probe(&client, &base_url, &probe, None).await, Ok(()));
+        assert_eq!(
+            read_probe(&client, &base_url, &probe, Some("abc")).await,
+            Ok(false)
+        );
+        assert_eq!(server.requests()[0].method, "POST");
+    }
+}
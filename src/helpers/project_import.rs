@@ -0,0 +1,367 @@
+use crate::ai_functions::aifunc_backend::print_rest_api_endpoints;
+use crate::apis::cache::response_cache_from_env;
+use crate::apis::llm_config::LLMConfig;
+use crate::apis::provider::{provider_from_env, LLMProvider};
+use crate::apis::usage::Budget;
+use crate::helpers::backend_templates::{BackendTemplate, Storage, ACTIX_JSON, AXUM_JSON};
+use crate::helpers::general::{
+    ai_task_request_with_memory, save_api_endpoints, save_factsheet, save_openapi_schema,
+    save_typescript_client, Workspace,
+};
+use crate::helpers::prompt_library::PromptLibrary;
+use crate::helpers::route_extractor::{extract_routes, extract_structs, StructDef};
+use crate::helpers::run_context::{new_usage_tracker, with_run_context, RunContext};
+use crate::models::agent_basic::basic_agent::BasicAgent;
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{FactSheet, ProjectScope, Requirements, RouteObject};
+use crate::models::general::api_schema::HttpMethod;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Crates that mean the project keeps its data in SQLite
+const SQLITE_CRATES: [&str; 2] = ["rusqlite", "sqlx"];
+
+// What an import found
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedProject {
+    pub factsheet: FactSheet,
+    pub routes: Vec<RouteObject>,
+}
+
+// Value of a `key = "value"` line in the [package] section
+fn package_field(cargo_toml: &str, key: &str) -> Option<String> {
+    let mut in_package: bool = false;
+    for line in cargo_toml.lines().map(|line| line.trim()) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            continue;
+        }
+        let (name, value) = match line.split_once('=') {
+            Some(field) if in_package => field,
+            _ => continue,
+        };
+        if name.trim() == key {
+            return Some(value.trim().trim_matches('"').to_string());
+        }
+    }
+    None
+}
+
+fn has_dependency(cargo_toml: &str, name: &str) -> bool {
+    cargo_toml.lines().any(|line| {
+        line.trim()
+            .strip_prefix(name)
+            .is_some_and(|rest| rest.trim_start().starts_with(['=', '.']))
+    })
+}
+
+// The template of the project's framework, on SQLite when the project uses it
+pub fn imported_template(cargo_toml: &str) -> Result<BackendTemplate, String> {
+    let base: BackendTemplate = if has_dependency(cargo_toml, "actix-web") {
+        ACTIX_JSON
+    } else if has_dependency(cargo_toml, "axum") {
+        AXUM_JSON
+    } else {
+        return Err("Only actix-web and axum projects can be imported".to_string());
+    };
+    let storage: Storage = match SQLITE_CRATES
+        .iter()
+        .any(|name| has_dependency(cargo_toml, name))
+    {
+        true => Storage::Sqlite,
+        false => Storage::JsonFile,
+    };
+
+    // The framework matters more than the storage when no template has both
+    Ok(base.with_storage(storage).unwrap_or(base))
+}
+
+// Every .rs file under src, in path order
+fn rust_sources(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Could not read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+
+    let mut sources: Vec<PathBuf> = vec![];
+    for path in entries {
+        if path.is_dir() {
+            sources.extend(rust_sources(&path)?);
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            sources.push(path);
+        }
+    }
+    Ok(sources)
+}
+
+// Migrations the project applies, in name order, without the seed data
+fn read_migrations(project_dir: &Path) -> Option<String> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(project_dir.join("migrations"))
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "sql"))
+        .filter(|path| path.file_name().is_none_or(|name| name != "seed.sql"))
+        .collect();
+    paths.sort();
+    let migrations: Vec<String> = paths
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .collect();
+    match migrations.is_empty() {
+        true => None,
+        false => Some(migrations.join("\n")),
+    }
+}
+
+// Routes the static extractor missed, e.g. built by macros, are read by the LLM
+async fn extract_routes_with_llm(
+    template: &BackendTemplate,
+    source: &str,
+) -> Result<Vec<RouteObject>, String> {
+    let mut attributes: BasicAgent = BasicAgent::new(
+        "Reads the routes of an existing backend".to_string(),
+        "Project Importer".to_string(),
+    );
+    let msg_context: String = format!(
+        "FRAMEWORK: {} \n ROUTE_SYNTAX: {} \n CODE_INPUT: {}",
+        template.framework.name(),
+        template.framework.route_syntax(),
+        source
+    );
+    let api_endpoints_str: String = ai_task_request_with_memory(
+        msg_context,
+        &mut attributes,
+        get_function_string!(print_rest_api_endpoints),
+        print_rest_api_endpoints,
+        &[],
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    serde_json::from_str(&api_endpoints_str).map_err(|e| format!("Could not read routes: {}", e))
+}
+
+fn route_label(route_object: &RouteObject) -> String {
+    format!(
+        "{} {}",
+        route_object.method.as_str().to_uppercase(),
+        route_object.route
+    )
+}
+
+// Factsheet of an existing cargo project, so agents change its code instead of a template
+pub async fn import_project(project_dir: &Path) -> Result<ImportedProject, String> {
+    let cargo_path: PathBuf = project_dir.join("Cargo.toml");
+    let cargo_toml: String = fs::read_to_string(&cargo_path)
+        .map_err(|e| format!("Could not read {}: {}", cargo_path.display(), e))?;
+    let template: BackendTemplate = imported_template(&cargo_toml)?;
+
+    let main_path: PathBuf = project_dir.join("src").join("main.rs");
+    let backend_code: String = fs::read_to_string(&main_path)
+        .map_err(|e| format!("Could not read {}: {}", main_path.display(), e))?;
+    let sources: Vec<PathBuf> = rust_sources(&project_dir.join("src"))?;
+
+    // Agents rewrite main.rs only and leave auth.rs alone, code in other modules would go stale
+    let auth_path: PathBuf = project_dir.join("src").join("auth.rs");
+    let modules: Vec<String> = sources
+        .iter()
+        .filter(|path| **path != main_path && **path != auth_path)
+        .map(|path| path.display().to_string())
+        .collect();
+    if !modules.is_empty() {
+        return Err(format!(
+            "Only projects with their code in src/main.rs, and src/auth.rs for login, can be imported, found {}",
+            modules.join(", ")
+        ));
+    }
+    let mut source: String = String::new();
+    for path in sources {
+        source.push_str(&fs::read_to_string(&path).unwrap_or_default());
+        source.push('\n');
+    }
+
+    let mut routes: Vec<RouteObject> = extract_routes(&source);
+    if routes.is_empty() {
+        routes = extract_routes_with_llm(&template, &source).await?;
+    }
+    let data_entities: Vec<String> = extract_structs(&source)
+        .iter()
+        .filter(|def| def.is_serde)
+        .map(StructDef::entity)
+        .collect();
+    let features: Vec<String> = routes.iter().map(route_label).collect();
+
+    let name: String = package_field(&cargo_toml, "name").unwrap_or("backend".to_string());
+    let project_description: String = match package_field(&cargo_toml, "description") {
+        Some(description) => description,
+        None => format!(
+            "build a website backend called {} that serves {}",
+            name,
+            features.join(", ")
+        ),
+    };
+
+    // The project's own login code is left alone, only the auth module scaffolds login
+    let has_login: bool = routes
+        .iter()
+        .any(|route_object| route_object.route.as_str().ends_with("/login"));
+    let project_scope: ProjectScope = ProjectScope {
+        is_crud_required: routes
+            .iter()
+            .any(|route_object| route_object.method != HttpMethod::Get),
+        is_user_login_and_logout: has_login && project_dir.join("src").join("auth.rs").is_file(),
+        is_external_urls_required: has_dependency(&cargo_toml, "reqwest"),
+    };
+
+    let factsheet: FactSheet = FactSheet {
+        project_description: project_description.clone(),
+        requirements: Some(Requirements {
+            summary: project_description,
+            features,
+            data_entities,
+            constraints: vec!["existing routes keep their paths and bodies".to_string()],
+            ..Requirements::default()
+        }),
+        project_scope: Some(project_scope),
+        external_urls: None,
+        external_url_probes: None,
+        backend_template: Some(template.name.to_string()),
+        database_migration: read_migrations(project_dir),
+        change_request: None,
+        backend_code: Some(backend_code),
+        // Like the backend developer, the factsheet keeps the routes an amend run must not lose
+        api_endpoint_schema: Some(routes.clone()),
+    };
+    Ok(ImportedProject { factsheet, routes })
+}
+
+// e.g. `cargo run -- import ../my_service`, then change it with
+// `PROJECT_DIR=../my_service cargo run -- amend "..."`
+pub async fn import_command(args: &[String]) -> Result<String, String> {
+    let usage: &str = "Usage: import <project_dir>";
+    let project_dir: PathBuf = match args {
+        [project_dir] => PathBuf::from(project_dir),
+        _ => return Err(usage.to_string()),
+    };
+    let provider: Arc<dyn LLMProvider> = provider_from_env()?;
+    let workspace: Workspace = Workspace::for_project(&project_dir, None);
+    if let Some(schema_dir) = workspace.api_schema_path.parent() {
+        fs::create_dir_all(schema_dir)
+            .map_err(|e| format!("Could not create {}: {}", schema_dir.display(), e))?;
+    }
+
+    let context: RunContext = RunContext {
+        provider,
+        workspace,
+        llm_config: Arc::new(LLMConfig::from_env()?),
+        prompts: Arc::new(PromptLibrary::from_env()?),
+        response_cache: response_cache_from_env().map(Arc::new),
+        usage: new_usage_tracker(Budget::from_env()),
+        ..RunContext::default()
+    };
+    with_run_context(context, async move {
+        let imported: ImportedProject = import_project(&project_dir).await?;
+        let factsheet_path: PathBuf = save_factsheet(&imported.factsheet);
+        let api_endpoints_str: String =
+            serde_json::to_string_pretty(&imported.routes).map_err(|e| e.to_string())?;
+        save_api_endpoints(&api_endpoints_str);
+        save_typescript_client(&imported.routes);
+        if let Err(schema_errors) = save_openapi_schema(&imported.routes) {
+            eprintln!(
+                "WARNING: Generated OpenAPI schema is invalid: {}",
+                schema_errors.join("; ")
+            );
+        }
+
+        Ok(format!(
+            "Imported {} routes of a {} project from {}, factsheet saved to {}",
+            imported.routes.len(),
+            imported
+                .factsheet
+                .backend_template
+                .as_deref()
+                .unwrap_or_default(),
+            project_dir.display(),
+            factsheet_path.display()
+        ))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::backend_templates::AXUM_AUTH;
+    use crate::helpers::general::save_auth_module;
+    use std::env;
+
+    const CARGO_TOML: &str = "[package]\nname = \"todo_service\"\nversion = \"0.1.0\"\n\n[dependencies]\nactix-web = \"4\"\nserde = { version = \"1\", features = [\"derive\"] }\n";
+
+    #[tokio::test]
+    async fn tests_import_project() {
+        let project_dir: PathBuf = env::temp_dir().join(format!("import_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::create_dir_all(project_dir.join("migrations")).unwrap();
+        fs::write(project_dir.join("Cargo.toml"), CARGO_TOML).unwrap();
+        fs::write(
+            project_dir.join("src").join("main.rs"),
+            include_str!("../code_template.rs"),
+        )
+        .unwrap();
+        fs::write(
+            project_dir.join("migrations").join("0001_init.sql"),
+            "CREATE TABLE tasks;",
+        )
+        .unwrap();
+        fs::write(
+            project_dir.join("migrations").join("seed.sql"),
+            "INSERT INTO tasks;",
+        )
+        .unwrap();
+
+        let imported: ImportedProject = import_project(&project_dir).await.unwrap();
+        assert_eq!(imported.routes.len(), 7);
+        let factsheet: FactSheet = imported.factsheet;
+        assert_eq!(factsheet.backend_template.as_deref(), Some("actix_json"));
+        assert!(factsheet
+            .project_description
+            .starts_with("build a website backend called todo_service that serves POST /task"));
+        assert_eq!(
+            factsheet.database_migration.as_deref(),
+            Some("CREATE TABLE tasks;")
+        );
+        let requirements: Requirements = factsheet.requirements.unwrap();
+        assert_eq!(requirements.features[0], "POST /task");
+        assert!(requirements
+            .data_entities
+            .contains(&"task: id, name, complete".to_string()));
+        let scope: ProjectScope = factsheet.project_scope.unwrap();
+        assert!(scope.is_crud_required && !scope.is_user_login_and_logout);
+        assert_eq!(factsheet.api_endpoint_schema, Some(imported.routes));
+
+        // A login project keeps its own auth.rs, other modules are refused
+        let auth_path: PathBuf = project_dir.join("src").join("auth.rs");
+        fs::write(&auth_path, "// own auth").unwrap();
+        assert!(import_project(&project_dir).await.is_ok());
+        let context: RunContext = RunContext {
+            workspace: Workspace::for_project(&project_dir, None),
+            observers: vec![],
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+        with_run_context(context, async { save_auth_module(AXUM_AUTH.source) }).await;
+        assert_eq!(fs::read_to_string(&auth_path).unwrap(), "// own auth");
+        fs::write(project_dir.join("src").join("handlers.rs"), "").unwrap();
+        assert!(import_project(&project_dir)
+            .await
+            .unwrap_err()
+            .contains("handlers.rs"));
+
+        let axum_sqlite: &str = "[dependencies]\naxum = \"0.6\"\nrusqlite = \"0.29\"\n";
+        assert_eq!(imported_template(axum_sqlite).unwrap().name, "axum_json");
+        assert!(imported_template("[dependencies]\nwarp = \"0.3\"\n").is_err());
+        let _ = fs::remove_dir_all(&project_dir);
+    }
+}
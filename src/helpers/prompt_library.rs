@@ -0,0 +1,313 @@
+use crate::ai_functions::ai_function_by_name;
+use crate::models::general::llm::Message;
+use dotenv::dotenv;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_PROMPT_DIR: &str = "prompts";
+const PROMPT_EXTENSION: &str = "prompt";
+
+// File name of the override for the wrapper used by every ai_function without its own template
+const DEFAULT_TEMPLATE_NAME: &str = "default";
+
+// Variables a template may use
+const TEMPLATE_VARIABLES: [&str; 3] = ["ai_function", "function_definition", "input"];
+
+// The "function printer" instruction wrapped around every ai_function doc comment
+const BUILTIN_TEMPLATE: &str = "FUNCTION: {{function_definition}}
+  INSTRUCTION: You are a function printer. You ONLY print the results of functions.
+  Nothing else. No commentary. Here is the input to the function: {{input}}.
+  Print out what the function will return.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub version: String,
+    pub body: String,
+}
+
+impl PromptTemplate {
+    // Optional front matter sets the version, otherwise it is derived from the content:
+    // ---
+    // version: scope-v2
+    // ---
+    pub fn parse(name: &str, raw: &str, version_prefix: &str) -> Result<Self, String> {
+        let raw: String = raw.replace("\r\n", "\n");
+        let (version, body): (Option<String>, String) = match raw.strip_prefix("---\n") {
+            Some(rest) => {
+                let (header, body) = rest
+                    .split_once("\n---\n")
+                    .ok_or(format!("{}: front matter is not closed with ---", name))?;
+                let version: Option<String> = header
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(key, _)| key.trim() == "version")
+                    .map(|(_, value)| value.trim().to_string());
+                (version, body.to_string())
+            }
+            None => (None, raw),
+        };
+
+        // Reject variables the renderer would not fill in
+        let unknown: Vec<String> = template_variables(&body)
+            .into_iter()
+            .filter(|variable| !TEMPLATE_VARIABLES.contains(&variable.as_str()))
+            .collect();
+        if !unknown.is_empty() {
+            return Err(format!(
+                "{}: unknown template variables {{{{{}}}}}, expected one of {}",
+                name,
+                unknown.join("}}, {{"),
+                TEMPLATE_VARIABLES.join(", ")
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            version: version.unwrap_or(format!("{}-{}", version_prefix, content_hash(&body))),
+            body,
+        })
+    }
+
+    pub fn render(&self, variables: &BTreeMap<&str, &str>) -> String {
+        render_template(&self.body, variables)
+    }
+}
+
+// Prompt sent for an ai_function and the template and definition versions it came from
+#[derive(Debug, Clone)]
+pub struct RenderedPrompt {
+    pub message: Message,
+    pub version: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PromptLibrary {
+    pub default_template: PromptTemplate,
+    pub overrides: BTreeMap<String, PromptTemplate>,
+}
+
+impl PromptLibrary {
+    pub fn builtin() -> Self {
+        Self {
+            default_template: PromptTemplate::parse(
+                DEFAULT_TEMPLATE_NAME,
+                BUILTIN_TEMPLATE,
+                "builtin",
+            )
+            .expect("Built-in prompt template is invalid"),
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    // Every <ai_function>.prompt in the directory replaces that function's prompt,
+    // default.prompt replaces the wrapper used by the rest
+    pub fn load_dir(dir: &Path) -> Result<Self, String> {
+        let mut library: PromptLibrary = Self::builtin();
+        let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path: PathBuf = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(PROMPT_EXTENSION) {
+                continue;
+            }
+            let name: String = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string();
+            if name != DEFAULT_TEMPLATE_NAME && ai_function_by_name(&name).is_none() {
+                return Err(format!("{}: no ai_function named {}", path.display(), name));
+            }
+
+            let raw: String =
+                fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let template: PromptTemplate = PromptTemplate::parse(&name, &raw, "file")?;
+            if name == DEFAULT_TEMPLATE_NAME {
+                library.default_template = template;
+            } else {
+                library.overrides.insert(name, template);
+            }
+        }
+        Ok(library)
+    }
+
+    // PROMPT_DIR, defaults to ./prompts. Without the directory only built-in prompts are used.
+    pub fn from_env() -> Result<Self, String> {
+        dotenv().ok();
+
+        let dir: PathBuf =
+            PathBuf::from(env::var("PROMPT_DIR").unwrap_or(DEFAULT_PROMPT_DIR.to_string()));
+        if dir.is_dir() {
+            Self::load_dir(&dir)
+        } else {
+            Ok(Self::builtin())
+        }
+    }
+
+    pub fn render(
+        &self,
+        ai_function: &str,
+        function_definition: &str,
+        input: &str,
+    ) -> RenderedPrompt {
+        let template: &PromptTemplate = self
+            .overrides
+            .get(ai_function)
+            .unwrap_or(&self.default_template);
+        let variables: BTreeMap<&str, &str> = BTreeMap::from([
+            ("ai_function", ai_function),
+            ("function_definition", function_definition),
+            ("input", input),
+        ]);
+
+        RenderedPrompt {
+            message: Message {
+                role: "system".to_string(),
+                content: template.render(&variables),
+            },
+            // The ai_function's doc comment is part of the prompt, so its hash is part of the version
+            version: format!(
+                "{}@{}+{}",
+                template.name,
+                template.version,
+                content_hash(function_definition)
+            ),
+        }
+    }
+}
+
+// Names used as {{variable}} in a template
+fn template_variables(template: &str) -> Vec<String> {
+    let mut variables: Vec<String> = vec![];
+    let mut rest: &str = template;
+    while let Some(start) = rest.find("{{") {
+        match rest[start + 2..].find("}}") {
+            Some(end) => {
+                variables.push(rest[start + 2..start + 2 + end].trim().to_string());
+                rest = &rest[start + 2 + end + 2..];
+            }
+            None => break,
+        }
+    }
+    variables
+}
+
+// Single pass, so braces inside substituted values are never expanded
+fn render_template(template: &str, variables: &BTreeMap<&str, &str>) -> String {
+    let mut rendered: String = String::with_capacity(template.len());
+    let mut rest: &str = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        match rest[start + 2..].find("}}") {
+            Some(end) => {
+                let name: &str = rest[start + 2..start + 2 + end].trim();
+                match variables.get(name) {
+                    Some(value) => rendered.push_str(value),
+                    None => rendered.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &rest[start + 2 + end + 2..];
+            }
+            None => {
+                rendered.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .take(4)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
+
+    #[test]
+    fn tests_builtin_prompt() {
+        let library: PromptLibrary = PromptLibrary::builtin();
+        let input: &str = "Build me a {{todo}} app";
+        let rendered: RenderedPrompt = library.render(
+            "convert_user_input_to_goal",
+            convert_user_input_to_goal(input),
+            input,
+        );
+
+        assert!(rendered
+            .message
+            .content
+            .starts_with("FUNCTION: pub fn convert_user_input_to_goal"));
+        assert!(rendered
+            .message
+            .content
+            .contains("Here is the input to the function: Build me a {{todo}} app."));
+        assert!(rendered.version.starts_with("default@builtin-"));
+    }
+
+    #[test]
+    fn tests_parse_template_versions_and_variables() {
+        let template: PromptTemplate = PromptTemplate::parse(
+            "print_site_urls",
+            "---\nversion: urls-v2\n---\nList APIs for {{ input }} ({{ai_function}})",
+            "file",
+        )
+        .unwrap();
+        assert_eq!(template.version, "urls-v2");
+
+        let variables: BTreeMap<&str, &str> = BTreeMap::from([
+            ("input", "a weather app"),
+            ("ai_function", "print_site_urls"),
+        ]);
+        assert_eq!(
+            template.render(&variables),
+            "List APIs for a weather app (print_site_urls)"
+        );
+
+        let unversioned: PromptTemplate =
+            PromptTemplate::parse("print_site_urls", "{{input}}", "file").unwrap();
+        assert!(unversioned.version.starts_with("file-"));
+
+        assert!(PromptTemplate::parse("print_site_urls", "{{user_request}}", "file").is_err());
+    }
+
+    #[test]
+    fn tests_load_prompt_dir() {
+        let dir: PathBuf = env::temp_dir().join(format!("prompts_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("print_project_scope.prompt"),
+            "---\nversion: scope-v3\n---\nScope: {{input}}",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let library: PromptLibrary = PromptLibrary::load_dir(&dir).unwrap();
+        let scope: RenderedPrompt = library.render("print_project_scope", "fn ...", "todo app");
+        assert_eq!(scope.message.content, "Scope: todo app");
+        assert_eq!(
+            scope.version,
+            format!("print_project_scope@scope-v3+{}", content_hash("fn ..."))
+        );
+        let changed: RenderedPrompt =
+            library.render("print_project_scope", "fn ... // changed", "todo app");
+        assert_ne!(changed.version, scope.version);
+
+        let urls: RenderedPrompt = library.render("print_site_urls", "fn ...", "todo app");
+        assert!(urls.version.starts_with("default@builtin-"));
+
+        fs::write(dir.join("print_scope.prompt"), "{{input}}").unwrap();
+        assert!(PromptLibrary::load_dir(&dir).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
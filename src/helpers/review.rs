@@ -0,0 +1,227 @@
+use crate::helpers::command_line::get_review_decision;
+use crate::helpers::events::{publish, AgentEvent};
+use crate::helpers::run_context::{current_review_gates, current_reviewer};
+use crate::models::agent_basic::basic_agent::BasicAgent;
+use async_trait::async_trait;
+use dotenv::dotenv;
+use serde::Serialize;
+use std::env;
+
+// Points in a run where a human may step in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewGate {
+    Scope,
+    ExternalUrls,
+    CodeVersion,
+    RunServer,
+}
+
+impl ReviewGate {
+    pub const ALL: [ReviewGate; 4] = [
+        ReviewGate::Scope,
+        ReviewGate::ExternalUrls,
+        ReviewGate::CodeVersion,
+        ReviewGate::RunServer,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Scope => "scope",
+            Self::ExternalUrls => "external_urls",
+            Self::CodeVersion => "code_version",
+            Self::RunServer => "run_server",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Scope => "Project scope",
+            Self::ExternalUrls => "External urls",
+            Self::CodeVersion => "Code version",
+            Self::RunServer => "Build and run the server",
+        }
+    }
+
+    // Error a rejection stops the agent with
+    pub fn rejection(&self) -> String {
+        match self {
+            Self::RunServer => "Generated code was not approved to run".to_string(),
+            _ => format!("{} was rejected in review", self.title()),
+        }
+    }
+}
+
+// Only running AI written code is gated unless configured otherwise
+pub fn default_review_gates() -> Vec<ReviewGate> {
+    vec![ReviewGate::RunServer]
+}
+
+// REVIEW_GATES, e.g. "scope,code_version,run_server", "all" or "none"
+pub fn parse_review_gates(gates: &str) -> Result<Vec<ReviewGate>, String> {
+    match gates.trim() {
+        "all" => return Ok(ReviewGate::ALL.to_vec()),
+        "none" | "" => return Ok(vec![]),
+        _ => {}
+    }
+
+    let mut review_gates: Vec<ReviewGate> = vec![];
+    for name in gates.split(',').map(|name| name.trim()) {
+        let gate: ReviewGate = ReviewGate::ALL
+            .into_iter()
+            .find(|gate| gate.name() == name)
+            .ok_or(format!(
+                "Unknown review gate '{}' in REVIEW_GATES, expected one of scope, external_urls, code_version, run_server, all or none",
+                name
+            ))?;
+        if !review_gates.contains(&gate) {
+            review_gates.push(gate);
+        }
+    }
+    Ok(review_gates)
+}
+
+pub fn review_gates_from_env() -> Result<Vec<ReviewGate>, String> {
+    dotenv().ok();
+
+    match env::var("REVIEW_GATES") {
+        Ok(gates) => parse_review_gates(&gates),
+        Err(_) => Ok(default_review_gates()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReviewRequest {
+    pub gate: ReviewGate,
+    pub agent: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    Approve,
+    Reject,
+    // Sent along with the agent's next LLM call, which redoes the step
+    Feedback(String),
+}
+
+// Decides at each review gate, e.g. on the terminal or from a dashboard
+#[async_trait]
+pub trait Reviewer: Send + Sync {
+    async fn review(&self, request: &ReviewRequest) -> ReviewDecision;
+}
+
+// A closure approves or rejects every gate
+#[async_trait]
+impl<F: Fn() -> bool + Send + Sync> Reviewer for F {
+    async fn review(&self, _request: &ReviewRequest) -> ReviewDecision {
+        match self() {
+            true => ReviewDecision::Approve,
+            false => ReviewDecision::Reject,
+        }
+    }
+}
+
+// Asks on the terminal without blocking the other tasks of the runtime
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerminalReviewer;
+
+#[async_trait]
+impl Reviewer for TerminalReviewer {
+    async fn review(&self, request: &ReviewRequest) -> ReviewDecision {
+        let request: ReviewRequest = request.clone();
+        tokio::task::spawn_blocking(move || get_review_decision(&request))
+            .await
+            .unwrap_or(ReviewDecision::Reject)
+    }
+}
+
+// Stop at a gate if the run reviews it. Feedback is queued for the agent's next LLM call.
+pub async fn review_gate(
+    gate: ReviewGate,
+    agent: &mut BasicAgent,
+    summary: String,
+) -> ReviewDecision {
+    if !current_review_gates().contains(&gate) {
+        return ReviewDecision::Approve;
+    }
+
+    let request: ReviewRequest = ReviewRequest {
+        gate,
+        agent: agent.position.clone(),
+        summary,
+    };
+    publish(AgentEvent::ReviewRequested {
+        agent: request.agent.clone(),
+        gate,
+        summary: request.summary.clone(),
+    });
+    let decision: ReviewDecision = current_reviewer().review(&request).await;
+    publish(AgentEvent::ReviewDecided {
+        agent: agent.position.clone(),
+        gate,
+        decision: decision.clone(),
+    });
+
+    if let ReviewDecision::Feedback(feedback) = &decision {
+        agent.pending_feedback.push(feedback.clone());
+    }
+    decision
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::run_context::{with_run_context, RunContext};
+    use crate::models::agent_basic::basic_traits::BasicTraits;
+    use std::sync::Arc;
+
+    struct FeedbackReviewer;
+
+    #[async_trait]
+    impl Reviewer for FeedbackReviewer {
+        async fn review(&self, request: &ReviewRequest) -> ReviewDecision {
+            ReviewDecision::Feedback(format!("Rework the {}", request.gate.name()))
+        }
+    }
+
+    #[test]
+    fn tests_parse_review_gates() {
+        assert_eq!(
+            parse_review_gates("scope, code_version,scope").unwrap(),
+            vec![ReviewGate::Scope, ReviewGate::CodeVersion]
+        );
+        assert_eq!(parse_review_gates("all").unwrap(), ReviewGate::ALL.to_vec());
+        assert_eq!(parse_review_gates("none").unwrap(), vec![]);
+        assert!(parse_review_gates("scope,deploy").is_err());
+    }
+
+    #[tokio::test]
+    async fn tests_review_gate_queues_feedback() {
+        let context: RunContext = RunContext {
+            reviewer: Arc::new(FeedbackReviewer),
+            review_gates: vec![ReviewGate::Scope],
+            observers: vec![],
+            ..RunContext::default()
+        };
+
+        with_run_context(context, async {
+            let mut agent: BasicAgent =
+                BasicAgent::new("Design".to_string(), "Solutions Architect".to_string());
+
+            let scope_decision: ReviewDecision =
+                review_gate(ReviewGate::Scope, &mut agent, "{}".to_string()).await;
+            let urls_decision: ReviewDecision =
+                review_gate(ReviewGate::ExternalUrls, &mut agent, "[]".to_string()).await;
+
+            assert_eq!(
+                scope_decision,
+                ReviewDecision::Feedback("Rework the scope".to_string())
+            );
+            assert_eq!(urls_decision, ReviewDecision::Approve);
+            assert_eq!(agent.pending_feedback, vec!["Rework the scope".to_string()]);
+        })
+        .await;
+    }
+}
@@ -0,0 +1,793 @@
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::api_schema::{HttpMethod, PathTemplate, Shape};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+const METHODS: [(&str, HttpMethod); 7] = [
+    ("get", HttpMethod::Get),
+    ("post", HttpMethod::Post),
+    ("put", HttpMethod::Put),
+    ("patch", HttpMethod::Patch),
+    ("delete", HttpMethod::Delete),
+    ("head", HttpMethod::Head),
+    ("options", HttpMethod::Options),
+];
+
+// Structs inside structs are described this deep
+const MAX_SHAPE_DEPTH: u8 = 4;
+
+// Scopes and nested routers are followed this deep
+const MAX_NESTING: u8 = 8;
+
+// A struct with named fields, i.e. struct Task { id: u64, name: String }
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+    // Derives Serialize or Deserialize, so it is part of the data model
+    pub is_serde: bool,
+}
+
+impl StructDef {
+    // In the style of the requirements, i.e. "task: id, name, completed"
+    pub fn entity(&self) -> String {
+        let fields: Vec<&str> = self.fields.iter().map(|(name, _)| name.as_str()).collect();
+        format!("{}: {}", self.name.to_lowercase(), fields.join(", "))
+    }
+}
+
+// Source without comments, with char literals blanked and raw strings written as plain ones,
+// so quotes and brackets inside them cannot confuse the scan
+fn strip_comments(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut stripped: String = String::with_capacity(source.len());
+    let mut i: usize = 0;
+    while i < chars.len() {
+        let after_ident: bool = i > 0 && is_ident_char(chars[i - 1]);
+        match (chars[i], chars.get(i + 1).copied()) {
+            ('"', _) => {
+                let end: usize = string_end(&chars, i + 1);
+                stripped.extend(&chars[i..end]);
+                i = end;
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            // Block comments nest in Rust
+            ('/', Some('*')) => {
+                let mut depth: usize = 0;
+                while i < chars.len() {
+                    if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                        depth += 1;
+                        i += 2;
+                    } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+                stripped.push(' ');
+            }
+            // r"..." and r#"..."#, also after a b prefix
+            ('r', Some('"' | '#'))
+                if !after_ident
+                    || (chars[i - 1] == 'b' && (i < 2 || !is_ident_char(chars[i - 2]))) =>
+            {
+                let hashes: usize = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+                let open: usize = i + 1 + hashes;
+                if chars.get(open) != Some(&'"') {
+                    stripped.push('r');
+                    i += 1;
+                    continue;
+                }
+                let closing: Vec<char> = format!("\"{}", "#".repeat(hashes)).chars().collect();
+                let close: usize = (open + 1..chars.len())
+                    .find(|at| chars[*at..].starts_with(&closing))
+                    .unwrap_or(chars.len());
+                let contents: String = chars[open + 1..close].iter().collect();
+                stripped.push('"');
+                stripped.push_str(&contents.replace('\\', "\\\\").replace('"', "\\\""));
+                stripped.push('"');
+                i = (close + closing.len()).min(chars.len());
+            }
+            // '"' and '\'' are chars, 'a without a closing quote is a lifetime
+            ('\'', Some('\\')) => {
+                let close: usize = (i + 3..chars.len())
+                    .find(|at| chars[*at] == '\'')
+                    .unwrap_or(chars.len());
+                stripped.push_str("'_'");
+                i = close + 1;
+            }
+            ('\'', Some(_)) if chars.get(i + 2) == Some(&'\'') => {
+                stripped.push_str("'_'");
+                i += 3;
+            }
+            (c, _) => {
+                stripped.push(c);
+                i += 1;
+            }
+        }
+    }
+    stripped
+}
+
+// Index after the closing quote of a string whose contents start at `from`
+fn string_end(chars: &[char], from: usize) -> usize {
+    let mut i: usize = from;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+// Text between the bracket at `open` and its match, skipping string literals
+fn enclosed(source: &str, open: usize, close: char) -> Option<&str> {
+    let open_char: char = source[open..].chars().next()?;
+    let mut depth: usize = 0;
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+    for (i, c) in source[open..].char_indices() {
+        if in_string {
+            match (escaped, c) {
+                (false, '\\') => escaped = true,
+                (false, '"') => in_string = false,
+                _ => escaped = false,
+            }
+            continue;
+        }
+        if c == '"' {
+            in_string = true;
+        } else if c == open_char {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(&source[open + 1..open + i]);
+            }
+        }
+    }
+    None
+}
+
+// Contents of the string literal the text starts with
+fn leading_string(text: &str) -> Option<String> {
+    let text: &str = text.trim_start();
+    let rest: &str = text.strip_prefix('"')?;
+    let mut value: String = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?),
+            '"' => return Some(value),
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Every `name(...)` in the text with the position of its opening bracket
+fn calls(text: &str) -> Vec<(&str, usize)> {
+    let mut found: Vec<(&str, usize)> = vec![];
+    let mut ident_start: Option<usize> = None;
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match (escaped, c) {
+                (false, '\\') => escaped = true,
+                (false, '"') => in_string = false,
+                _ => escaped = false,
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                ident_start = None;
+            }
+            c if is_ident_char(c) => {
+                ident_start.get_or_insert(i);
+            }
+            '(' => {
+                if let Some(start) = ident_start.take() {
+                    found.push((&text[start..i], i));
+                }
+            }
+            _ => ident_start = None,
+        }
+    }
+    found
+}
+
+fn method_named(name: &str) -> Option<HttpMethod> {
+    METHODS
+        .iter()
+        .find(|(method_name, _)| *method_name == name)
+        .map(|(_, method)| *method)
+}
+
+// Last segment of a path like handlers::create_task, None for closures and other expressions
+fn handler_name(expr: &str) -> Option<String> {
+    let name: &str = expr.trim().rsplit("::").next()?;
+    match !name.is_empty() && name.chars().all(is_ident_char) {
+        true => Some(name.to_string()),
+        false => None,
+    }
+}
+
+// Methods and handlers of a route registration, i.e. web::get().to(list) or get(list).post(add)
+fn method_handlers(args: &str) -> Vec<(HttpMethod, Option<String>)> {
+    let calls: Vec<(&str, usize)> = calls(args);
+    let mut found: Vec<(HttpMethod, Option<String>)> = vec![];
+    for (i, (name, open)) in calls.iter().enumerate() {
+        let method: HttpMethod = match method_named(name) {
+            Some(method) => method,
+            None => continue,
+        };
+        let inner: &str = enclosed(args, *open, ')').unwrap_or("");
+        let handler: Option<String> = match inner.trim().is_empty() {
+            // Actix names the handler in the .to(...) that follows
+            true => calls[i + 1..]
+                .iter()
+                .find(|(name, _)| *name == "to")
+                .and_then(|(_, open)| enclosed(args, *open, ')'))
+                .and_then(handler_name),
+            false => handler_name(inner),
+        };
+        found.push((method, handler));
+    }
+    found
+}
+
+// Structs with named fields, tuple and unit structs are skipped
+pub fn extract_structs(source: &str) -> Vec<StructDef> {
+    let source: String = strip_comments(source);
+    let mut structs: Vec<StructDef> = vec![];
+    let mut search_from: usize = 0;
+    while let Some(found) = source[search_from..].find("struct ") {
+        let start: usize = search_from + found;
+        search_from = start + "struct ".len();
+        if start > 0 && source[..start].chars().last().is_some_and(is_ident_char) {
+            continue;
+        }
+
+        let after: &str = &source[search_from..];
+        let name: String = after.chars().take_while(|c| is_ident_char(*c)).collect();
+        let body_open: usize = match after.find(['{', ';', '(']) {
+            Some(open) if after[open..].starts_with('{') => search_from + open,
+            _ => continue,
+        };
+        let body: &str = match enclosed(&source, body_open, '}') {
+            Some(body) => body,
+            None => continue,
+        };
+
+        // Attributes since the item before, i.e. #[derive(Serialize, Deserialize)]
+        let item_start: usize = source[..start].rfind(['}', ';']).map_or(0, |end| end + 1);
+        let attributes: &str = &source[item_start..start];
+        structs.push(StructDef {
+            name,
+            fields: struct_fields(body),
+            is_serde: attributes.contains("Serialize") || attributes.contains("Deserialize"),
+        });
+    }
+    structs
+}
+
+fn struct_fields(body: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<String> = vec![];
+    let mut depth: i32 = 0;
+    let mut field: String = String::new();
+    for c in body.chars() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                fields.push(field.clone());
+                field.clear();
+                continue;
+            }
+            _ => {}
+        }
+        field.push(c);
+    }
+    fields.push(field);
+
+    fields
+        .iter()
+        .filter_map(|field| {
+            // Drop field attributes, i.e. #[serde(default)]
+            let field: &str = match field.rfind(']') {
+                Some(end) => &field[end + 1..],
+                None => field,
+            };
+            let field: &str = field.trim();
+            let field: &str = field
+                .strip_prefix("pub(crate)")
+                .or(field.strip_prefix("pub"))
+                .unwrap_or(field);
+            let (name, ty) = field.split_once(':')?;
+            Some((name.trim().to_string(), ty.trim().to_string()))
+        })
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+// Shape of a Rust type, i.e. Vec<Task> is a list of task objects
+fn type_shape(ty: &str, structs: &[StructDef], depth: u8) -> Shape {
+    let ty: &str = ty.trim().trim_start_matches('&').trim();
+    let (outer, inner): (&str, Option<&str>) = match ty.find('<') {
+        Some(open) if ty.ends_with('>') => (&ty[..open], Some(&ty[open + 1..ty.len() - 1])),
+        _ => (ty, None),
+    };
+    let outer: &str = outer.rsplit("::").next().unwrap_or(outer).trim();
+    match (outer, inner) {
+        ("Vec", Some(item)) => Shape::Array(Box::new(type_shape(item, structs, depth))),
+        ("Option" | "Box", Some(item)) => type_shape(item, structs, depth),
+        _ => match structs.iter().find(|def| def.name == outer) {
+            Some(def) if depth < MAX_SHAPE_DEPTH => Shape::Object(
+                def.fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), type_shape(ty, structs, depth + 1)))
+                    .collect::<BTreeMap<String, Shape>>(),
+            ),
+            _ => Shape::from_value(&Value::from(outer.to_lowercase())).unwrap_or(Shape::Any),
+        },
+    }
+}
+
+// Type inside the first Json<...> of a signature
+fn json_type(text: &str) -> Option<&str> {
+    let start: usize = text.find("Json<")? + "Json".len();
+    enclosed(text, start, '>')
+}
+
+// Parameters and return type of `fn name(...) -> ... {`
+fn signature<'a>(source: &'a str, name: &str) -> Option<(&'a str, &'a str)> {
+    let needle: String = format!("fn {}(", name);
+    let start: usize = source.find(&needle)?;
+    let open: usize = start + needle.len() - 1;
+    let params: &str = enclosed(source, open, ')')?;
+    let after: &str = &source[open + params.len() + 2..];
+    let returns: &str = &after[..after.find(['{', ';']).unwrap_or(after.len())];
+    Some((params, returns))
+}
+
+// Part of the source under a path, i.e. what follows web::scope("/api") or the router nested
+// with axum's .nest("/api", ...)
+#[derive(Debug, Clone, PartialEq)]
+struct PathRegion {
+    start: usize,
+    end: usize,
+    path: String,
+    // Where the scope or nest is written, the paths around it come first
+    origin: usize,
+}
+
+// The bracket that closes around the expression starting at `from`, or a , or ; after it
+fn expression_end(source: &str, from: usize) -> usize {
+    let mut depth: usize = 0;
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+    for (i, c) in source[from..].char_indices() {
+        if in_string {
+            match (escaped, c) {
+                (false, '\\') => escaped = true,
+                (false, '"') => in_string = false,
+                _ => escaped = false,
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' | ',' | ';' if depth == 0 => return from + i,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+// Body of the router a .nest names, i.e. `fn api_routes() -> Router { .. }` or `let api = ..;`
+fn router_definition(source: &str, router: &str) -> Option<(usize, usize)> {
+    let router: &str = router.trim();
+    let path: &str = &router[..router.find('(').unwrap_or(router.len())];
+    // Anything after the call, i.e. Router::new().route(..), is an inline router
+    if path.len() < router.len()
+        && enclosed(router, path.len(), ')')?.len() + 2 != router.len() - path.len()
+    {
+        return None;
+    }
+    let name: String = handler_name(path)?;
+
+    let fn_needle: String = format!("fn {}(", name);
+    if let Some(start) = source.find(&fn_needle) {
+        let body_open: usize = start + source[start..].find('{')?;
+        let body: &str = enclosed(source, body_open, '}')?;
+        return Some((body_open + 1, body_open + 1 + body.len()));
+    }
+    let let_needle: String = format!("let {}", name);
+    let mut search_from: usize = 0;
+    while let Some(found) = source[search_from..].find(&let_needle) {
+        let start: usize = search_from + found + let_needle.len();
+        search_from = start;
+        if source[start..].chars().next().is_some_and(is_ident_char) {
+            continue;
+        }
+        let value_start: usize = start + source[start..].find('=')? + 1;
+        return Some((value_start, expression_end(source, value_start)));
+    }
+    None
+}
+
+fn innermost(regions: &[PathRegion], at: usize) -> Option<&PathRegion> {
+    regions
+        .iter()
+        .filter(|region| region.start <= at && at < region.end)
+        .max_by_key(|region| region.start)
+}
+
+// Prefixes of a position, more than one when the same router is nested in several places
+fn prefixes_at(regions: &[PathRegion], at: usize, depth: u8) -> Vec<String> {
+    let inner: &PathRegion = match innermost(regions, at) {
+        Some(inner) if depth < MAX_NESTING => inner,
+        _ => return vec![String::new()],
+    };
+    regions
+        .iter()
+        .filter(|region| region.start == inner.start && region.end == inner.end)
+        .flat_map(|region| {
+            prefixes_at(regions, region.origin, depth + 1)
+                .into_iter()
+                .map(|outer| join_path(&outer, &region.path))
+        })
+        .collect()
+}
+
+// e.g. "/api" and "/task" make "/api/task", a nested "/" is the prefix itself
+fn join_path(prefix: &str, path: &str) -> String {
+    match (prefix.trim_end_matches('/'), path) {
+        ("", path) => path.to_string(),
+        (prefix, "" | "/") => prefix.to_string(),
+        (prefix, path) if path.starts_with('/') => format!("{}{}", prefix, path),
+        (prefix, path) => format!("{}/{}", prefix, path),
+    }
+}
+
+// Routes registered in actix-web or axum source, with bodies taken from the Json<T> handlers use
+pub fn extract_routes(source: &str) -> Vec<RouteObject> {
+    let source: String = strip_comments(source);
+    let structs: Vec<StructDef> = extract_structs(&source);
+    let calls: Vec<(&str, usize)> = calls(&source);
+    let mut registrations: Vec<(String, HttpMethod, Option<String>)> = vec![];
+
+    // web::scope("/api") and .nest("/api", ..) prefix the routes inside them, the
+    // .route(..) calls chained on web::resource("/task") take its path
+    let mut scopes: Vec<PathRegion> = vec![];
+    let mut resources: Vec<PathRegion> = vec![];
+    for (name, open) in &calls {
+        let (args, path): (&str, String) = match enclosed(&source, *open, ')') {
+            Some(args) => match leading_string(args) {
+                Some(path) => (args, path),
+                None => continue,
+            },
+            None => continue,
+        };
+        let origin: usize = open - name.len();
+        let chained_end: usize = expression_end(&source, open + args.len() + 2);
+        match *name {
+            "scope" => scopes.push(PathRegion {
+                start: *open,
+                end: chained_end,
+                path,
+                origin,
+            }),
+            "resource" => resources.push(PathRegion {
+                start: *open,
+                end: chained_end,
+                path,
+                origin,
+            }),
+            "nest" => {
+                let router: &str = args.split_once(',').map_or("", |(_, rest)| rest);
+                let (start, end): (usize, usize) =
+                    router_definition(&source, router).unwrap_or((open + 1, open + 1 + args.len()));
+                scopes.push(PathRegion {
+                    start,
+                    end,
+                    path,
+                    origin,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // .route("/task", web::get().to(list)), web::resource("/task").route(...) and
+    // axum's .route("/task", get(list).post(add))
+    for (name, open) in &calls {
+        if *name != "route" {
+            continue;
+        }
+        let args: &str = match enclosed(&source, *open, ')') {
+            Some(args) => args,
+            None => continue,
+        };
+        let (path, methods): (String, &str) = match leading_string(args) {
+            Some(path) => (path, args.split_once(',').map_or("", |(_, rest)| rest)),
+            None => match innermost(&resources, *open) {
+                Some(resource) => (resource.path.clone(), args),
+                None => continue,
+            },
+        };
+        for prefix in prefixes_at(&scopes, *open, 0) {
+            for (method, handler) in method_handlers(methods) {
+                registrations.push((join_path(&prefix, &path), method, handler));
+            }
+        }
+    }
+
+    // Actix attribute macros, i.e. #[get("/task")] async fn list(...)
+    let mut search_from: usize = 0;
+    while let Some(found) = source[search_from..].find("#[") {
+        let start: usize = search_from + found + 2;
+        search_from = start;
+        let macro_name: String = source[start..]
+            .chars()
+            .take_while(|c| is_ident_char(*c))
+            .collect();
+        let method: HttpMethod = match method_named(&macro_name) {
+            Some(method) => method,
+            None => continue,
+        };
+        let args_open: usize = start + macro_name.len();
+        let path: Option<String> = source[args_open..]
+            .strip_prefix('(')
+            .and_then(leading_string);
+        let handler: Option<String> = source[args_open..].find("fn ").and_then(|fn_start| {
+            let name: String = source[args_open + fn_start + 3..]
+                .chars()
+                .take_while(|c| is_ident_char(*c))
+                .collect();
+            handler_name(&name)
+        });
+        let path: String = match path {
+            Some(path) => path,
+            None => continue,
+        };
+
+        // A scope prefixes the handlers registered with .service(handler)
+        let mut prefixes: Vec<String> = calls
+            .iter()
+            .filter(|(name, _)| *name == "service")
+            .filter(|(_, open)| {
+                handler.is_some() && enclosed(&source, *open, ')').and_then(handler_name) == handler
+            })
+            .flat_map(|(_, open)| prefixes_at(&scopes, *open, 0))
+            .collect();
+        if prefixes.is_empty() {
+            prefixes.push(String::new());
+        }
+        for prefix in prefixes {
+            registrations.push((join_path(&prefix, &path), method, handler.clone()));
+        }
+    }
+
+    let mut routes: Vec<RouteObject> = vec![];
+    for (path, method, handler) in registrations {
+        let route: PathTemplate = PathTemplate::parse(&path);
+        if routes
+            .iter()
+            .any(|known| known.route == route && known.method == method)
+        {
+            continue;
+        }
+        let (params, returns): (&str, &str) = handler
+            .as_deref()
+            .and_then(|handler| signature(&source, handler))
+            .unwrap_or(("", ""));
+        routes.push(RouteObject {
+            method,
+            request_body: json_type(params).map(|ty| type_shape(ty, &structs, 0)),
+            response: json_type(returns).map(|ty| type_shape(ty, &structs, 0)),
+            route,
+            requires_auth: false,
+        });
+    }
+    routes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route_keys(routes: &[RouteObject]) -> Vec<String> {
+        routes
+            .iter()
+            .map(|route| format!("{} {}", route.method.as_str(), route.route))
+            .collect()
+    }
+
+    #[test]
+    fn tests_extract_actix_routes() {
+        let routes: Vec<RouteObject> = extract_routes(include_str!("../code_template.rs"));
+        assert_eq!(
+            route_keys(&routes),
+            vec![
+                "post /task",
+                "get /task",
+                "put /task",
+                "get /task/{id}",
+                "delete /task/{id}",
+                "post /register",
+                "post /login"
+            ]
+        );
+        assert!(routes[3].route.is_dynamic());
+        match &routes[0].request_body {
+            Some(Shape::Object(fields)) => {
+                assert_eq!(fields.get("id"), Some(&Shape::Integer));
+                assert_eq!(fields.get("name"), Some(&Shape::String));
+            }
+            other => panic!("expected the Task fields, got {:?}", other),
+        }
+
+        let attributes: &str = r#"
+            #[get("/health")] // says "ok"
+            async fn health() -> impl Responder { "ok" }
+
+            #[post("/notes")]
+            async fn add_note(note: web::Json<Note>) -> impl Responder { HttpResponse::Ok() }
+
+            App::new().service(web::resource("/notes/{id}").route(web::delete().to(remove)))
+        "#;
+        assert_eq!(
+            route_keys(&extract_routes(attributes)),
+            vec!["delete /notes/{id}", "get /health", "post /notes"]
+        );
+    }
+
+    #[test]
+    fn tests_extract_axum_routes_and_structs() {
+        let source: &str = r#"
+            #[derive(Serialize, Deserialize, Clone)]
+            pub struct Note {
+                pub id: u64,
+                #[serde(default)]
+                pub tags: Vec<String>,
+                pub rating: Option<f32>,
+            }
+
+            struct AppState { notes: Mutex<HashMap<u64, Note>> }
+
+            async fn list(State(state): State<AppState>) -> Json<Vec<Note>> { todo!() }
+            async fn add(State(state): State<AppState>, Json(note): Json<Note>) -> StatusCode { todo!() }
+
+            let app = Router::new()
+                .route("/notes", get(list).post(handlers::add))
+                .route("/notes/:id", get(|| async { "one" }).delete(remove));
+        "#;
+
+        let structs: Vec<StructDef> = extract_structs(source);
+        assert_eq!(structs.len(), 2);
+        assert_eq!(structs[0].entity(), "note: id, tags, rating");
+        assert!(structs[0].is_serde);
+        assert!(!structs[1].is_serde);
+        assert_eq!(structs[1].fields[0].1, "Mutex<HashMap<u64, Note>>");
+
+        let routes: Vec<RouteObject> = extract_routes(source);
+        assert_eq!(
+            route_keys(&routes),
+            vec![
+                "get /notes",
+                "post /notes",
+                "get /notes/{id}",
+                "delete /notes/{id}"
+            ]
+        );
+        let note: Shape = Shape::Object(BTreeMap::from([
+            ("id".to_string(), Shape::Integer),
+            ("tags".to_string(), Shape::Array(Box::new(Shape::String))),
+            ("rating".to_string(), Shape::Number),
+        ]));
+        assert_eq!(
+            routes[0].response,
+            Some(Shape::Array(Box::new(note.clone())))
+        );
+        assert_eq!(routes[1].request_body, Some(note));
+        assert_eq!(routes[2].request_body, None);
+    }
+
+    #[test]
+    fn tests_extract_scoped_actix_routes() {
+        let source: &str = r#"
+            #[get("/me")]
+            async fn me() -> impl Responder { "me" }
+
+            App::new()
+                .service(
+                    web::scope("/api")
+                        .route("/task", web::get().to(list))
+                        .service(web::resource("/task/{id}").route(web::delete().to(remove)))
+                        .service(web::scope("v1").route("/ping", web::get().to(ping)))
+                        .service(me),
+                )
+                .service(web::resource("/health").route(web::get().to(health)))
+                .service(custom_resource().route(web::post().to(create)))
+                .route("/", web::get().to(index))
+        "#;
+        assert_eq!(
+            route_keys(&extract_routes(source)),
+            vec![
+                "get /api/task",
+                "delete /api/task/{id}",
+                "get /api/v1/ping",
+                "get /health",
+                "get /",
+                "get /api/me"
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_extract_nested_axum_routes() {
+        let source: &str = r#"
+            fn task_routes() -> Router<AppState> {
+                Router::new().route("/", get(list)).route("/:id", delete(remove))
+            }
+
+            let api = Router::new()
+                .route("/health", get(health))
+                .nest("/tasks", task_routes());
+            let app = Router::new()
+                .nest("/api", api)
+                .nest("/admin", Router::new().route("/stats", get(stats)))
+                .route("/", get(index));
+        "#;
+        assert_eq!(
+            route_keys(&extract_routes(source)),
+            vec![
+                "get /api/tasks",
+                "delete /api/tasks/{id}",
+                "get /api/health",
+                "get /admin/stats",
+                "get /"
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_extract_routes_skips_literals_and_comments() {
+        let source: &str = r##"
+            /* Old routes, .route("/old", get(old)) and a stray " quote
+               /* nested */ .route("/older", get(older)) */
+            fn escape(c: char) -> bool { c == '"' || c == '(' || c == '\'' }
+            fn describe<'a>(name: &'a str) -> &'a str { name }
+            const PAGE: &str = r#"<a href="/x">("#;
+
+            Router::new()
+                .route("/notes", get(list))
+                .route(r"/raw", post(add))
+                .route(r#"/raw/"quoted""#, get(quoted))
+        "##;
+        assert_eq!(
+            route_keys(&extract_routes(source)),
+            vec!["get /notes", "post /raw", "get /raw/\"quoted\""]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::apis::cache::ResponseCache;
+use crate::apis::llm_config::LLMConfig;
+use crate::apis::provider::{LLMProvider, OpenAIProvider};
+use crate::apis::usage::{Budget, PriceTable, UsageTracker};
+use crate::helpers::backend_templates::{BackendTemplate, Storage};
+use crate::helpers::clarify::Clarifier;
+use crate::helpers::events::{AgentObserver, TerminalObserver};
+use crate::helpers::general::Workspace;
+use crate::helpers::prompt_library::PromptLibrary;
+use crate::helpers::review::{default_review_gates, ReviewGate, Reviewer, TerminalReviewer};
+use crate::models::agents::agent_architect::ArchitectConfig;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+// Pauses a run between steps, e.g. from a dashboard
+#[derive(Debug, Clone)]
+pub struct RunControl {
+    paused: Arc<watch::Sender<bool>>,
+}
+
+impl Default for RunControl {
+    fn default() -> Self {
+        let (paused, _) = watch::channel(false);
+        Self {
+            paused: Arc::new(paused),
+        }
+    }
+}
+
+impl RunControl {
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    pub async fn wait_while_paused(&self) {
+        let mut paused: watch::Receiver<bool> = self.paused.subscribe();
+        while *paused.borrow_and_update() {
+            if paused.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// Settings for one pipeline run, visible to every agent running inside it
+pub struct RunContext {
+    pub provider: Arc<dyn LLMProvider>,
+    pub workspace: Workspace,
+    pub observers: Vec<Arc<dyn AgentObserver>>,
+    pub reviewer: Arc<dyn Reviewer>,
+    pub review_gates: Vec<ReviewGate>,
+    // Without a clarifier the request goes straight to goal conversion
+    pub clarifier: Option<Arc<dyn Clarifier>>,
+    // Without one the template is picked from the project scope
+    pub backend_template: Option<BackendTemplate>,
+    // Replaces the storage of the template, e.g. SQLite instead of a JSON file
+    pub backend_storage: Option<Storage>,
+    pub architect: ArchitectConfig,
+    pub llm_config: Arc<LLMConfig>,
+    pub prompts: Arc<PromptLibrary>,
+    // Without one every call goes to the provider
+    pub response_cache: Option<Arc<ResponseCache>>,
+    // Token and cost totals of this run, checked against its budget before every call
+    pub usage: Arc<Mutex<UsageTracker>>,
+    pub control: RunControl,
+    pub print_to_terminal: bool,
+}
+
+impl Default for RunContext {
+    fn default() -> Self {
+        Self {
+            provider: Arc::new(OpenAIProvider),
+            workspace: Workspace::default(),
+            observers: vec![Arc::new(TerminalObserver)],
+            reviewer: Arc::new(TerminalReviewer),
+            review_gates: default_review_gates(),
+            clarifier: None,
+            backend_template: None,
+            backend_storage: None,
+            architect: ArchitectConfig::default(),
+            llm_config: Arc::new(LLMConfig::default()),
+            prompts: Arc::new(PromptLibrary::builtin()),
+            response_cache: None,
+            usage: new_usage_tracker(Budget::default()),
+            control: RunControl::default(),
+            print_to_terminal: true,
+        }
+    }
+}
+
+// A tracker for one run, priced with the built-in price table
+pub fn new_usage_tracker(budget: Budget) -> Arc<Mutex<UsageTracker>> {
+    Arc::new(Mutex::new(UsageTracker::new(budget, PriceTable::default())))
+}
+
+tokio::task_local! {
+    static RUN_CONTEXT: Arc<RunContext>;
+}
+
+// Run a future with its own provider, workspace and observers
+pub async fn with_run_context<F: Future>(context: RunContext, future: F) -> F::Output {
+    RUN_CONTEXT.scope(Arc::new(context), future).await
+}
+
+// Outside a pipeline run, e.g. in tests, the defaults apply
+fn current_run_context() -> Option<Arc<RunContext>> {
+    RUN_CONTEXT.try_with(|context| context.clone()).ok()
+}
+
+pub fn current_provider() -> Arc<dyn LLMProvider> {
+    current_run_context()
+        .map(|context| context.provider.clone())
+        .unwrap_or(Arc::new(OpenAIProvider))
+}
+
+pub fn current_workspace() -> Workspace {
+    current_run_context()
+        .map(|context| context.workspace.clone())
+        .unwrap_or_default()
+}
+
+pub fn prints_to_terminal() -> bool {
+    current_run_context().is_none_or(|context| context.print_to_terminal)
+}
+
+pub fn current_observers() -> Vec<Arc<dyn AgentObserver>> {
+    current_run_context()
+        .map(|context| context.observers.clone())
+        .unwrap_or(vec![Arc::new(TerminalObserver)])
+}
+
+// Review gates ask on the terminal unless the run decides
+pub fn current_reviewer() -> Arc<dyn Reviewer> {
+    current_run_context().map_or(Arc::new(TerminalReviewer), |context| {
+        context.reviewer.clone()
+    })
+}
+
+pub fn current_review_gates() -> Vec<ReviewGate> {
+    current_run_context()
+        .map(|context| context.review_gates.clone())
+        .unwrap_or(default_review_gates())
+}
+
+pub fn current_clarifier() -> Option<Arc<dyn Clarifier>> {
+    current_run_context().and_then(|context| context.clarifier.clone())
+}
+
+pub fn current_backend_template() -> Option<BackendTemplate> {
+    current_run_context().and_then(|context| context.backend_template)
+}
+
+pub fn current_backend_storage() -> Option<Storage> {
+    current_run_context().and_then(|context| context.backend_storage)
+}
+
+pub fn current_architect_config() -> ArchitectConfig {
+    current_run_context()
+        .map(|context| context.architect)
+        .unwrap_or_default()
+}
+
+pub fn current_llm_config() -> Arc<LLMConfig> {
+    current_run_context()
+        .map(|context| context.llm_config.clone())
+        .unwrap_or_default()
+}
+
+pub fn current_prompt_library() -> Arc<PromptLibrary> {
+    current_run_context()
+        .map(|context| context.prompts.clone())
+        .unwrap_or(Arc::new(PromptLibrary::builtin()))
+}
+
+pub fn current_response_cache() -> Option<Arc<ResponseCache>> {
+    current_run_context().and_then(|context| context.response_cache.clone())
+}
+
+// Outside a run every call gets a fresh tracker without a budget
+pub fn current_usage_tracker() -> Arc<Mutex<UsageTracker>> {
+    current_run_context()
+        .map(|context| context.usage.clone())
+        .unwrap_or(new_usage_tracker(Budget::default()))
+}
+
+// Checkpoint between steps, returns once the run is not paused
+pub async fn wait_while_paused() {
+    if let Some(context) = current_run_context() {
+        context.control.wait_while_paused().await;
+    }
+}
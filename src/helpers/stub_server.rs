@@ -0,0 +1,227 @@
+// Minimal scripted HTTP server for tests that must not touch the network
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone)]
+pub struct StubResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub delay: Option<Duration>,
+    pub body_delay: Option<Duration>,
+    pub cut_off: Option<usize>,
+}
+
+impl StubResponse {
+    pub fn new(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: body.to_string(),
+            delay: None,
+            body_delay: None,
+            cut_off: None,
+        }
+    }
+
+    pub fn json(status: u16, body: &str) -> Self {
+        Self::new(status, body).header("Content-Type", "application/json")
+    }
+
+    pub fn redirect(location: &str) -> Self {
+        Self::new(302, "").header("Location", location)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    // Send the head straight away but stall before the body
+    pub fn body_delay(mut self, delay: Duration) -> Self {
+        self.body_delay = Some(delay);
+        self
+    }
+
+    // Close the connection after this many body bytes, as a dropped connection would
+    pub fn cut_off(mut self, len: usize) -> Self {
+        self.cut_off = Some(len);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+type Script = Arc<Mutex<HashMap<String, Vec<StubResponse>>>>;
+
+pub struct StubServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<StubRequest>>>,
+    handle: JoinHandle<()>,
+}
+
+impl StubServer {
+    // Each path replays its responses in order, repeating the last one
+    pub async fn start(routes: Vec<(&str, Vec<StubResponse>)>) -> Self {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind stub server");
+        let addr: SocketAddr = listener.local_addr().unwrap();
+
+        let script: Script = Arc::new(Mutex::new(
+            routes
+                .into_iter()
+                .map(|(path, responses)| (path.to_string(), responses))
+                .collect(),
+        ));
+        let requests: Arc<Mutex<Vec<StubRequest>>> = Arc::new(Mutex::new(vec![]));
+
+        let requests_log: Arc<Mutex<Vec<StubRequest>>> = requests.clone();
+        let handle: JoinHandle<()> = tokio::spawn(async move {
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(_) => continue,
+                };
+                let script: Script = script.clone();
+                let requests_log: Arc<Mutex<Vec<StubRequest>>> = requests_log.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(stream, script, requests_log).await;
+                });
+            }
+        });
+
+        Self {
+            addr,
+            requests,
+            handle,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn requests(&self) -> Vec<StubRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn hit_count(&self, path: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.path == path)
+            .count()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    script: Script,
+    requests_log: Arc<Mutex<Vec<StubRequest>>>,
+) -> std::io::Result<()> {
+    // Read head
+    let mut buffer: Vec<u8> = vec![];
+    let mut chunk: [u8; 4096] = [0; 4096];
+    let head_end: usize = loop {
+        let read: usize = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head: String = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+    let method: String = request_line.next().unwrap_or("").to_string();
+    let path: String = request_line
+        .next()
+        .unwrap_or("/")
+        .split('?')
+        .next()
+        .unwrap_or("/")
+        .to_string();
+
+    // Read body
+    let content_length: usize = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0);
+    while buffer.len() < head_end + content_length {
+        let read: usize = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let body: String = String::from_utf8_lossy(&buffer[head_end..]).to_string();
+
+    requests_log.lock().unwrap().push(StubRequest {
+        method,
+        path: path.clone(),
+        body,
+    });
+
+    // Pick scripted response
+    let response: StubResponse = {
+        let mut script = script.lock().unwrap();
+        match script.get_mut(&path) {
+            Some(responses) if responses.len() > 1 => responses.remove(0),
+            Some(responses) if responses.len() == 1 => responses[0].clone(),
+            _ => StubResponse::new(404, "not found"),
+        }
+    };
+
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut raw: String = format!("HTTP/1.1 {} Stub\r\n", response.status);
+    for (name, value) in &response.headers {
+        raw.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    raw.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    ));
+    stream.write_all(raw.as_bytes()).await?;
+    if let Some(delay) = response.body_delay {
+        stream.flush().await?;
+        tokio::time::sleep(delay).await;
+    }
+    let body_len: usize = response
+        .cut_off
+        .unwrap_or(response.body.len())
+        .min(response.body.len());
+    stream
+        .write_all(&response.body.as_bytes()[..body_len])
+        .await?;
+    stream.shutdown().await
+}
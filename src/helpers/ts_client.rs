@@ -0,0 +1,265 @@
+use crate::helpers::openapi::operation_id;
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::api_schema::Shape;
+use std::collections::HashSet;
+
+const TS_CLIENT_PRELUDE: &str = r#"// This file is generated from the API endpoint schema. Do not edit by hand.
+
+let baseUrl: string = "http://localhost:8080";
+
+export function setBaseUrl(url: string): void {
+  baseUrl = url.replace(/\/$/, "");
+}
+
+let sessionToken: string | undefined;
+
+// Sent as a Bearer token once set, e.g. with the token returned by login
+export function setToken(token: string | undefined): void {
+  sessionToken = token;
+}
+
+async function request<T>(method: string, url: string, body?: unknown): Promise<T> {
+  const headers: Record<string, string> = {};
+  if (body !== undefined) {
+    headers["Content-Type"] = "application/json";
+  }
+  if (sessionToken !== undefined) {
+    headers["Authorization"] = `Bearer ${sessionToken}`;
+  }
+  const res = await fetch(url, {
+    method,
+    headers,
+    body: body === undefined ? undefined : JSON.stringify(body),
+  });
+  if (!res.ok) {
+    throw new Error(`${method} ${url} failed with status ${res.status}`);
+  }
+  const text = await res.text();
+  return (text.length > 0 ? JSON.parse(text) : undefined) as T;
+}
+
+"#;
+
+// Generate a typed TypeScript client module from the extracted endpoint schema
+pub fn routes_to_typescript(routes: &Vec<RouteObject>) -> String {
+    let mut interfaces: Vec<String> = vec![];
+    let mut functions: Vec<String> = vec![];
+    let mut op_ids: HashSet<String> = HashSet::new();
+
+    for route_object in routes {
+        let method: &str = route_object.method.as_str();
+        let op_id: String = unique_name(&mut op_ids, operation_id(method, &route_object.route));
+        let type_prefix: String = to_pascal_case(&op_id);
+
+        // Request body interface
+        let request_type: Option<String> = route_object.request_body.as_ref().map(|shape| {
+            let name: String = format!("{}Request", type_prefix);
+            interfaces.push(declare_type(&name, &shape_to_ts(shape, 1)));
+            name
+        });
+
+        // Response interface
+        let response_type: String = match &route_object.response {
+            Some(shape) => {
+                let name: String = format!("{}Response", type_prefix);
+                interfaces.push(declare_type(&name, &shape_to_ts(shape, 1)));
+                name
+            }
+            None => "void".to_string(),
+        };
+
+        // Function arguments: path params then body
+        let path_params: &Vec<String> = route_object.route.params();
+        let mut args: Vec<String> = path_params
+            .iter()
+            .map(|param| format!("{}: string | number", to_identifier(param)))
+            .collect();
+        if let Some(request_type) = &request_type {
+            args.push(format!("body: {}", request_type));
+        }
+
+        // Interpolate path params into the url
+        let mut url_path: String = route_object.route.as_str().to_string();
+        for param in path_params {
+            if let Some(placeholder) = route_object.route.placeholder(param) {
+                url_path = url_path.replace(
+                    placeholder,
+                    &format!("${{encodeURIComponent(String({}))}}", to_identifier(param)),
+                );
+            }
+        }
+
+        let body_arg: &str = if request_type.is_some() {
+            "body"
+        } else {
+            "undefined"
+        };
+        functions.push(format!(
+            "export async function {}({}): Promise<{}> {{\n  return request<{}>(\"{}\", `${{baseUrl}}{}`, {});\n}}",
+            to_camel_case(&op_id),
+            args.join(", "),
+            response_type,
+            response_type,
+            method.to_uppercase(),
+            url_path,
+            body_arg
+        ));
+    }
+
+    let declarations: String = interfaces
+        .iter()
+        .map(|interface| format!("{}\n\n", interface))
+        .collect();
+    format!(
+        "{}{}{}\n",
+        TS_CLIENT_PRELUDE,
+        declarations,
+        functions.join("\n\n")
+    )
+}
+
+// Repeated routes, or paths that differ only in punctuation, get a numbered suffix
+fn unique_name(taken: &mut HashSet<String>, name: String) -> String {
+    let mut unique: String = name.clone();
+    let mut suffix: usize = 2;
+    while !taken.insert(unique.clone()) {
+        unique = format!("{}_{}", name, suffix);
+        suffix += 1;
+    }
+    unique
+}
+
+// Convert a body shape into a TypeScript type
+fn shape_to_ts(shape: &Shape, depth: usize) -> String {
+    match shape {
+        Shape::String => "string".to_string(),
+        Shape::Number | Shape::Integer => "number".to_string(),
+        Shape::Bool => "boolean".to_string(),
+        Shape::Any => "unknown".to_string(),
+        Shape::Array(item) => format!("Array<{}>", shape_to_ts(item, depth)),
+        Shape::Object(fields) => {
+            let indent: String = "  ".repeat(depth);
+            let lines: Vec<String> = fields
+                .iter()
+                .map(|(name, field)| {
+                    format!(
+                        "{}{}: {};",
+                        indent,
+                        quote_property(name),
+                        shape_to_ts(field, depth + 1)
+                    )
+                })
+                .collect();
+            format!("{{\n{}\n{}}}", lines.join("\n"), "  ".repeat(depth - 1))
+        }
+    }
+}
+
+// Objects become interfaces, everything else a type alias
+fn declare_type(name: &str, ts: &str) -> String {
+    if ts.starts_with('{') {
+        format!("export interface {} {}", name, ts)
+    } else {
+        format!("export type {} = {};", name, ts)
+    }
+}
+
+fn quote_property(name: &str) -> String {
+    if to_identifier(name) == name {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\\\""))
+    }
+}
+
+fn to_identifier(name: &str) -> String {
+    let ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match ident.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", ident),
+        _ => ident,
+    }
+}
+
+fn to_pascal_case(snake: &str) -> String {
+    snake
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn to_camel_case(snake: &str) -> String {
+    let pascal: String = to_pascal_case(snake);
+    let mut chars = pascal.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_routes_to_typescript() {
+        let routes_str: &str = r#"
+      [
+        {
+          "route": "/task/{id}",
+          "is_route_dynamic": "true",
+          "method": "get",
+          "request_body": "None",
+          "response": { "id": "number", "name": "string", "complete": "bool" }
+        },
+        {
+          "route": "/task",
+          "is_route_dynamic": "false",
+          "method": "post",
+          "request_body": { "id": "number", "name": "string", "complete": "bool" },
+          "response": "None"
+        }
+      ]"#;
+        let routes: Vec<RouteObject> = serde_json::from_str(routes_str).unwrap();
+
+        let ts_client: String = routes_to_typescript(&routes);
+        dbg!(&ts_client);
+
+        assert!(ts_client.contains("export interface GetTaskByIdResponse {"));
+        assert!(ts_client.contains("  complete: boolean;"));
+        assert!(ts_client.contains(
+            "export async function getTaskById(id: string | number): Promise<GetTaskByIdResponse>"
+        ));
+        assert!(ts_client.contains("${encodeURIComponent(String(id))}"));
+        assert!(ts_client.contains("export function setToken(token: string | undefined): void"));
+        assert!(ts_client
+            .contains("export async function postTask(body: PostTaskRequest): Promise<void>"));
+    }
+
+    #[test]
+    fn tests_duplicate_routes_get_unique_names() {
+        let routes_str: &str = r#"[
+          {"route": "/task", "is_route_dynamic": "false", "method": "post", "request_body": {"name": "string"}, "response": "None"},
+          {"route": "/task", "is_route_dynamic": "false", "method": "post", "request_body": {"title": "string"}, "response": "None"},
+          {"route": "/task-list", "is_route_dynamic": "false", "method": "get", "request_body": "None", "response": "None"},
+          {"route": "/task_list", "is_route_dynamic": "false", "method": "get", "request_body": "None", "response": "None"}
+        ]"#;
+        let routes: Vec<RouteObject> = serde_json::from_str(routes_str).unwrap();
+
+        let ts_client: String = routes_to_typescript(&routes);
+        assert!(ts_client.contains("export async function postTask(body: PostTaskRequest)"));
+        assert!(ts_client.contains("export async function postTask2(body: PostTask2Request)"));
+        assert!(ts_client.contains("export interface PostTask2Request {"));
+        assert!(ts_client.contains("export async function getTaskList()"));
+        assert!(ts_client.contains("export async function getTaskList2()"));
+    }
+}
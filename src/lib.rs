@@ -0,0 +1,24 @@
+// Crate internal, macros defined before the modules are visible in all of them
+macro_rules! get_function_string {
+    ($func: ident) => {{
+        stringify!($func)
+    }};
+}
+
+#[macro_use]
+pub mod ai_functions;
+pub mod apis;
+pub mod dashboard;
+pub mod helpers;
+pub mod models;
+pub mod pipeline;
+pub mod service;
+
+pub use apis::provider::{CassetteProvider, LLMProvider, OpenAIProvider};
+pub use helpers::backend_templates::BackendTemplate;
+pub use helpers::events::{AgentEvent, AgentObserver, JsonLinesObserver, TerminalObserver};
+pub use helpers::general::Workspace;
+pub use helpers::git_history::GitHistory;
+pub use helpers::review::{ReviewDecision, ReviewGate, ReviewRequest, Reviewer};
+pub use models::agents::agent_traits::{FactSheet, SpecialFunctions};
+pub use pipeline::{Pipeline, PipelineBuilder, PipelineError, PipelineOutput};
@@ -1,28 +1,172 @@
-#[macro_export]
-macro_rules! get_function_string {
-    ($func: ident) => {{
-        stringify!($func)
-    }};
-}
-
-#[macro_use]
-mod ai_functions;
-mod apis;
-mod helpers;
-mod models;
+use auto_gippity::apis::cache::{cache_command, response_cache_from_env};
+use auto_gippity::apis::llm_config::LLMConfig;
+use auto_gippity::apis::provider::{provider_from_env, LLMProvider};
+use auto_gippity::apis::usage::{Budget, UsageTracker};
+use auto_gippity::dashboard::run_dashboard;
+use auto_gippity::helpers::backend_templates::{
+    backend_storage_from_env, backend_template_from_env, Storage,
+};
+use auto_gippity::helpers::clarify::{
+    clarification_mode_from_env, AnswersFile, ClarificationMode, TerminalClarifier,
+};
+use auto_gippity::helpers::command_line::get_user_response;
+use auto_gippity::helpers::eval::eval_command;
+use auto_gippity::helpers::git_history::{git_history_from_env, new_run_id};
+use auto_gippity::helpers::project_import::import_command;
+use auto_gippity::helpers::prompt_library::PromptLibrary;
+use auto_gippity::helpers::review::review_gates_from_env;
+use auto_gippity::models::agents::agent_architect::{architect_config_from_env, ArchitectConfig};
+use auto_gippity::service::serve_command;
+use auto_gippity::{
+    BackendTemplate, GitHistory, JsonLinesObserver, Pipeline, PipelineBuilder, PipelineError,
+    PipelineOutput, ReviewGate, Workspace,
+};
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
-use helpers::command_line::get_user_response;
-use models::agents_manager::managing_agent::ManagingAgent;
+// A command or config that fails to load ends the process with its error
+fn load_or_exit<T, E: Display>(res: Result<T, E>) -> T {
+    match res {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    let usr_req: String = get_user_response("What website are we building today?");
+    // Maintenance commands, e.g. `cargo run -- cache stats`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("cache") {
+        println!("{}", load_or_exit(cache_command(&args[1..])));
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("eval") {
+        println!("{}", load_or_exit(eval_command(&args[1..]).await));
+        return;
+    }
+
+    // Fail fast on a broken model config or prompt template rather than mid-run
+    let (llm_config, prompts): (LLMConfig, PromptLibrary) = load_or_exit(
+        LLMConfig::from_env().and_then(|config| Ok((config, PromptLibrary::from_env()?))),
+    );
+
+    // REST API for project requests, e.g. `cargo run -- serve --max-concurrent 2`
+    if args.first().map(|arg| arg.as_str()) == Some("serve") {
+        println!("{}", load_or_exit(serve_command(&args[1..]).await));
+        return;
+    }
+
+    // Factsheet of an existing project, e.g. `cargo run -- import ../todo_api`
+    if args.first().map(|arg| arg.as_str()) == Some("import") {
+        println!("{}", load_or_exit(import_command(&args[1..]).await));
+        return;
+    }
+
+    // Full screen dashboard instead of scrolling output, e.g. `cargo run -- tui`
+    let use_dashboard: bool = args.first().map(|arg| arg.as_str()) == Some("tui");
+
+    // Change the last finished project, e.g. `cargo run -- amend "let tasks have a due date"`
+    let change_request: Option<String> = match args.first().map(|arg| arg.as_str()) {
+        Some("amend") if args.len() > 1 => Some(args[1..].join(" ")),
+        Some("amend") => {
+            eprintln!("Usage: amend \"<change request>\"");
+            std::process::exit(1);
+        }
+        _ => None,
+    };
+
+    let usr_req: String = match &change_request {
+        Some(change_request) => change_request.clone(),
+        None => get_user_response("What website are we building today?"),
+    };
+
+    // LLM_PROVIDER=mock runs the whole project offline
+    let provider: Arc<dyn LLMProvider> = load_or_exit(provider_from_env());
 
-    let mut manage_agent: ManagingAgent = ManagingAgent::new(usr_req)
-        .await
-        .expect("Error creating agent");
+    // REVIEW_GATES picks where the run stops for approval or feedback
+    let review_gates: Vec<ReviewGate> = load_or_exit(review_gates_from_env());
 
-    manage_agent.execute_project().await;
+    // Clarifying questions are asked on the terminal unless answered from a file or turned off
+    let clarification_mode: ClarificationMode = load_or_exit(clarification_mode_from_env());
 
-    // dbg!(manage_agent);
-}
\ No newline at end of file
+    // BACKEND_TEMPLATE forces a framework template, otherwise the project scope picks one
+    let backend_template: Option<BackendTemplate> = load_or_exit(backend_template_from_env());
+
+    // BACKEND_STORAGE=sqlite stores the generated backend's data in SQLite with migrations
+    let backend_storage: Option<Storage> = load_or_exit(backend_storage_from_env());
+
+    // A forced template keeps its framework, so it has to support the storage
+    if let (Some(template), Some(storage)) = (backend_template, backend_storage) {
+        load_or_exit(template.with_storage(storage));
+    }
+
+    // ARCHITECT_MAX_URL_ROUNDS and ARCHITECT_MIN_WORKING_URLS tune the external url search
+    let architect_config: ArchitectConfig = load_or_exit(architect_config_from_env());
+
+    // Every code iteration is committed to the generated project unless GIT_HISTORY=off
+    let git_history: bool = load_or_exit(git_history_from_env());
+
+    // Agent events go to the terminal and to a JSON lines log
+    let workspace: Workspace = Workspace::from_env();
+    let mut pipeline: PipelineBuilder = Pipeline::builder()
+        .provider(provider)
+        .workspace(workspace.clone())
+        .review_gates(review_gates)
+        .architect_config(architect_config)
+        .llm_config(llm_config)
+        .prompts(prompts)
+        .budget(Budget::from_env());
+    if let Some(response_cache) = response_cache_from_env() {
+        pipeline = pipeline.response_cache(response_cache);
+    }
+    if let Some(backend_template) = backend_template {
+        pipeline = pipeline.backend_template(backend_template);
+    }
+    if let Some(backend_storage) = backend_storage {
+        pipeline = pipeline.backend_storage(backend_storage);
+    }
+    if git_history {
+        let run_id: String = new_run_id();
+        println!(
+            "Run {}, code iterations are committed to the generated project",
+            run_id
+        );
+        let project_dir: &Path = &workspace.web_server_project_path;
+        pipeline = pipeline.observer(load_or_exit(GitHistory::new(project_dir, &run_id)));
+    }
+    match JsonLinesObserver::from_env() {
+        Ok(event_log) => pipeline = pipeline.observer(event_log),
+        Err(e) => eprintln!("Could not open event log: {}", e),
+    }
+    match &clarification_mode {
+        ClarificationMode::AnswersFile(path) => {
+            pipeline = pipeline.clarifier(load_or_exit(AnswersFile::load(path)))
+        }
+        ClarificationMode::Interactive if !use_dashboard => {
+            pipeline = pipeline.clarifier(TerminalClarifier)
+        }
+        _ => {}
+    }
+
+    let usage: Arc<Mutex<UsageTracker>> = pipeline.usage_tracker();
+    let project_res: Result<PipelineOutput, PipelineError> = if use_dashboard {
+        let ask_clarifying_questions: bool = clarification_mode == ClarificationMode::Interactive;
+        run_dashboard(pipeline, &usr_req, ask_clarifying_questions).await
+    } else if change_request.is_some() {
+        pipeline.build().amend(&usr_req).await
+    } else {
+        pipeline.build().run(&usr_req).await
+    };
+
+    // End of run cost summary
+    println!("{}", usage.lock().unwrap().summary());
+
+    if let Err(e) = project_res {
+        eprintln!("Project stopped: {}", e);
+        std::process::exit(1);
+    }
+}
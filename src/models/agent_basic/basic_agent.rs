@@ -1,7 +1,9 @@
+use crate::helpers::events::{publish, AgentEvent};
 use crate::models::agent_basic::basic_traits::BasicTraits;
 use crate::models::general::llm::Message;
+use serde::Serialize;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub enum AgentState {
     Discovery,
     Working,
@@ -15,6 +17,8 @@ pub struct BasicAgent {
     pub position: String,
     pub state: AgentState,
     pub memory: Vec<Message>,
+    // Reviewer feedback for the next LLM call
+    pub pending_feedback: Vec<String>,
 }
 
 impl BasicTraits for BasicAgent {
@@ -24,10 +28,16 @@ impl BasicTraits for BasicAgent {
             position,
             state: AgentState::Discovery,
             memory: Vec::from([]),
+            pending_feedback: vec![],
         }
     }
 
     fn update_state(&mut self, new_state: AgentState) {
+        publish(AgentEvent::StateChanged {
+            agent: self.position.clone(),
+            from: self.state.clone(),
+            to: new_state.clone(),
+        });
         self.state = new_state;
     }
 
@@ -46,4 +56,8 @@ impl BasicTraits for BasicAgent {
     fn get_memory(&self) -> &Vec<Message> {
         &self.memory
     }
-}
\ No newline at end of file
+
+    fn add_memory(&mut self, message: Message) {
+        self.memory.push(message);
+    }
+}
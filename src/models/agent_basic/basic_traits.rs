@@ -8,4 +8,5 @@ pub trait BasicTraits {
     fn get_position(&self) -> &String;
     fn get_state(&self) -> &AgentState;
     fn get_memory(&self) -> &Vec<Message>;
-}
\ No newline at end of file
+    fn add_memory(&mut self, message: Message);
+}
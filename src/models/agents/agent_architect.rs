@@ -1,14 +1,73 @@
-use crate::ai_functions::aifunc_architect::{print_project_scope, print_site_urls};
+use crate::ai_functions::aifunc_architect::{
+    print_project_scope, print_replacement_site_urls, print_site_urls,
+};
 use crate::helpers::command_line::PrintCommand;
-use crate::helpers::general::{ai_task_request_decoded, check_status_code};
+use crate::helpers::general::{
+    ai_task_request_decoded_with_memory, probe_url, url_probe_rejection, AITaskError,
+};
+use crate::helpers::review::{review_gate, ReviewDecision, ReviewGate};
+use crate::helpers::run_context::current_architect_config;
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
 use crate::models::agent_basic::basic_traits::BasicTraits;
-use crate::models::agents::agent_traits::{FactSheet, ProjectScope, SpecialFunctions};
+use crate::models::agents::agent_traits::{FactSheet, ProjectScope, SpecialFunctions, UrlProbe};
 
 use async_trait::async_trait;
+use dotenv::dotenv;
 use reqwest::Client;
+use std::env;
 use std::time::Duration;
 
+const DEFAULT_MAX_URL_ROUNDS: u8 = 3;
+const DEFAULT_MIN_WORKING_URLS: usize = 1;
+
+// How hard the architect looks for working external urls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArchitectConfig {
+    pub max_url_rounds: u8,
+    pub min_working_urls: usize,
+}
+
+impl Default for ArchitectConfig {
+    fn default() -> Self {
+        Self {
+            max_url_rounds: DEFAULT_MAX_URL_ROUNDS,
+            min_working_urls: DEFAULT_MIN_WORKING_URLS,
+        }
+    }
+}
+
+fn parse_env_number<T: std::str::FromStr>(
+    name: &str,
+    value: Option<String>,
+    default: T,
+) -> Result<T, String> {
+    match value {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| format!("{} must be a non-negative number, got {}", name, value)),
+        None => Ok(default),
+    }
+}
+
+// e.g. ARCHITECT_MAX_URL_ROUNDS=5 ARCHITECT_MIN_WORKING_URLS=2
+pub fn architect_config_from_env() -> Result<ArchitectConfig, String> {
+    dotenv().ok();
+
+    Ok(ArchitectConfig {
+        max_url_rounds: parse_env_number(
+            "ARCHITECT_MAX_URL_ROUNDS",
+            env::var("ARCHITECT_MAX_URL_ROUNDS").ok(),
+            DEFAULT_MAX_URL_ROUNDS,
+        )?,
+        min_working_urls: parse_env_number(
+            "ARCHITECT_MIN_WORKING_URLS",
+            env::var("ARCHITECT_MIN_WORKING_URLS").ok(),
+            DEFAULT_MIN_WORKING_URLS,
+        )?,
+    })
+}
+
 // Solutions Architect
 #[derive(Debug)]
 pub struct AgentSolutionArchitect {
@@ -23,26 +82,31 @@ impl AgentSolutionArchitect {
             position: "Solutions Architect".to_string(),
             state: AgentState::Discovery,
             memory: vec![],
+            pending_feedback: vec![],
         };
 
         Self { attributes }
     }
 
     // Retrieve Project Scope
-    async fn call_project_scope(&mut self, factsheet: &mut FactSheet) -> ProjectScope {
-        let msg_context: String = format!("{}", factsheet.project_description);
+    async fn call_project_scope(
+        &mut self,
+        factsheet: &mut FactSheet,
+    ) -> Result<ProjectScope, AITaskError> {
+        let msg_context: String =
+            factsheet.with_requirements(factsheet.project_description.clone());
 
-        let ai_response: ProjectScope = ai_task_request_decoded::<ProjectScope>(
+        let ai_response: ProjectScope = ai_task_request_decoded_with_memory::<ProjectScope>(
             msg_context,
-            &self.attributes.position,
+            &mut self.attributes,
             get_function_string!(print_project_scope),
             print_project_scope,
+            &[],
         )
-        .await;
+        .await?;
 
-        factsheet.project_scope = Some(ai_response.clone());
-        self.attributes.update_state(AgentState::Finished);
-        return ai_response;
+        factsheet.project_scope = Some(ai_response);
+        Ok(ai_response)
     }
 
     // Retrieve Project Scope
@@ -50,18 +114,80 @@ impl AgentSolutionArchitect {
         &mut self,
         factsheet: &mut FactSheet,
         msg_context: String,
-    ) {
-        let ai_response: Vec<String> = ai_task_request_decoded::<Vec<String>>(
+    ) -> Result<(), AITaskError> {
+        let ai_response: Vec<String> = ai_task_request_decoded_with_memory::<Vec<String>>(
             msg_context,
-            &self.attributes.position,
+            &mut self.attributes,
             get_function_string!(print_site_urls),
             print_site_urls,
+            &[],
         )
-        .await;
+        .await?;
 
         factsheet.external_urls = Some(ai_response);
-        self.attributes.state = AgentState::UnitTesting;
+        Ok(())
+    }
+
+    // Ask for alternatives to the urls that failed testing
+    async fn call_replacement_urls(
+        &mut self,
+        factsheet: &mut FactSheet,
+        rejected_urls: &[(String, String)],
+    ) -> Result<(), AITaskError> {
+        let working_urls: Vec<String> = factsheet.external_urls.clone().unwrap_or_default();
+        let rejected_list: Vec<String> = rejected_urls
+            .iter()
+            .map(|(url, reason)| format!("{} ({})", url, reason))
+            .collect();
+
+        let msg_context: String = format!(
+            "PROJECT_DESCRIPTION: {} \n WORKING_URLS: {:?} \n REJECTED_URLS: {:?}",
+            factsheet.project_description, working_urls, rejected_list
+        );
+
+        // Earlier url suggestions show the LLM what has already been tried
+        let ai_response: Vec<String> = ai_task_request_decoded_with_memory::<Vec<String>>(
+            msg_context,
+            &mut self.attributes,
+            get_function_string!(print_replacement_site_urls),
+            print_replacement_site_urls,
+            &[
+                get_function_string!(print_site_urls),
+                get_function_string!(print_replacement_site_urls),
+            ],
+        )
+        .await?;
+
+        factsheet.external_urls = Some(merge_replacement_urls(
+            working_urls,
+            rejected_urls,
+            ai_response,
+        ));
+        Ok(())
+    }
+}
+
+impl Default for AgentSolutionArchitect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Add new candidate urls, skipping duplicates and anything already rejected
+fn merge_replacement_urls(
+    working_urls: Vec<String>,
+    rejected_urls: &[(String, String)],
+    candidates: Vec<String>,
+) -> Vec<String> {
+    let mut urls: Vec<String> = working_urls;
+    for candidate in candidates {
+        let candidate: String = candidate.trim().to_string();
+        let is_rejected: bool = rejected_urls.iter().any(|(url, _)| url == &candidate);
+        if !candidate.is_empty() && !is_rejected && !urls.contains(&candidate) {
+            urls.push(candidate);
+        }
     }
+    urls
 }
 
 #[async_trait]
@@ -74,39 +200,84 @@ impl SpecialFunctions for AgentSolutionArchitect {
         &mut self,
         factsheet: &mut FactSheet,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        // Replacement url rounds come from the run's config
+        let config: ArchitectConfig = current_architect_config();
+        let mut url_round: u8 = 0;
+        let mut url_probes: Vec<UrlProbe> = vec![];
+        let mut rejected_urls: Vec<(String, String)> = vec![];
+        let mut is_scope_approved: bool = false;
+
         // !!! WARNING - BE CAREFUL OF INFINITATE LOOPS !!!
         while self.attributes.state != AgentState::Finished {
             match self.attributes.state {
                 AgentState::Discovery => {
-                    let project_scope: ProjectScope = self.call_project_scope(factsheet).await;
-
-                    // Confirm if external urls
-                    if project_scope.is_external_urls_required {
-                        self.call_determine_external_urls(
-                            factsheet,
-                            factsheet.project_description.clone(),
-                        )
-                        .await;
-                        self.attributes.state = AgentState::UnitTesting;
+                    // Feedback on the scope asks for it again
+                    if !is_scope_approved {
+                        let project_scope: ProjectScope =
+                            self.call_project_scope(factsheet).await?;
+                        let scope_summary: String =
+                            serde_json::to_string_pretty(&project_scope).unwrap_or_default();
+                        match review_gate(ReviewGate::Scope, &mut self.attributes, scope_summary)
+                            .await
+                        {
+                            ReviewDecision::Approve => is_scope_approved = true,
+                            ReviewDecision::Reject => {
+                                return Err(ReviewGate::Scope.rejection().into())
+                            }
+                            ReviewDecision::Feedback(_) => continue,
+                        }
+
+                        // Confirm if external urls
+                        if !project_scope.is_external_urls_required {
+                            self.attributes.update_state(AgentState::Finished);
+                            continue;
+                        }
+                    }
+
+                    // Feedback on the urls asks for them again
+                    self.call_determine_external_urls(
+                        factsheet,
+                        factsheet.project_description.clone(),
+                    )
+                    .await?;
+                    let urls_summary: String = factsheet
+                        .external_urls
+                        .clone()
+                        .unwrap_or_default()
+                        .join("\n");
+                    match review_gate(ReviewGate::ExternalUrls, &mut self.attributes, urls_summary)
+                        .await
+                    {
+                        ReviewDecision::Approve => {
+                            self.attributes.update_state(AgentState::UnitTesting)
+                        }
+                        ReviewDecision::Reject => {
+                            return Err(ReviewGate::ExternalUrls.rejection().into())
+                        }
+                        ReviewDecision::Feedback(_) => continue,
                     }
                 }
 
                 AgentState::UnitTesting => {
-                    let mut exclude_urls: Vec<String> = vec![];
-
                     let client: Client = Client::builder()
                         .timeout(Duration::from_secs(5))
                         .build()
                         .unwrap();
 
                     // Defining urls to check
-                    let urls: &Vec<String> = factsheet
+                    let urls: Vec<String> = factsheet
                         .external_urls
-                        .as_ref()
+                        .clone()
                         .expect("No URL object on factsheet");
 
-                    // Find faulty urls
-                    for url in urls {
+                    // Find faulty urls, only testing urls not checked in an earlier round
+                    for url in &urls {
+                        let is_tested: bool = url_probes.iter().any(|probe| &probe.url == url)
+                            || rejected_urls.iter().any(|(rejected, _)| rejected == url);
+                        if is_tested {
+                            continue;
+                        }
+
                         let endpoint_str: String = format!("Testing URL Endpoint: {}", url);
                         PrintCommand::UnitTest.print_agent_message(
                             self.attributes.position.as_str(),
@@ -114,36 +285,78 @@ impl SpecialFunctions for AgentSolutionArchitect {
                         );
 
                         // Perform URL Test
-                        match check_status_code(&client, url).await {
-                            Ok(status_code) => {
-                                if status_code != 200 {
-                                    exclude_urls.push(url.clone())
-                                }
+                        // Rejected probes are kept with their reason
+                        let rejection: Option<String> = match probe_url(&client, url).await {
+                            Ok(mut probe) => {
+                                probe.rejection = url_probe_rejection(&probe);
+                                let rejection: Option<String> = probe.rejection.clone();
+                                url_probes.push(probe);
+                                rejection
                             }
-                            Err(e) => println!("Error checking {}: {}", url, e),
+                            Err(e) => Some(format!("request failed: {}", e)),
+                        };
+
+                        if let Some(reason) = rejection {
+                            let err_msg: String = format!("Rejecting URL {}: {}", url, reason);
+                            PrintCommand::Issue.print_agent_message(
+                                self.attributes.position.as_str(),
+                                err_msg.as_str(),
+                            );
+                            rejected_urls.push((url.clone(), reason));
                         }
                     }
 
                     // Exclude any faulty urls
-                    if exclude_urls.len() > 0 {
-                        let new_urls: Vec<String> = factsheet
-                            .external_urls
-                            .as_ref()
-                            .unwrap()
+                    let working_urls: Vec<String> = urls
+                        .into_iter()
+                        .filter(|url| !rejected_urls.iter().any(|(rejected, _)| rejected == url))
+                        .collect();
+                    factsheet.external_urls = Some(working_urls.clone());
+
+                    // Store real payload samples for the backend developer, and the rejections
+                    factsheet.external_url_probes = Some(url_probes.clone());
+
+                    // Confirm done
+                    if working_urls.len() >= config.min_working_urls {
+                        self.attributes.update_state(AgentState::Finished);
+                        continue;
+                    }
+
+                    // Give up once out of replacement rounds
+                    if url_round >= config.max_url_rounds {
+                        let rejected_list: Vec<String> = rejected_urls
                             .iter()
-                            .filter(|url| !exclude_urls.contains(&url))
-                            .cloned()
+                            .map(|(url, reason)| format!("{} ({})", url, reason))
                             .collect();
-                        factsheet.external_urls = Some(new_urls);
+                        let err_msg: String = format!(
+                            "Project needs external data but only {} of the required {} working url(s) were found after {} replacement round(s). Rejected: {}",
+                            working_urls.len(),
+                            config.min_working_urls,
+                            url_round,
+                            rejected_list.join(", ")
+                        );
+                        PrintCommand::Issue.print_agent_message(
+                            self.attributes.position.as_str(),
+                            err_msg.as_str(),
+                        );
+                        return Err(err_msg.into());
                     }
 
-                    // Confirm done
-                    self.attributes.state = AgentState::Finished;
+                    // Request alternatives and test again
+                    url_round += 1;
+                    let round_msg: String = format!(
+                        "Too few working URLs, requesting replacements (round {} of {})",
+                        url_round, config.max_url_rounds
+                    );
+                    PrintCommand::UnitTest
+                        .print_agent_message(self.attributes.position.as_str(), round_msg.as_str());
+                    self.call_replacement_urls(factsheet, &rejected_urls)
+                        .await?;
                 }
 
                 // Default to Finished state
                 _ => {
-                    self.attributes.state = AgentState::Finished;
+                    self.attributes.update_state(AgentState::Finished);
                 }
             }
         }
@@ -155,6 +368,153 @@ impl SpecialFunctions for AgentSolutionArchitect {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::apis::provider::LLMProvider;
+    use crate::apis::retry::LLMError;
+    use crate::helpers::run_context::{with_run_context, RunContext};
+    use crate::helpers::stub_server::{StubResponse, StubServer};
+    use crate::models::general::llm::{CompletionParams, LLMResponse, Message};
+    use std::sync::{Arc, Mutex};
+
+    // Suggests urls on the stub server, every replacement round offers the same ones
+    struct ScriptedProvider {
+        base_url: String,
+        site_urls: Vec<&'static str>,
+        replacement_urls: Vec<&'static str>,
+        replacement_rounds: Mutex<u8>,
+    }
+
+    impl ScriptedProvider {
+        fn new(
+            server: &StubServer,
+            site_urls: Vec<&'static str>,
+            replacement_urls: Vec<&'static str>,
+        ) -> Self {
+            Self {
+                base_url: server.url(""),
+                site_urls,
+                replacement_urls,
+                replacement_rounds: Mutex::new(0),
+            }
+        }
+
+        fn urls(&self, paths: &[&str]) -> String {
+            let urls: Vec<String> = paths
+                .iter()
+                .map(|path| format!("{}{}", self.base_url, path))
+                .collect();
+            serde_json::to_string(&urls).unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for ScriptedProvider {
+        fn name(&self) -> String {
+            "scripted".to_string()
+        }
+
+        async fn complete(
+            &self,
+            ai_function: &str,
+            _messages: Vec<Message>,
+            _params: &CompletionParams,
+        ) -> Result<LLMResponse, LLMError> {
+            let content: String = match ai_function {
+                "print_project_scope" => r#"{"is_crud_required": false, "is_user_login_and_logout": false, "is_external_urls_required": true}"#.to_string(),
+                "print_site_urls" => self.urls(&self.site_urls),
+                "print_replacement_site_urls" => {
+                    *self.replacement_rounds.lock().unwrap() += 1;
+                    self.urls(&self.replacement_urls)
+                }
+                _ => return Err(LLMError::Config(format!("no script for {}", ai_function))),
+            };
+            Ok(LLMResponse {
+                content,
+                model: "scripted".to_string(),
+                usage: None,
+            })
+        }
+    }
+
+    async fn run_architect(provider: Arc<ScriptedProvider>) -> (Result<(), String>, FactSheet) {
+        let context: RunContext = RunContext {
+            provider,
+            architect: ArchitectConfig {
+                max_url_rounds: 2,
+                min_working_urls: 1,
+            },
+            review_gates: vec![],
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+        let mut factsheet: FactSheet =
+            serde_json::from_str(r#"{"project_description": "Show the latest prices"}"#).unwrap();
+        let mut agent: AgentSolutionArchitect = AgentSolutionArchitect::new();
+        let execute_res: Result<(), String> = with_run_context(context, async {
+            agent
+                .execute(&mut factsheet)
+                .await
+                .map_err(|e| e.to_string())
+        })
+        .await;
+        (execute_res, factsheet)
+    }
+
+    #[tokio::test]
+    async fn tests_architect_replaces_rejected_urls() {
+        let server: StubServer = StubServer::start(vec![
+            ("/forbidden", vec![StubResponse::json(403, "{}")]),
+            (
+                "/prices",
+                vec![StubResponse::json(
+                    200,
+                    r#"[{"symbol": "EUR", "price": 1.08}]"#,
+                )],
+            ),
+        ])
+        .await;
+        let provider: Arc<ScriptedProvider> = Arc::new(ScriptedProvider::new(
+            &server,
+            vec!["/forbidden"],
+            vec!["/prices"],
+        ));
+
+        let (execute_res, factsheet) = run_architect(provider.clone()).await;
+
+        assert_eq!(execute_res, Ok(()));
+        assert_eq!(*provider.replacement_rounds.lock().unwrap(), 1);
+        assert_eq!(factsheet.external_urls, Some(vec![server.url("/prices")]));
+        let probes: Vec<UrlProbe> = factsheet.external_url_probes.unwrap();
+        assert_eq!(probes.len(), 2);
+        assert!(probes[0].rejection.is_some());
+        assert_eq!(probes[1].rejection, None);
+    }
+
+    #[tokio::test]
+    async fn tests_architect_gives_up_after_max_url_rounds() {
+        let server: StubServer = StubServer::start(vec![(
+            "/keyed",
+            vec![StubResponse::json(200, r#"{"error": "Missing API key"}"#)],
+        )])
+        .await;
+        let provider: Arc<ScriptedProvider> = Arc::new(ScriptedProvider::new(
+            &server,
+            vec!["/keyed"],
+            vec!["/keyed"],
+        ));
+
+        let (execute_res, factsheet) = run_architect(provider.clone()).await;
+
+        let err_msg: String = execute_res.unwrap_err();
+        assert!(
+            err_msg.starts_with("Project needs external data but only 0 of the required 1 working url(s) were found after 2 replacement round(s). Rejected: "),
+            "{}",
+            err_msg
+        );
+        assert!(err_msg.contains(&server.url("/keyed")));
+        assert_eq!(*provider.replacement_rounds.lock().unwrap(), 2);
+        assert_eq!(factsheet.external_urls, Some(vec![]));
+        assert_eq!(server.requests().len(), 1);
+    }
 
     #[tokio::test]
     async fn tests_solution_architect() {
@@ -162,8 +522,13 @@ mod tests {
 
         let mut factsheet: FactSheet = FactSheet {
       project_description: "Build a full stack website with user login and logout that shows latest Forex prices".to_string(),
+      requirements: None,
       project_scope: None,
       external_urls: None,
+      external_url_probes: None,
+      backend_template: None,
+      database_migration: None,
+      change_request: None,
       backend_code: None,
       api_endpoint_schema: None,
     };
@@ -172,9 +537,45 @@ mod tests {
             .execute(&mut factsheet)
             .await
             .expect("Unable to execute Solutions Architect Agent");
-        assert!(factsheet.project_scope != None);
+        assert!(factsheet.project_scope.is_some());
         assert!(factsheet.external_urls.is_some());
 
         dbg!(factsheet);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn tests_parse_env_number() {
+        assert_eq!(parse_env_number("ROUNDS", None, 3u8), Ok(3));
+        assert_eq!(
+            parse_env_number("ROUNDS", Some(" 5".to_string()), 3u8),
+            Ok(5)
+        );
+        assert!(parse_env_number("ROUNDS", Some("-1".to_string()), 3u8).is_err());
+    }
+
+    #[test]
+    fn tests_merge_replacement_urls() {
+        let rejected_urls: Vec<(String, String)> = vec![(
+            "https://api.example.com/keyed".to_string(),
+            "response asks for an API key".to_string(),
+        )];
+
+        let urls: Vec<String> = merge_replacement_urls(
+            vec!["https://worldtimeapi.org/api/timezone".to_string()],
+            &rejected_urls,
+            vec![
+                "https://api.example.com/keyed".to_string(),
+                "https://worldtimeapi.org/api/timezone".to_string(),
+                " https://timeapi.io/api/TimeZone/AvailableTimeZones ".to_string(),
+            ],
+        );
+
+        assert_eq!(
+            urls,
+            vec![
+                "https://worldtimeapi.org/api/timezone".to_string(),
+                "https://timeapi.io/api/TimeZone/AvailableTimeZones".to_string(),
+            ]
+        );
+    }
+}
@@ -1,23 +1,50 @@
 use crate::ai_functions::aifunc_backend::{
-    print_backend_webserver_code, print_fixed_code, print_improved_webserver_code,
-    print_rest_api_endpoints,
+    print_amended_migration, print_amended_webserver_code, print_backend_webserver_code,
+    print_fixed_code, print_improved_webserver_code, print_rest_api_endpoints, print_seed_data,
+    print_sql_migration,
 };
 use crate::helpers::general::{
-    check_status_code, read_code_template_contents, read_exec_main_contents, save_api_endpoints,
-    save_backend_code, WEB_SERVER_PROJECT_PATH,
+    check_status_code, next_migration_name, read_code_template_contents, read_exec_main_contents,
+    save_api_endpoints, save_auth_module, save_backend_code, save_migration, save_openapi_schema,
+    save_typescript_client,
 };
 
-use crate::helpers::command_line::{confirm_safe_code, PrintCommand};
-use crate::helpers::general::ai_task_request;
+use crate::helpers::auth_check::{
+    auth_routes, check_protected_routes, new_credentials, session_token, AuthCheck, AuthRoutes,
+};
+use crate::helpers::backend_templates::{
+    add_missing_dependencies, resolve_backend_template, BackendTemplate, Storage,
+};
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::events::{publish, AgentEvent};
+use crate::helpers::general::{ai_task_request_with_memory, AITaskError};
+use crate::helpers::persistence::{
+    persistence_probe, probe_nonce, read_probe, write_probe, PersistenceProbe,
+};
+use crate::helpers::review::{review_gate, ReviewDecision, ReviewGate};
+use crate::helpers::run_context::current_workspace;
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
 use crate::models::agents::agent_traits::{FactSheet, RouteObject, SpecialFunctions};
+use crate::models::general::api_schema::HttpMethod;
 
 use async_trait::async_trait;
 use reqwest::Client;
-use std::process::{Command, Stdio};
+use serde_json::Value;
+use std::io;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
 use std::time::Duration;
 use tokio::time;
 
+// Migrations are applied in name order, the seed only fills a new database
+const INITIAL_MIGRATION: &str = "0001_init.sql";
+const SEED_FILE: &str = "seed.sql";
+
+// Words that mark a clause of a change request as taking something out
+const REMOVAL_WORDS: [&str; 4] = ["remove", "delete", "drop", "get rid of"];
+
 #[derive(Debug)]
 pub struct AgentBackendDeveloper {
     attributes: BasicAgent,
@@ -32,6 +59,7 @@ impl AgentBackendDeveloper {
             position: "Backend Developer".to_string(),
             state: AgentState::Discovery,
             memory: vec![],
+            pending_feedback: vec![],
         };
 
         Self {
@@ -41,79 +69,369 @@ impl AgentBackendDeveloper {
         }
     }
 
-    async fn call_initial_backend_code(&mut self, factsheet: &mut FactSheet) {
-        let code_template_str: String = read_code_template_contents();
+    async fn call_initial_backend_code(
+        &mut self,
+        factsheet: &mut FactSheet,
+        template: &BackendTemplate,
+    ) -> Result<(), AITaskError> {
+        let code_template_str: String = read_code_template_contents(template);
 
         // Concatenate Instruction
-        let msg_context: String = format!(
-            "CODE TEMPLATE: {} \n PROJECT_DESCRIPTION: {} \n",
-            code_template_str, factsheet.project_description
-        );
+        let mut msg_context: String = factsheet.with_requirements(format!(
+            "{} CODE TEMPLATE: {} \n PROJECT_DESCRIPTION: {} \n EXTERNAL_API_SAMPLES: {} \n",
+            template.prompt_context(),
+            code_template_str,
+            factsheet.project_description,
+            factsheet.external_api_samples()
+        ));
+        if let Some(migration) = &factsheet.database_migration {
+            msg_context = format!("{} \n MIGRATION: {}", msg_context, migration);
+        }
+        if let Some(auth) = &template.auth {
+            msg_context = format!("{} \n AUTH_MODULE: {}", msg_context, auth.source);
+        }
 
-        let ai_response: String = ai_task_request(
+        let ai_response: String = ai_task_request_with_memory(
             msg_context,
-            &self.attributes.position,
+            &mut self.attributes,
             get_function_string!(print_backend_webserver_code),
             print_backend_webserver_code,
+            &[],
         )
-        .await;
+        .await?;
 
         save_backend_code(&ai_response);
         factsheet.backend_code = Some(ai_response);
+        Ok(())
     }
 
-    async fn call_improved_backend_code(&mut self, factsheet: &mut FactSheet) {
+    // Tables for the data model, and example rows for a new database
+    async fn call_database_migration(
+        &mut self,
+        factsheet: &mut FactSheet,
+    ) -> Result<(), AITaskError> {
+        let data_model: String = match &factsheet.requirements {
+            Some(requirements) if !requirements.data_entities.is_empty() => {
+                serde_json::to_string(&requirements.data_entities).unwrap_or_default()
+            }
+            _ => "None, derive it from the PROJECT_DESCRIPTION".to_string(),
+        };
         let msg_context: String = format!(
-            "CODE TEMPLATE: {:?} \n PROJECT_DESCRIPTION: {:?} \n",
-            factsheet.backend_code, factsheet
+            "DATA_MODEL: {} \n PROJECT_DESCRIPTION: {} \n",
+            data_model, factsheet.project_description
         );
+        let migration: String = ai_task_request_with_memory(
+            msg_context,
+            &mut self.attributes,
+            get_function_string!(print_sql_migration),
+            print_sql_migration,
+            &[],
+        )
+        .await?;
+        save_migration(INITIAL_MIGRATION, &migration);
+
+        let seed_context: String = format!(
+            "MIGRATION: {} \n PROJECT_DESCRIPTION: {} \n",
+            migration, factsheet.project_description
+        );
+        let seed: String = ai_task_request_with_memory(
+            seed_context,
+            &mut self.attributes,
+            get_function_string!(print_seed_data),
+            print_seed_data,
+            &[],
+        )
+        .await?;
+        save_migration(SEED_FILE, &seed);
 
-        let ai_response: String = ai_task_request(
+        factsheet.database_migration = Some(migration);
+        Ok(())
+    }
+
+    // Schema changes of an amend run go in a migration of their own, applied ones stay as they are
+    async fn call_amended_migration(
+        &mut self,
+        factsheet: &mut FactSheet,
+        change: &str,
+    ) -> Result<(), AITaskError> {
+        let migration: String = factsheet.database_migration.clone().unwrap_or_default();
+        let msg_context: String = format!("MIGRATION: {} \n CHANGE: {} \n", migration, change);
+        let ai_response: String = ai_task_request_with_memory(
             msg_context,
-            &self.attributes.position,
+            &mut self.attributes,
+            get_function_string!(print_amended_migration),
+            print_amended_migration,
+            &[],
+        )
+        .await?;
+
+        // The data model is untouched
+        if ai_response.trim().is_empty() || ai_response.trim().eq_ignore_ascii_case("none") {
+            return Ok(());
+        }
+        save_migration(&next_migration_name("amend"), &ai_response);
+        factsheet.database_migration = Some(format!("{}\n{}", migration, ai_response));
+        Ok(())
+    }
+
+    async fn call_improved_backend_code(
+        &mut self,
+        factsheet: &mut FactSheet,
+        template: &BackendTemplate,
+    ) -> Result<(), AITaskError> {
+        let msg_context: String = format!(
+            "{} CODE TEMPLATE: {:?} \n PROJECT_DESCRIPTION: {:?} \n EXTERNAL_API_SAMPLES: {} \n",
+            template.prompt_context(),
+            factsheet.backend_code,
+            factsheet,
+            factsheet.external_api_samples()
+        );
+
+        let ai_response: String = ai_task_request_with_memory(
+            msg_context,
+            &mut self.attributes,
             get_function_string!(print_improved_webserver_code),
             print_improved_webserver_code,
+            &[],
         )
-        .await;
+        .await?;
+
+        save_backend_code(&ai_response);
+        factsheet.backend_code = Some(ai_response);
+        Ok(())
+    }
+
+    // Targeted change to code that already works, for an amend run
+    async fn call_amend_backend_code(
+        &mut self,
+        factsheet: &mut FactSheet,
+        template: &BackendTemplate,
+        change: &str,
+    ) -> Result<(), AITaskError> {
+        let mut msg_context: String = format!(
+            "{} PROJECT_DESCRIPTION: {} \n CODE_INPUT: {} \n CHANGE: {} \n",
+            template.prompt_context(),
+            factsheet.project_description,
+            factsheet.backend_code.as_deref().unwrap_or_default(),
+            change
+        );
+        if let Some(migration) = &factsheet.database_migration {
+            msg_context = format!("{} \n MIGRATION: {}", msg_context, migration);
+        }
+
+        let ai_response: String = ai_task_request_with_memory(
+            msg_context,
+            &mut self.attributes,
+            get_function_string!(print_amended_webserver_code),
+            print_amended_webserver_code,
+            &[],
+        )
+        .await?;
 
         save_backend_code(&ai_response);
         factsheet.backend_code = Some(ai_response);
+        Ok(())
     }
 
-    async fn call_fix_code_bugs(&mut self, factsheet: &mut FactSheet) {
+    async fn call_fix_code_bugs(
+        &mut self,
+        factsheet: &mut FactSheet,
+        template: &BackendTemplate,
+    ) -> Result<(), AITaskError> {
         let msg_context: String = format!(
-            "BROKEN_CODE: {:?} \n ERROR_BUGS: {:?} \n
+            "{} BROKEN_CODE: {:?} \n ERROR_BUGS: {:?} \n EXTERNAL_API_SAMPLES: {} \n
       THIS FUNCTION ONLY OUTPUTS CODE. JUST OUTPUT THE CODE.",
-            factsheet.backend_code, self.bug_errors
+            template.prompt_context(),
+            factsheet.backend_code,
+            self.bug_errors,
+            factsheet.external_api_samples()
         );
 
-        let ai_response: String = ai_task_request(
+        // Earlier fix attempts show the LLM which changes did not work
+        let ai_response: String = ai_task_request_with_memory(
             msg_context,
-            &self.attributes.position,
+            &mut self.attributes,
             get_function_string!(print_fixed_code),
             print_fixed_code,
+            &[get_function_string!(print_fixed_code)],
         )
-        .await;
+        .await?;
 
         save_backend_code(&ai_response);
         factsheet.backend_code = Some(ai_response);
+        Ok(())
     }
 
-    async fn call_extract_rest_api_endpoints(&self) -> String {
+    async fn call_extract_rest_api_endpoints(
+        &mut self,
+        template: &BackendTemplate,
+    ) -> Result<String, AITaskError> {
         let backend_code: String = read_exec_main_contents();
 
         // Structure message context
-        let msg_context: String = format!("CODE_INPUT: {}", backend_code);
+        let msg_context: String = format!(
+            "FRAMEWORK: {} \n ROUTE_SYNTAX: {} \n CODE_INPUT: {}",
+            template.framework.name(),
+            template.framework.route_syntax(),
+            backend_code
+        );
 
-        let ai_response: String = ai_task_request(
+        ai_task_request_with_memory(
             msg_context,
-            &self.attributes.position,
+            &mut self.attributes,
             get_function_string!(print_rest_api_endpoints),
             print_rest_api_endpoints,
+            &[],
         )
-        .await;
+        .await
+    }
+
+    // Post a record, restart the server and read the record back
+    async fn check_data_survives_restart(
+        &self,
+        template: &BackendTemplate,
+        project_path: &Path,
+        server: &mut RunningServer,
+        probe: &PersistenceProbe,
+        auth: Option<(&AuthRoutes, &Value)>,
+    ) -> Result<(), String> {
+        let client: Client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let base_url: String = server.base_url();
+
+        // Sessions do not outlive the server, so sign in again after the restart
+        let token: Option<String> = session_token(&client, &base_url, auth).await?;
+        write_probe(&client, &base_url, probe, token.as_deref()).await?;
 
-        ai_response
+        PrintCommand::UnitTest.print_agent_message(
+            self.attributes.position.as_str(),
+            "Backend Code Unit Testing: Restarting web server...",
+        );
+        server
+            .restart(template, project_path)
+            .map_err(|e| format!("Could not restart the web server: {}", e))?;
+        time::sleep(Duration::from_secs(template.harness.startup_secs)).await;
+
+        let token: Option<String> = session_token(&client, &base_url, auth).await?;
+        match read_probe(&client, &base_url, probe, token.as_deref()).await? {
+            true => Ok(()),
+            false => Err(format!("GET {} no longer lists the record", probe.route)),
+        }
+    }
+
+    // GET every unprotected endpoint, the routes that did not answer 200 come back for rework
+    async fn failing_endpoints(&self, base_url: &str, endpoints: &[RouteObject]) -> Vec<String> {
+        // Create client with timout
+        let client: Client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+
+        let mut failures: Vec<String> = vec![];
+        for endpoint in endpoints {
+            if endpoint.requires_auth {
+                continue;
+            }
+
+            // Confirm url testing
+            let testing_msg: String = format!("Testing endpoint '{}'...", endpoint.route);
+            PrintCommand::UnitTest
+                .print_agent_message(self.attributes.position.as_str(), testing_msg.as_str());
+
+            // Test url
+            let url: String = format!("{}{}", base_url, endpoint.route);
+            let status_res: Result<u16, reqwest::Error> = check_status_code(&client, &url).await;
+            publish(AgentEvent::EndpointTestResult {
+                agent: self.attributes.position.clone(),
+                route: endpoint.route.to_string(),
+                status_code: status_res.as_ref().ok().copied(),
+                passed: matches!(status_res, Ok(200)),
+                error: status_res.as_ref().err().map(|e| e.to_string()),
+            });
+            match status_res {
+                Ok(200) => {}
+                Ok(status_code) => failures.push(format!(
+                    "GET {} returned {} instead of 200",
+                    endpoint.route, status_code
+                )),
+                Err(e) => failures.push(format!("GET {} failed: {}", endpoint.route, e)),
+            }
+        }
+        failures
+    }
+
+    // Sign up and check the protected routes, the checks that did not pass come back for rework
+    async fn failing_auth_checks(
+        &self,
+        base_url: &str,
+        routes: &AuthRoutes,
+        credentials: &Value,
+    ) -> Vec<String> {
+        let client: Client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let checks: Vec<AuthCheck> =
+            check_protected_routes(&client, base_url, routes, credentials).await;
+
+        let failures: Vec<String> = checks.iter().filter_map(AuthCheck::failure).collect();
+        for check in checks {
+            publish(AgentEvent::EndpointTestResult {
+                agent: self.attributes.position.clone(),
+                route: check.route,
+                status_code: check.status_code,
+                passed: check.passed,
+                error: check.error,
+            });
+        }
+        failures
+    }
+
+    // Hand the errors back for rework, the count carries over until the server passes its checks
+    fn record_bug(&mut self, error_str: String) {
+        // Update error stats
+        self.bug_count += 1;
+        publish(AgentEvent::BugFound {
+            agent: self.attributes.position.clone(),
+            bug_count: self.bug_count,
+            errors: error_str.clone(),
+        });
+        self.bug_errors = Some(error_str);
+
+        // Exit if too many bugs
+        if self.bug_count > 2 {
+            PrintCommand::Issue.print_agent_message(
+                self.attributes.position.as_str(),
+                "Backend Code Unit Testing: Too many bugs found in code",
+            );
+            panic!("Error: Too many bugs")
+        }
+
+        // Pass back for rework
+        self.attributes.update_state(AgentState::Working);
+    }
+
+    // Let the reviewer look at the code version just written
+    async fn review_code_version(&mut self, factsheet: &FactSheet) -> ReviewDecision {
+        let code_summary: String = format!(
+            "{} lines written to {}",
+            factsheet
+                .backend_code
+                .as_deref()
+                .unwrap_or("")
+                .lines()
+                .count(),
+            current_workspace().exec_main_path.display()
+        );
+        review_gate(ReviewGate::CodeVersion, &mut self.attributes, code_summary).await
+    }
+}
+
+impl Default for AgentBackendDeveloper {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -127,21 +445,85 @@ impl SpecialFunctions for AgentBackendDeveloper {
         &mut self,
         factsheet: &mut FactSheet,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let template: BackendTemplate = resolve_backend_template(factsheet)?;
+        let template_msg: String = format!(
+            "Writing the backend from the {} template ({})",
+            template.name,
+            template.framework.name()
+        );
+        PrintCommand::AICall.print_agent_message(self.attributes.position.as_str(), &template_msg);
+
         while self.attributes.state != AgentState::Finished {
             match &self.attributes.state {
                 AgentState::Discovery => {
-                    self.call_initial_backend_code(factsheet).await;
-                    self.attributes.state = AgentState::Working;
+                    // SQLite backends are written against their migration
+                    if template.storage == Storage::Sqlite && factsheet.database_migration.is_none()
+                    {
+                        self.call_database_migration(factsheet).await?;
+                    }
+                    // Login projects get the auth module to build on
+                    if let Some(auth) = &template.auth {
+                        save_auth_module(auth.source);
+                    }
+
+                    // A change request modifies the existing code, then goes straight to testing.
+                    // Feedback reworks the code in Working, so the migration is only added once.
+                    if let (Some(change), Some(_)) =
+                        (factsheet.change_request.clone(), &factsheet.backend_code)
+                    {
+                        if template.storage == Storage::Sqlite
+                            && factsheet.database_migration.is_some()
+                        {
+                            self.call_amended_migration(factsheet, &change).await?;
+                        }
+                        self.call_amend_backend_code(factsheet, &template, &change)
+                            .await?;
+                        match self.review_code_version(factsheet).await {
+                            ReviewDecision::Approve => {
+                                self.attributes.update_state(AgentState::UnitTesting)
+                            }
+                            ReviewDecision::Reject => {
+                                return Err(ReviewGate::CodeVersion.rejection().into())
+                            }
+                            ReviewDecision::Feedback(_) => {
+                                self.attributes.update_state(AgentState::Working)
+                            }
+                        }
+                        continue;
+                    }
+                    self.call_initial_backend_code(factsheet, &template).await?;
+
+                    // Feedback on the first version goes into the improvement step
+                    if self.review_code_version(factsheet).await == ReviewDecision::Reject {
+                        return Err(ReviewGate::CodeVersion.rejection().into());
+                    }
+                    self.attributes.update_state(AgentState::Working);
                     continue;
                 }
 
                 AgentState::Working => {
-                    if self.bug_count == 0 {
-                        self.call_improved_backend_code(factsheet).await;
-                    } else {
-                        self.call_fix_code_bugs(factsheet).await;
+                    // An amend run keeps to targeted changes, it never rewrites the whole server
+                    match (self.bug_count, factsheet.change_request.clone()) {
+                        (0, Some(change)) => {
+                            self.call_amend_backend_code(factsheet, &template, &change)
+                                .await?
+                        }
+                        (0, None) => {
+                            self.call_improved_backend_code(factsheet, &template)
+                                .await?
+                        }
+                        _ => self.call_fix_code_bugs(factsheet, &template).await?,
                     }
-                    self.attributes.state = AgentState::UnitTesting;
+
+                    // Feedback repeats this step
+                    match self.review_code_version(factsheet).await {
+                        ReviewDecision::Approve => {}
+                        ReviewDecision::Reject => {
+                            return Err(ReviewGate::CodeVersion.rejection().into())
+                        }
+                        ReviewDecision::Feedback(_) => continue,
+                    }
+                    self.attributes.update_state(AgentState::UnitTesting);
                     continue;
                 }
 
@@ -152,10 +534,22 @@ impl SpecialFunctions for AgentBackendDeveloper {
                         "Backend Code Unit Testing: Requesting user input",
                     );
 
-                    let is_safe_code: bool = confirm_safe_code();
-
-                    if !is_safe_code {
-                        panic!("Better go work on some AI alignment instead...")
+                    let run_summary: String = format!(
+                        "cargo build and run in {}",
+                        current_workspace().web_server_project_path.display()
+                    );
+                    match review_gate(ReviewGate::RunServer, &mut self.attributes, run_summary)
+                        .await
+                    {
+                        ReviewDecision::Approve => {}
+                        ReviewDecision::Reject => {
+                            return Err(ReviewGate::RunServer.rejection().into())
+                        }
+                        // Rework the code with the feedback before it runs
+                        ReviewDecision::Feedback(_) => {
+                            self.attributes.update_state(AgentState::Working);
+                            continue;
+                        }
                     }
 
                     // Build and Test Code
@@ -164,41 +558,40 @@ impl SpecialFunctions for AgentBackendDeveloper {
                         "Backend Code Unit Testing: building project...",
                     );
 
-                    // Build Code
-                    let build_backend_server: std::process::Output = Command::new("cargo")
-                        .arg("build")
-                        .current_dir(WEB_SERVER_PROJECT_PATH)
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .output()
-                        .expect("Failed to build backend application");
-
-                    // Determine if build errors
-                    if build_backend_server.status.success() {
-                        self.bug_count = 0;
-                        PrintCommand::UnitTest.print_agent_message(
-                            self.attributes.position.as_str(),
-                            "Backend Code Unit Testing: Test server build successful...",
-                        );
-                    } else {
-                        let error_arr: Vec<u8> = build_backend_server.stderr;
-                        let error_str: String = String::from_utf8(error_arr).unwrap();
-
-                        // Update error stats
-                        self.bug_count += 1;
-                        self.bug_errors = Some(error_str);
-
-                        // Exit if too many bugs
-                        if self.bug_count > 2 {
-                            PrintCommand::Issue.print_agent_message(
+                    // The project needs every crate the template allows
+                    let project_path: PathBuf = current_workspace().web_server_project_path;
+                    match add_missing_dependencies(&template, &project_path) {
+                        Ok(added) if !added.is_empty() => {
+                            PrintCommand::UnitTest.print_agent_message(
                                 self.attributes.position.as_str(),
-                                "Backend Code Unit Testing: Too many bugs found in code",
+                                format!("Added to Cargo.toml: {}", added.join(", ")).as_str(),
                             );
-                            panic!("Error: Too many bugs")
                         }
+                        Ok(_) => {}
+                        Err(e) => PrintCommand::Issue
+                            .print_agent_message(self.attributes.position.as_str(), e.as_str()),
+                    }
 
-                        // Pass back for rework
-                        self.attributes.state = AgentState::Working;
+                    // Build Code
+                    let build_backend_server: std::process::Output =
+                        Command::new(template.harness.build[0])
+                            .args(&template.harness.build[1..])
+                            .current_dir(&project_path)
+                            .stdout(Stdio::piped())
+                            .stderr(Stdio::piped())
+                            .output()
+                            .expect("Failed to build backend application");
+
+                    // Determine if build errors
+                    let build_output: String =
+                        String::from_utf8_lossy(&build_backend_server.stderr).to_string();
+                    publish(AgentEvent::BuildResult {
+                        agent: self.attributes.position.clone(),
+                        success: build_backend_server.status.success(),
+                        output: build_output.clone(),
+                    });
+                    if !build_backend_server.status.success() {
+                        self.record_bug(build_output);
                         continue;
                     }
 
@@ -208,106 +601,155 @@ impl SpecialFunctions for AgentBackendDeveloper {
                     */
 
                     // Extract API Endpoints
-                    let api_endpoints_str: String = self.call_extract_rest_api_endpoints().await;
+                    let api_endpoints_str: String =
+                        self.call_extract_rest_api_endpoints(&template).await?;
 
                     // Convert API Endpoints into Values
                     let api_endpoints: Vec<RouteObject> =
                         serde_json::from_str(api_endpoints_str.as_str())
                             .expect("Failed to decode API Endpoints");
 
+                    // Routes that worked before a change must still work after it
+                    let previous_endpoints: Vec<RouteObject> =
+                        factsheet.api_endpoint_schema.clone().unwrap_or_default();
+                    let all_endpoints: Vec<RouteObject> = merge_endpoints(
+                        &api_endpoints,
+                        previous_endpoints,
+                        factsheet.change_request.as_deref().unwrap_or_default(),
+                    );
+
+                    // Store API Endpoints
+                    factsheet.api_endpoint_schema = Some(all_endpoints.clone());
+
                     // Define endpoints to check
-                    let check_endpoints: Vec<RouteObject> = api_endpoints
+                    let check_endpoints: Vec<RouteObject> = all_endpoints
                         .iter()
-                        .filter(|&route_object| {
-                            route_object.method == "get" && route_object.is_route_dynamic == "false"
+                        .filter(|route_object| {
+                            route_object.method == HttpMethod::Get
+                                && !route_object.route.is_dynamic()
                         })
                         .cloned()
                         .collect();
 
-                    // Store API Endpoints
-                    factsheet.api_endpoint_schema = Some(check_endpoints.clone());
-
                     // Run backend application
                     PrintCommand::UnitTest.print_agent_message(
                         self.attributes.position.as_str(),
                         "Backend Code Unit Testing: Starting web server...",
                     );
 
-                    // Execute running server
-                    let mut run_backend_server: std::process::Child = Command::new("cargo")
-                        .arg("run")
-                        .current_dir(WEB_SERVER_PROJECT_PATH)
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .spawn()
-                        .expect("Failed to run backend application");
+                    // Execute running server, on a port of its own so concurrent runs do not clash
+                    let mut run_backend_server: RunningServer =
+                        RunningServer::start(&template, &project_path)?;
 
                     // Let user know testing on server will take place soon
+                    let launch_msg: String = format!(
+                        "Backend Code Unit Testing: Launching tests on server in {} seconds...",
+                        template.harness.startup_secs
+                    );
                     PrintCommand::UnitTest.print_agent_message(
                         self.attributes.position.as_str(),
-                        "Backend Code Unit Testing: Launching tests on server in 5 seconds...",
+                        launch_msg.as_str(),
                     );
 
-                    let seconds_sleep: Duration = Duration::from_secs(5);
+                    let seconds_sleep: Duration =
+                        Duration::from_secs(template.harness.startup_secs);
                     time::sleep(seconds_sleep).await;
 
-                    // Check status code
-                    for endpoint in check_endpoints {
-                        // Confirm url testing
-                        let testing_msg: String =
-                            format!("Testing endpoint '{}'...", endpoint.route);
-                        PrintCommand::UnitTest.print_agent_message(
-                            self.attributes.position.as_str(),
-                            testing_msg.as_str(),
-                        );
+                    // Check status code, protected routes are checked signed in and out below
+                    let failed_endpoints: Vec<String> = self
+                        .failing_endpoints(&run_backend_server.base_url(), &check_endpoints)
+                        .await;
+                    if !failed_endpoints.is_empty() {
+                        run_backend_server.stop();
+                        self.record_bug(format!(
+                            "These endpoints failed their checks: {}",
+                            failed_endpoints.join("; ")
+                        ));
+                        continue;
+                    }
 
-                        // Create client with timout
-                        let client: Client = Client::builder()
-                            .timeout(Duration::from_secs(5))
-                            .build()
-                            .unwrap();
-
-                        // Test url
-                        let url: String = format!("http://localhost:8080{}", endpoint.route);
-                        match check_status_code(&client, &url).await {
-                            Ok(status_code) => {
-                                if status_code != 200 {
-                                    let err_msg: String = format!(
-                                        "WARNING: Failed to call backend url endpoint {}",
-                                        endpoint.route
-                                    );
-                                    PrintCommand::Issue.print_agent_message(
-                                        self.attributes.position.as_str(),
-                                        err_msg.as_str(),
-                                    );
-                                }
-                            }
-                            Err(e) => {
-                                // kill $(lsof -t -i:8080)
-                                run_backend_server
-                                    .kill()
-                                    .expect("Failed to kill backend web server");
-                                let err_msg: String = format!("Error checking backend {}", e);
-                                PrintCommand::Issue.print_agent_message(
-                                    self.attributes.position.as_str(),
-                                    err_msg.as_str(),
-                                );
-                            }
+                    // Protected routes turn anonymous requests away and serve signed in users
+                    let auth: Option<(AuthRoutes, Value)> =
+                        auth_routes(&all_endpoints).map(|routes| {
+                            let credentials: Value =
+                                new_credentials(routes.register.request_body.as_ref());
+                            (routes, credentials)
+                        });
+                    if let Some((routes, credentials)) = &auth {
+                        let failed_checks: Vec<String> = self
+                            .failing_auth_checks(
+                                &run_backend_server.base_url(),
+                                routes,
+                                credentials,
+                            )
+                            .await;
+                        if !failed_checks.is_empty() {
+                            run_backend_server.stop();
+                            self.record_bug(format!(
+                                "Protected routes failed their checks: {}",
+                                failed_checks.join("; ")
+                            ));
+                            continue;
                         }
                     }
 
-                    save_api_endpoints(&api_endpoints_str);
+                    // Data written through the api must still be there after a restart
+                    if let Some(probe) = persistence_probe(&all_endpoints, probe_nonce()) {
+                        let check_res: Result<(), String> = self
+                            .check_data_survives_restart(
+                                &template,
+                                &project_path,
+                                &mut run_backend_server,
+                                &probe,
+                                auth.as_ref()
+                                    .map(|(routes, credentials)| (routes, credentials)),
+                            )
+                            .await;
+                        publish(AgentEvent::PersistenceChecked {
+                            agent: self.attributes.position.clone(),
+                            route: probe.route.clone(),
+                            passed: check_res.is_ok(),
+                            error: check_res.clone().err(),
+                        });
+
+                        // Lost data is a bug in the code, not something to report and move past
+                        if let Err(e) = check_res {
+                            run_backend_server.stop();
+                            self.record_bug(format!(
+                                "Data posted to {} was gone after a server restart: {}",
+                                probe.route, e
+                            ));
+                            continue;
+                        }
+                    }
+
+                    // Every export describes the merged endpoints, as the factsheet does
+                    let all_endpoints_str: String = serde_json::to_string_pretty(&all_endpoints)
+                        .expect("Failed to encode API Endpoints");
+                    save_api_endpoints(&all_endpoints_str);
+                    save_typescript_client(&all_endpoints);
+
+                    // Export OpenAPI document for Swagger UI and client generators
+                    if let Err(schema_errors) = save_openapi_schema(&all_endpoints) {
+                        let err_msg: String = format!(
+                            "WARNING: Generated OpenAPI schema is invalid: {}",
+                            schema_errors.join("; ")
+                        );
+                        PrintCommand::Issue.print_agent_message(
+                            self.attributes.position.as_str(),
+                            err_msg.as_str(),
+                        );
+                    }
 
                     PrintCommand::UnitTest.print_agent_message(
                         self.attributes.position.as_str(),
                         "Backend testing complete...",
                     );
 
-                    run_backend_server
-                        .kill()
-                        .expect("Failed to kill backend web server on completion");
+                    run_backend_server.stop();
 
-                    self.attributes.state = AgentState::Finished;
+                    self.bug_count = 0;
+                    self.attributes.update_state(AgentState::Finished);
                 }
 
                 _ => {}
@@ -317,9 +759,346 @@ impl SpecialFunctions for AgentBackendDeveloper {
     }
 }
 
+// Endpoints of the new code, plus earlier ones it lost unless the change takes them out
+fn merge_endpoints(
+    current: &[RouteObject],
+    previous: Vec<RouteObject>,
+    change: &str,
+) -> Vec<RouteObject> {
+    let change: String = change.to_lowercase();
+    let removal_clauses: Vec<&str> = change
+        .split(['.', ',', ';', '\n'])
+        .flat_map(|clause| clause.split(" and "))
+        .filter(|clause| REMOVAL_WORDS.iter().any(|word| clause.contains(word)))
+        .collect();
+
+    let mut endpoints: Vec<RouteObject> = current.to_vec();
+    for endpoint in previous {
+        let route: String = endpoint.route.to_string().to_lowercase();
+        let is_removed: bool = removal_clauses.iter().any(|clause| {
+            clause
+                .split_whitespace()
+                .any(|word| word.trim_matches(|c: char| "'\"`()".contains(c)) == route)
+        });
+        let is_kept: bool = endpoints
+            .iter()
+            .any(|kept| kept.route == endpoint.route && kept.method == endpoint.method);
+        if !is_removed && !is_kept {
+            endpoints.push(endpoint);
+        }
+    }
+    endpoints
+}
+
+// The generated server under test. It is killed when dropped, so a cancelled run does not
+// leave it listening.
+struct RunningServer {
+    child: Child,
+    port: u16,
+}
+
+impl RunningServer {
+    fn start(template: &BackendTemplate, project_path: &Path) -> io::Result<Self> {
+        let port: u16 = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let child: Child = Self::spawn(template, project_path, port)?;
+        Ok(Self { child, port })
+    }
+
+    fn spawn(template: &BackendTemplate, project_path: &Path, port: u16) -> io::Result<Child> {
+        Command::new(template.harness.run[0])
+            .args(&template.harness.run[1..])
+            .env(template.port_env_var, port.to_string())
+            .current_dir(project_path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    }
+
+    fn base_url(&self) -> String {
+        format!("http://localhost:{}", self.port)
+    }
+
+    // Same port, so the urls already handed out stay valid
+    fn restart(&mut self, template: &BackendTemplate, project_path: &Path) -> io::Result<()> {
+        self.stop();
+        self.child = Self::spawn(template, project_path, self.port)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for RunningServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::apis::provider::MockProvider;
+    use crate::helpers::backend_templates::{TestHarness, ACTIX_JSON};
+    use crate::helpers::general::Workspace;
+    use crate::helpers::review::{ReviewRequest, Reviewer};
+    use crate::helpers::run_context::{with_run_context, RunContext};
+    use crate::helpers::stub_server::{StubResponse, StubServer};
+    use std::env;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn tests_running_server_gets_port_and_is_killed_on_drop() {
+        let project_path: PathBuf =
+            env::temp_dir().join(format!("running_server_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_path);
+        fs::create_dir_all(&project_path).unwrap();
+        let template: BackendTemplate = BackendTemplate {
+            harness: TestHarness {
+                build: &["true"],
+                run: &["sh", "-c", "echo $PORT > port.txt; exec sleep 30"],
+                startup_secs: 0,
+            },
+            ..ACTIX_JSON
+        };
+
+        let server: RunningServer = RunningServer::start(&template, &project_path).unwrap();
+        let pid: String = server.child.id().to_string();
+        let port: u16 = server.port;
+        assert_eq!(server.base_url(), format!("http://localhost:{}", port));
+        let mut port_written: String = String::new();
+        for _ in 0..50 {
+            port_written = fs::read_to_string(project_path.join("port.txt")).unwrap_or_default();
+            if !port_written.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(port_written.trim(), port.to_string());
+
+        drop(server);
+        let alive: bool = Command::new("kill")
+            .args(["-0", &pid])
+            .stderr(Stdio::null())
+            .status()
+            .unwrap()
+            .success();
+        assert!(!alive);
+        let _ = fs::remove_dir_all(&project_path);
+    }
+
+    #[test]
+    fn tests_merge_endpoints_keeps_lost_routes() {
+        let routes: Vec<RouteObject> = serde_json::from_str(
+            r#"[
+              {"route": "/task", "method": "get", "request_body": "None", "response": "None"},
+              {"route": "/task/{id}", "method": "delete", "request_body": "None", "response": "None"},
+              {"route": "/health", "method": "get", "request_body": "None", "response": "None"}
+            ]"#,
+        )
+        .unwrap();
+        let current: Vec<RouteObject> = vec![routes[0].clone()];
+
+        let merged: Vec<RouteObject> = merge_endpoints(
+            &current,
+            routes.clone(),
+            "Add a due date to tasks and remove the /health route.",
+        );
+        let merged: Vec<String> = merged
+            .iter()
+            .map(|endpoint| format!("{} {}", endpoint.method, endpoint.route))
+            .collect();
+        assert_eq!(merged, vec!["get /task", "delete /task/{id}"]);
+        assert_eq!(merge_endpoints(&current, routes, "").len(), 3);
+    }
+
+    #[tokio::test]
+    async fn tests_failing_endpoints_go_back_for_rework() {
+        let endpoints: Vec<RouteObject> = serde_json::from_str(
+            r#"[
+              {"route": "/health", "method": "get", "request_body": "None", "response": "None"},
+              {"route": "/task", "method": "get", "request_body": "None", "response": "None"},
+              {"route": "/profile", "method": "get", "request_body": "None", "response": "None", "requires_auth": "true"}
+            ]"#,
+        )
+        .unwrap();
+        let server: StubServer = StubServer::start(vec![
+            ("/health", vec![StubResponse::new(200, "ok")]),
+            ("/task", vec![StubResponse::new(500, "")]),
+        ])
+        .await;
+        let context: RunContext = RunContext {
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+
+        let agent: AgentBackendDeveloper = AgentBackendDeveloper::new();
+        let failures: Vec<String> = with_run_context(
+            context,
+            agent.failing_endpoints(&server.url(""), &endpoints),
+        )
+        .await;
+        assert_eq!(failures, vec!["GET /task returned 500 instead of 200"]);
+        let requested: Vec<String> = server
+            .requests()
+            .into_iter()
+            .map(|request| request.path)
+            .collect();
+        assert_eq!(requested, vec!["/health", "/task"]);
+    }
+
+    #[tokio::test]
+    async fn tests_leaking_protected_route_goes_back_for_rework() {
+        let endpoints: Vec<RouteObject> = serde_json::from_str(
+            r#"[
+              {"route": "/register", "method": "post", "request_body": {"username": "string", "password": "string"}, "response": "None"},
+              {"route": "/login", "method": "post", "request_body": {"username": "string", "password": "string"}, "response": {"token": "string"}},
+              {"route": "/task", "method": "get", "request_body": "None", "response": "None", "requires_auth": "true"}
+            ]"#,
+        )
+        .unwrap();
+        let routes: AuthRoutes = auth_routes(&endpoints).unwrap();
+        let credentials: Value = new_credentials(routes.register.request_body.as_ref());
+
+        // The task list is served without a token
+        let server: StubServer = StubServer::start(vec![
+            ("/register", vec![StubResponse::new(200, "")]),
+            (
+                "/login",
+                vec![StubResponse::json(200, r#"{"token": "abc"}"#)],
+            ),
+            ("/task", vec![StubResponse::json(200, "[]")]),
+        ])
+        .await;
+        let context: RunContext = RunContext {
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+
+        let agent: AgentBackendDeveloper = AgentBackendDeveloper::new();
+        let failures: Vec<String> = with_run_context(
+            context,
+            agent.failing_auth_checks(&server.url(""), &routes, &credentials),
+        )
+        .await;
+        assert_eq!(failures, vec!["/task without a token returned 200"]);
+    }
+
+    #[tokio::test]
+    async fn tests_amend_adds_a_migration() {
+        let project_dir: PathBuf =
+            env::temp_dir().join(format!("amend_migration_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        let workspace: Workspace = Workspace::for_project(&project_dir, None);
+        fs::create_dir_all(workspace.migrations_dir()).unwrap();
+        let initial: &str = "CREATE TABLE IF NOT EXISTS tasks (id INTEGER PRIMARY KEY);";
+        fs::write(workspace.migrations_dir().join(INITIAL_MIGRATION), initial).unwrap();
+        fs::write(workspace.migrations_dir().join(SEED_FILE), "").unwrap();
+
+        let mut factsheet: FactSheet = serde_json::from_str(
+            r#"{"project_description": "Task list", "database_migration": "CREATE TABLE IF NOT EXISTS tasks (id INTEGER PRIMARY KEY);"}"#,
+        )
+        .unwrap();
+        let context: RunContext = RunContext {
+            provider: Arc::new(MockProvider),
+            workspace: workspace.clone(),
+            observers: vec![],
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+        let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new();
+        with_run_context(context, async {
+            agent
+                .call_amended_migration(&mut factsheet, "add a due date to tasks")
+                .await
+                .unwrap()
+        })
+        .await;
+
+        let added: String =
+            fs::read_to_string(workspace.migrations_dir().join("0002_amend.sql")).unwrap();
+        assert!(added.starts_with("ALTER TABLE tasks ADD COLUMN"));
+        let applied: String =
+            fs::read_to_string(workspace.migrations_dir().join(INITIAL_MIGRATION)).unwrap();
+        assert_eq!(applied, initial);
+        assert!(factsheet
+            .database_migration
+            .unwrap()
+            .ends_with(added.as_str()));
+        let _ = fs::remove_dir_all(&project_dir);
+    }
+
+    // Asks for a rework of the first code version, then stops before the server is run
+    #[derive(Default)]
+    struct ReworkOnceReviewer {
+        code_reviews: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Reviewer for ReworkOnceReviewer {
+        async fn review(&self, request: &ReviewRequest) -> ReviewDecision {
+            match request.gate {
+                ReviewGate::CodeVersion
+                    if self.code_reviews.fetch_add(1, Ordering::SeqCst) == 0 =>
+                {
+                    ReviewDecision::Feedback("Keep the handlers short".to_string())
+                }
+                ReviewGate::CodeVersion => ReviewDecision::Approve,
+                _ => ReviewDecision::Reject,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_amend_feedback_keeps_one_migration() {
+        let project_dir: PathBuf =
+            env::temp_dir().join(format!("amend_feedback_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        let workspace: Workspace = Workspace::for_project(&project_dir, None);
+        fs::create_dir_all(workspace.migrations_dir()).unwrap();
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        let initial: &str = "CREATE TABLE IF NOT EXISTS tasks (id INTEGER PRIMARY KEY);";
+        fs::write(workspace.migrations_dir().join(INITIAL_MIGRATION), initial).unwrap();
+
+        let mut factsheet: FactSheet = serde_json::from_str(
+            r#"{"project_description": "Task list", "backend_template": "actix_sqlite", "backend_code": "fn main() {}", "change_request": "add a due date to tasks", "database_migration": "CREATE TABLE IF NOT EXISTS tasks (id INTEGER PRIMARY KEY);"}"#,
+        )
+        .unwrap();
+        let reviewer: Arc<ReworkOnceReviewer> = Arc::new(ReworkOnceReviewer::default());
+        let context: RunContext = RunContext {
+            provider: Arc::new(MockProvider),
+            workspace: workspace.clone(),
+            reviewer: reviewer.clone(),
+            review_gates: vec![ReviewGate::CodeVersion, ReviewGate::RunServer],
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+        let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new();
+        let execute_res: Result<(), String> = with_run_context(context, async {
+            agent
+                .execute(&mut factsheet)
+                .await
+                .map_err(|e| e.to_string())
+        })
+        .await;
+
+        assert_eq!(
+            execute_res,
+            Err(ReviewGate::RunServer.rejection().to_string())
+        );
+        assert_eq!(reviewer.code_reviews.load(Ordering::SeqCst), 2);
+        let mut migrations: Vec<String> = fs::read_dir(workspace.migrations_dir())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        migrations.sort();
+        assert_eq!(migrations, vec![INITIAL_MIGRATION, "0002_amend.sql"]);
+        let _ = fs::remove_dir_all(&project_dir);
+    }
 
     #[tokio::test]
     async fn tests_backend_developer() {
@@ -348,4 +1127,4 @@ mod tests {
             .await
             .expect("Failed to execute Backend Developer agent");
     }
-}
\ No newline at end of file
+}
@@ -1,15 +1,23 @@
 use crate::models::agent_basic::basic_agent::BasicAgent;
+use crate::models::general::api_schema::{
+    deserialize_lenient_bool, deserialize_optional_shape, HttpMethod, PathTemplate, Shape,
+};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
+// The LLM's "is_route_dynamic" is ignored, the route's params decide it
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RouteObject {
-    pub is_route_dynamic: String,
-    pub method: String,
-    pub request_body: serde_json::Value,
-    pub response: serde_json::Value,
-    pub route: String,
+    pub method: HttpMethod,
+    #[serde(default, deserialize_with = "deserialize_optional_shape")]
+    pub request_body: Option<Shape>,
+    #[serde(default, deserialize_with = "deserialize_optional_shape")]
+    pub response: Option<Shape>,
+    pub route: PathTemplate,
+    // Behind the session middleware, anonymous requests get 401
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub requires_auth: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
@@ -19,17 +27,105 @@ pub struct ProjectScope {
     pub is_external_urls_required: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Clarification {
+    pub question: String,
+    pub answer: String,
+}
+
+// What the user wants, worked out from the request and their answers to clarifying questions
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Requirements {
+    pub summary: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub data_entities: Vec<String>,
+    #[serde(default)]
+    pub constraints: Vec<String>,
+    #[serde(default)]
+    pub assumptions: Vec<String>,
+    #[serde(default)]
+    pub clarifications: Vec<Clarification>,
+}
+
+// How a change request to a finished project is carried out
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChangePlan {
+    pub project_description: String,
+    pub change: String,
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub rerun_architect: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UrlProbe {
+    pub url: String,
+    pub final_url: String,
+    pub status_code: u16,
+    pub content_type: Option<String>,
+    pub latency_ms: u64,
+    pub body_sample: String,
+    pub is_json: bool,
+    pub json_shape: Option<Shape>,
+    // Why the architect dropped the url, kept so the rejection can be reviewed
+    #[serde(default)]
+    pub rejection: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct FactSheet {
     pub project_description: String,
+    #[serde(default)]
+    pub requirements: Option<Requirements>,
     pub project_scope: Option<ProjectScope>,
     pub external_urls: Option<Vec<String>>,
+    #[serde(default)]
+    pub external_url_probes: Option<Vec<UrlProbe>>,
+    // Name of the backend template the code is written from
+    #[serde(default)]
+    pub backend_template: Option<String>,
+    // SQL of the initial migration when the backend stores its data in SQLite
+    #[serde(default)]
+    pub database_migration: Option<String>,
+    // Set while an amend run applies a change to the existing backend code
+    #[serde(default)]
+    pub change_request: Option<String>,
     pub backend_code: Option<String>,
     pub api_endpoint_schema: Option<Vec<RouteObject>>,
 }
 
+impl FactSheet {
+    // Real payload shapes of the urls that passed testing, for every code prompt
+    pub fn external_api_samples(&self) -> String {
+        let samples: Vec<(&String, &Option<Shape>)> = self
+            .external_url_probes
+            .iter()
+            .flatten()
+            .filter(|probe| probe.rejection.is_none())
+            .map(|probe| (&probe.url, &probe.json_shape))
+            .collect();
+        match samples.is_empty() {
+            true => "None".to_string(),
+            false => serde_json::to_string(&samples).unwrap_or_default(),
+        }
+    }
+
+    // Add the requirements to an LLM input when the run gathered them
+    pub fn with_requirements(&self, msg_context: String) -> String {
+        match &self.requirements {
+            Some(requirements) => format!(
+                "{} \n REQUIREMENTS: {}",
+                msg_context,
+                serde_json::to_string(requirements).unwrap_or_default()
+            ),
+            None => msg_context,
+        }
+    }
+}
+
 #[async_trait]
-pub trait SpecialFunctions: Debug {
+pub trait SpecialFunctions: Debug + Send {
     // Used to that manager can get attributes from Agents
     fn get_attributes_from_agent(&self) -> &BasicAgent;
 
@@ -38,4 +134,4 @@ pub trait SpecialFunctions: Debug {
         &mut self,
         factsheet: &mut FactSheet,
     ) -> Result<(), Box<dyn std::error::Error>>;
-}
\ No newline at end of file
+}
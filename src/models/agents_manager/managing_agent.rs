@@ -1,80 +1,456 @@
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
-use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+use crate::models::agents::agent_traits::{
+    ChangePlan, Clarification, FactSheet, Requirements, SpecialFunctions,
+};
 
-use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
-use crate::helpers::general::ai_task_request;
+use crate::ai_functions::aifunc_managing::{
+    convert_user_input_to_goal, print_change_plan, print_clarifying_questions, print_requirements,
+};
+use crate::helpers::clarify::{Clarifier, MAX_CLARIFYING_QUESTIONS, NO_ANSWER};
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::events::{publish, AgentEvent};
+use crate::helpers::general::{
+    ai_task_request_decoded_with_memory, ai_task_request_with_memory, save_factsheet,
+    save_factsheet_checkpoint, AITaskError,
+};
+use crate::helpers::run_context::{current_clarifier, current_workspace, wait_while_paused};
 use crate::models::agents::agent_architect::AgentSolutionArchitect;
 use crate::models::agents::agent_backend::AgentBackendDeveloper;
+use std::fs;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 
 #[derive(Debug)]
 pub struct ManagingAgent {
     attributes: BasicAgent,
     factsheet: FactSheet,
     agents: Vec<Box<dyn SpecialFunctions>>,
+    // Change requests the scope is unaffected by only need the backend developer
+    rerun_architect: bool,
+}
+
+fn manager_attributes() -> BasicAgent {
+    BasicAgent {
+        objective: String::from("Gathers information and design solutions for website development"),
+        position: String::from("Project Manager"),
+        state: AgentState::Discovery,
+        memory: Vec::from([]),
+        pending_feedback: vec![],
+    }
 }
 
 impl ManagingAgent {
     pub async fn new(usr_req: String) -> Result<Self, Box<dyn std::error::Error>> {
-        let position: String = String::from("Project Manager");
-
-        let attributes: BasicAgent = BasicAgent {
-            objective: String::from(
-                "Gathers information and design solutions for website development",
-            ),
-            position: position.clone(),
-            state: AgentState::Discovery,
-            memory: Vec::from([]),
-        };
+        let mut attributes: BasicAgent = manager_attributes();
 
-        let project_description: String = ai_task_request(
-            usr_req,
-            &position,
-            get_func_string!(convert_user_input_to_goal),
-            convert_user_input_to_goal,
-        )
-        .await;
+        // Vague requests are clarified with the user first, when the run has someone to ask
+        let requirements: Option<Requirements> = match current_clarifier() {
+            Some(clarifier) => {
+                Some(gather_requirements(&usr_req, &mut attributes, clarifier).await?)
+            }
+            None => None,
+        };
 
         let agents: Vec<Box<dyn SpecialFunctions>> = Vec::from([]);
-        let factsheet: FactSheet = FactSheet {
-            project_description,
+        let mut factsheet: FactSheet = FactSheet {
+            project_description: String::new(),
+            requirements,
             project_scope: None,
             external_urls: None,
+            external_url_probes: None,
+            backend_template: None,
+            database_migration: None,
+            change_request: None,
             backend_code: None,
             api_endpoint_schema: None,
         };
+
+        factsheet.project_description = ai_task_request_with_memory(
+            factsheet.with_requirements(usr_req),
+            &mut attributes,
+            get_function_string!(convert_user_input_to_goal),
+            convert_user_input_to_goal,
+            &[],
+        )
+        .await?;
+
         Ok(Self {
             attributes,
             factsheet,
             agents,
+            rerun_architect: true,
+        })
+    }
+
+    // Carry out a change request on the factsheet of a finished run
+    pub async fn amend(
+        mut factsheet: FactSheet,
+        change_request: String,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut attributes: BasicAgent = manager_attributes();
+
+        // The code on disk wins, it may have been edited since the run
+        if let Ok(backend_code) = fs::read_to_string(current_workspace().exec_main_path) {
+            factsheet.backend_code = Some(backend_code);
+        }
+
+        let project: FactSheet = FactSheet {
+            backend_code: None,
+            ..factsheet.clone()
+        };
+        let msg_context: String = format!(
+            "FACTSHEET: {} \n CHANGE_REQUEST: {}",
+            serde_json::to_string(&project).unwrap_or_default(),
+            change_request
+        );
+        let plan: ChangePlan = ai_task_request_decoded_with_memory::<ChangePlan>(
+            msg_context,
+            &mut attributes,
+            get_function_string!(print_change_plan),
+            print_change_plan,
+            &[],
+        )
+        .await?;
+
+        let plan_msg: String = format!(
+            "Amending the project: {} ({})",
+            plan.change,
+            match plan.rerun_architect {
+                true => "scope changed, the Solutions Architect runs again",
+                false => "only the backend code changes",
+            }
+        );
+        PrintCommand::AICall.print_agent_message(attributes.position.as_str(), &plan_msg);
+
+        factsheet.project_description = plan.project_description;
+        factsheet.change_request = Some(plan.change);
+        Ok(Self {
+            attributes,
+            factsheet,
+            agents: vec![],
+            rerun_architect: plan.rerun_architect,
         })
     }
 
-    fn add_agent(&mut self, agent: Box<dyn SpecialFunctions>) {
+    pub fn add_agent(&mut self, agent: Box<dyn SpecialFunctions>) {
         self.agents.push(agent);
     }
 
+    pub fn factsheet(&self) -> &FactSheet {
+        &self.factsheet
+    }
+
     fn create_agents(&mut self) {
         //Adds Solutions Architect
-        self.add_agent(Box::new(AgentSolutionArchitect::new()));
+        if self.rerun_architect {
+            self.add_agent(Box::new(AgentSolutionArchitect::new()));
+        }
         self.add_agent(Box::new(AgentBackendDeveloper::new()));
     }
 
-    pub async fn execute_project(&mut self) {
-        self.create_agents();
+    // Runs the agents that were added, or the standard team if none were
+    pub async fn execute_project(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.agents.is_empty() {
+            self.create_agents();
+        }
+        publish(AgentEvent::FactSheetUpdated {
+            factsheet: Box::new(self.factsheet.clone()),
+        });
 
         for agent in &mut self.agents {
+            wait_while_paused().await;
+            let attributes: &BasicAgent = agent.get_attributes_from_agent();
+            let agent_position: String = attributes.position.clone();
+            publish(AgentEvent::AgentStarted {
+                agent: agent_position.clone(),
+                objective: attributes.objective.clone(),
+            });
+
+            // A panicking agent, i.e. one that found too many bugs, stops the project the same
+            // way an error does
             let agent_res: Result<(), Box<dyn std::error::Error>> =
-                agent.execute(&mut self.factsheet).await;
+                match CatchPanic::new(agent.execute(&mut self.factsheet)).await {
+                    Ok(agent_res) => agent_res,
+                    Err(panic_msg) => {
+                        Err(format!("{} panicked: {}", agent_position, panic_msg).into())
+                    }
+                };
+            publish(AgentEvent::FactSheetUpdated {
+                factsheet: Box::new(self.factsheet.clone()),
+            });
+            publish(AgentEvent::AgentFinished {
+                agent: agent_position,
+                error: agent_res.as_ref().err().map(|e| e.to_string()),
+            });
+
+            // Stop the project if an agent cannot complete its work, keeping partial results
+            if let Err(e) = agent_res {
+                let checkpoint_path: PathBuf = save_factsheet_checkpoint(&self.factsheet);
+                let stop_msg: String = format!(
+                    "Stopping project: {}. Partial results saved to {}",
+                    e,
+                    checkpoint_path.display()
+                );
+                PrintCommand::Issue
+                    .print_agent_message(self.attributes.position.as_str(), stop_msg.as_str());
+                return Err(e);
+            }
 
             // let agent_info: &BasicAgent = agent.get_attributes_from_agent();
             // dbg!(agent_info);
         }
+
+        // Kept for change requests, with the change applied
+        self.factsheet.change_request = None;
+        save_factsheet(&self.factsheet);
+        Ok(())
+    }
+}
+
+// Resolves to the panic message instead of unwinding when the wrapped future panics
+struct CatchPanic<F> {
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> CatchPanic<F> {
+    fn new(future: F) -> Self {
+        Self {
+            future: Box::pin(future),
+        }
     }
 }
 
+impl<F: Future> Future for CatchPanic<F> {
+    type Output = Result<F::Output, String>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future: Pin<&mut F> = self.future.as_mut();
+        match catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload
+                .downcast_ref::<&str>()
+                .map(|msg| msg.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string()))),
+        }
+    }
+}
+
+// Ask the LLM what is unclear, put the questions to the user and write up the requirements
+async fn gather_requirements(
+    usr_req: &str,
+    attributes: &mut BasicAgent,
+    clarifier: Arc<dyn Clarifier>,
+) -> Result<Requirements, AITaskError> {
+    let mut questions: Vec<String> = ai_task_request_decoded_with_memory::<Vec<String>>(
+        usr_req.to_string(),
+        attributes,
+        get_function_string!(print_clarifying_questions),
+        print_clarifying_questions,
+        &[],
+    )
+    .await?;
+    questions.truncate(MAX_CLARIFYING_QUESTIONS);
+
+    let mut clarifications: Vec<Clarification> = vec![];
+    if !questions.is_empty() {
+        publish(AgentEvent::ClarificationRequested {
+            agent: attributes.position.clone(),
+            questions: questions.clone(),
+        });
+        let answers: Vec<String> = clarifier.answer(&questions).await;
+        clarifications = questions
+            .into_iter()
+            .enumerate()
+            .map(|(i, question)| Clarification {
+                question,
+                answer: answers
+                    .get(i)
+                    .map(|answer| answer.trim())
+                    .filter(|answer| !answer.is_empty())
+                    .unwrap_or(NO_ANSWER)
+                    .to_string(),
+            })
+            .collect();
+    }
+
+    let msg_context: String = format!(
+        "USER_REQUEST: {} \n CLARIFICATIONS: {}",
+        usr_req,
+        serde_json::to_string(&clarifications).unwrap_or_default()
+    );
+    let mut requirements: Requirements = ai_task_request_decoded_with_memory::<Requirements>(
+        msg_context,
+        attributes,
+        get_function_string!(print_requirements),
+        print_requirements,
+        &[],
+    )
+    .await?;
+    requirements.clarifications = clarifications;
+    Ok(requirements)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::apis::provider::MockProvider;
+    use crate::apis::usage::Budget;
+    use crate::helpers::general::Workspace;
+    use crate::helpers::run_context::{new_usage_tracker, with_run_context, RunContext};
+    use crate::models::agent_basic::basic_traits::BasicTraits;
+    use async_trait::async_trait;
+    use std::env;
+
+    struct FixedAnswers;
+
+    #[async_trait]
+    impl Clarifier for FixedAnswers {
+        async fn answer(&self, _questions: &[String]) -> Vec<String> {
+            vec![" ".to_string()]
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_managing_agent_gathers_requirements() {
+        let context: RunContext = RunContext {
+            provider: Arc::new(MockProvider),
+            clarifier: Some(Arc::new(FixedAnswers)),
+            observers: vec![],
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+
+        let managing_agent: ManagingAgent =
+            with_run_context(context, ManagingAgent::new("todo app".to_string()))
+                .await
+                .expect("Error creating ManagingAgent");
+
+        let requirements: &Requirements = managing_agent
+            .factsheet
+            .requirements
+            .as_ref()
+            .expect("No requirements on factsheet");
+        assert_eq!(
+            requirements.clarifications,
+            vec![Clarification {
+                question: "Should each user only see their own tasks?".to_string(),
+                answer: NO_ANSWER.to_string(),
+            }]
+        );
+        assert!(!requirements.features.is_empty());
+        assert!(managing_agent
+            .factsheet
+            .with_requirements("PROJECT".to_string())
+            .contains("users only see their own tasks"));
+    }
+
+    #[derive(Debug)]
+    struct PanickingAgent {
+        attributes: BasicAgent,
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for PanickingAgent {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        async fn execute(
+            &mut self,
+            factsheet: &mut FactSheet,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            factsheet.backend_code = Some("partial".to_string());
+            panic!("Error: Too many bugs");
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_panicking_agent_saves_checkpoint() {
+        let project_dir: PathBuf =
+            env::temp_dir().join(format!("checkpoint_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(project_dir.join("schemas")).unwrap();
+        let workspace: Workspace = Workspace::for_project(&project_dir, None);
+        let context: RunContext = RunContext {
+            provider: Arc::new(MockProvider),
+            workspace: workspace.clone(),
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+
+        let project_res: Result<(), String> = with_run_context(context, async {
+            let mut managing_agent: ManagingAgent = ManagingAgent::new("todo app".to_string())
+                .await
+                .expect("Error creating ManagingAgent");
+            managing_agent.add_agent(Box::new(PanickingAgent {
+                attributes: BasicAgent::new("Panic".to_string(), "Panicker".to_string()),
+            }));
+            managing_agent
+                .execute_project()
+                .await
+                .map_err(|e| e.to_string())
+        })
+        .await;
+
+        assert_eq!(
+            project_res,
+            Err("Panicker panicked: Error: Too many bugs".to_string())
+        );
+        let checkpoint: String = fs::read_to_string(workspace.checkpoint_path()).unwrap();
+        assert!(checkpoint.contains("partial"));
+        let _ = fs::remove_dir_all(&project_dir);
+    }
+
+    #[tokio::test]
+    async fn tests_agent_over_budget_saves_checkpoint() {
+        let project_dir: PathBuf =
+            env::temp_dir().join(format!("over_budget_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(project_dir.join("schemas")).unwrap();
+        let workspace: Workspace = Workspace::for_project(&project_dir, None);
+        let context: RunContext = RunContext {
+            provider: Arc::new(MockProvider),
+            workspace: workspace.clone(),
+            usage: new_usage_tracker(Budget {
+                max_tokens: Some(1),
+                max_cost_usd: None,
+            }),
+            review_gates: vec![],
+            print_to_terminal: false,
+            ..RunContext::default()
+        };
+
+        // Turning the request into a goal spends the budget, the architect is stopped
+        let project_res: Result<(), String> = with_run_context(context, async {
+            let mut managing_agent: ManagingAgent = ManagingAgent::new("todo app".to_string())
+                .await
+                .expect("Error creating ManagingAgent");
+            managing_agent
+                .execute_project()
+                .await
+                .map_err(|e| e.to_string())
+        })
+        .await;
+
+        assert!(project_res
+            .unwrap_err()
+            .starts_with("LLM budget exceeded: spent"));
+        let checkpoint: FactSheet =
+            serde_json::from_str(&fs::read_to_string(workspace.checkpoint_path()).unwrap())
+                .unwrap();
+        assert_eq!(
+            checkpoint.project_description,
+            "build a website backend that stores tasks and lets users register and log in"
+        );
+        assert_eq!(checkpoint.project_scope, None);
+        let _ = fs::remove_dir_all(&project_dir);
+    }
 
     #[tokio::test]
     async fn tests_managing_agent() {
@@ -83,7 +459,10 @@ mod tests {
             .await
             .expect("Error creating ManagingAgent");
 
-        managing_agent.execute_project().await;
+        managing_agent
+            .execute_project()
+            .await
+            .expect("Error executing project");
 
         dbg!(managing_agent.factsheet);
     }
@@ -1 +1 @@
-pub mod managing_agent;
\ No newline at end of file
+pub mod managing_agent;
@@ -0,0 +1,350 @@
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use strum_macros::{Display, EnumString, IntoStaticStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum HttpMethod {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Head,
+    Options,
+}
+
+impl HttpMethod {
+    pub fn as_str(&self) -> &'static str {
+        self.into()
+    }
+}
+
+impl Serialize for HttpMethod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for HttpMethod {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let method: String = String::deserialize(deserializer)?;
+        HttpMethod::from_str(method.trim())
+            .map_err(|_| de::Error::custom(format!("unknown HTTP method '{}'", method)))
+    }
+}
+
+// Url path with named params, i.e. "/task/{id}"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+    raw: String,
+    params: Vec<String>,
+}
+
+impl PathTemplate {
+    pub fn parse(raw: &str) -> Self {
+        // Axum writes params as ":id" or "*rest", they are kept as "{id}" and "{rest}"
+        let raw: String = raw
+            .split('/')
+            .map(|segment| match segment.strip_prefix([':', '*']) {
+                Some(name) if !name.is_empty() => format!("{{{}}}", name),
+                _ => segment.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("/");
+
+        let mut params: Vec<String> = vec![];
+        let mut rest: &str = &raw;
+        while let Some(start) = rest.find('{') {
+            match rest[start..].find('}') {
+                Some(end) => {
+                    // Actix allows a regex after the name, i.e. "{id:\\d+}"
+                    let name: &str = rest[start + 1..start + end]
+                        .split(':')
+                        .next()
+                        .unwrap_or("")
+                        .trim();
+                    if !name.is_empty() {
+                        params.push(name.to_string());
+                    }
+                    rest = &rest[start + end + 1..];
+                }
+                None => break,
+            }
+        }
+
+        Self {
+            raw: raw.trim().to_string(),
+            params,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn params(&self) -> &Vec<String> {
+        &self.params
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        !self.params.is_empty()
+    }
+
+    // Exact placeholder text for a param, i.e. "{id}" or "{id:\\d+}"
+    pub fn placeholder(&self, param: &str) -> Option<&str> {
+        let plain: String = format!("{{{}}}", param);
+        let start: usize = match self.raw.find(&plain) {
+            Some(start) => start,
+            None => self.raw.find(&format!("{{{}:", param))?,
+        };
+        let end: usize = start + self.raw[start..].find('}')? + 1;
+        Some(&self.raw[start..end])
+    }
+}
+
+impl fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl Serialize for PathTemplate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for PathTemplate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw: String = String::deserialize(deserializer)?;
+        Ok(PathTemplate::parse(&raw))
+    }
+}
+
+// Description of a JSON request or response body
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    String,
+    Number,
+    Integer,
+    Bool,
+    Array(Box<Shape>),
+    Object(BTreeMap<String, Shape>),
+    Any,
+}
+
+impl Shape {
+    // Parse the LLM's loose format, where "None" and "not_provided" mean no body
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => None,
+            Value::String(type_name) => match type_name.trim().to_lowercase().as_str() {
+                "" | "none" | "null" | "not_provided" => None,
+                "string" | "str" | "text" => Some(Self::String),
+                "number" | "float" | "f32" | "f64" | "decimal" => Some(Self::Number),
+                "integer" | "int" | "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16"
+                | "i32" | "i64" | "isize" => Some(Self::Integer),
+                "bool" | "boolean" => Some(Self::Bool),
+                "array" | "list" => Some(Self::Array(Box::new(Self::Any))),
+                _ => Some(Self::Any),
+            },
+            Value::Bool(_) => Some(Self::Bool),
+            Value::Number(number) => match number.is_f64() {
+                true => Some(Self::Number),
+                false => Some(Self::Integer),
+            },
+            Value::Array(items) => {
+                let item_shape: Shape = items
+                    .first()
+                    .and_then(Self::from_value)
+                    .unwrap_or(Self::Any);
+                Some(Self::Array(Box::new(item_shape)))
+            }
+            Value::Object(fields) => {
+                let fields: BTreeMap<String, Shape> = fields
+                    .iter()
+                    .map(|(name, field)| {
+                        (name.clone(), Self::from_value(field).unwrap_or(Self::Any))
+                    })
+                    .collect();
+                Some(Self::Object(fields))
+            }
+        }
+    }
+
+    // Example body of this shape, strings carry the marker so the record can be found again
+    pub fn sample(&self, marker: &str, number: u32) -> Value {
+        match self {
+            Self::String | Self::Any => Value::from(marker),
+            // Whole numbers also fit integer fields the LLM labelled "number"
+            Self::Number | Self::Integer => Value::from(number),
+            Self::Bool => Value::from(true),
+            Self::Array(item) => Value::Array(vec![item.sample(marker, number)]),
+            Self::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(name, field)| (name.clone(), field.sample(marker, number)))
+                    .collect::<Map<String, Value>>(),
+            ),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            Self::String => Value::from("string"),
+            Self::Number => Value::from("number"),
+            Self::Integer => Value::from("integer"),
+            Self::Bool => Value::from("bool"),
+            Self::Any => Value::from("any"),
+            Self::Array(item) => Value::Array(vec![item.to_value()]),
+            Self::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(name, field)| (name.clone(), field.to_value()))
+                    .collect::<Map<String, Value>>(),
+            ),
+        }
+    }
+}
+
+impl Serialize for Shape {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Shape {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value: Value = Value::deserialize(deserializer)?;
+        Ok(Shape::from_value(&value).unwrap_or(Shape::Any))
+    }
+}
+
+// Accepts "None", "not_provided" and null as no shape
+pub fn deserialize_optional_shape<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Shape>, D::Error> {
+    let value: Value = Value::deserialize(deserializer)?;
+    Ok(Shape::from_value(&value))
+}
+
+// Accepts true, "true", "True", "yes" and "1" (and their opposites)
+pub fn deserialize_lenient_bool<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<bool, D::Error> {
+    let value: Value = Value::deserialize(deserializer)?;
+    match &value {
+        Value::Bool(flag) => Ok(*flag),
+        Value::Number(number) => Ok(number.as_i64() == Some(1)),
+        Value::String(flag) => match flag.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" => Ok(true),
+            "false" | "no" | "0" | "" => Ok(false),
+            other => Err(de::Error::custom(format!(
+                "expected a bool, found '{}'",
+                other
+            ))),
+        },
+        Value::Null => Ok(false),
+        _ => Err(de::Error::custom("expected a bool")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::agents::agent_traits::RouteObject;
+
+    #[test]
+    fn tests_lenient_route_object_decoding() {
+        let routes_str: &str = r#"
+      [
+        {
+          "route": "/weather/{date}",
+          "is_route_dynamic": "true",
+          "method": "GET",
+          "request_body": "None",
+          "response": { "date": "string", "temperature": "number", "readings": [ { "hour": "u64" } ] }
+        },
+        {
+          "route": "/crypto",
+          "is_route_dynamic": false,
+          "method": "post",
+          "request_body": { "symbol": "string", "watch": "bool" },
+          "response": "not_provided"
+        }
+      ]"#;
+        let routes: Vec<RouteObject> = serde_json::from_str(routes_str).unwrap();
+
+        assert_eq!(routes[0].method, HttpMethod::Get);
+        assert!(routes[0].route.is_dynamic());
+        assert_eq!(routes[0].route.params(), &vec!["date".to_string()]);
+        assert_eq!(routes[0].request_body, None);
+        match &routes[0].response {
+            Some(Shape::Object(fields)) => {
+                assert_eq!(fields["temperature"], Shape::Number);
+                assert_eq!(
+                    fields["readings"],
+                    Shape::Array(Box::new(Shape::Object(BTreeMap::from([(
+                        "hour".to_string(),
+                        Shape::Integer
+                    )]))))
+                );
+            }
+            other => panic!("Unexpected response shape {:?}", other),
+        }
+
+        assert_eq!(routes[1].method, HttpMethod::Post);
+        assert!(!routes[1].route.is_dynamic());
+        assert!(routes[1].request_body.is_some());
+        assert_eq!(routes[1].response, None);
+    }
+
+    #[test]
+    fn tests_path_template_placeholders() {
+        let path: PathTemplate = PathTemplate::parse("/user/{user_id}/task/{id:\\d+}");
+        assert_eq!(
+            path.params(),
+            &vec!["user_id".to_string(), "id".to_string()]
+        );
+        assert_eq!(path.placeholder("id"), Some("{id:\\d+}"));
+        assert_eq!(path.placeholder("user_id"), Some("{user_id}"));
+        assert!(!PathTemplate::parse("/task").is_dynamic());
+
+        let axum_path: PathTemplate = PathTemplate::parse("/user/:user_id/files/*path");
+        assert_eq!(axum_path.as_str(), "/user/{user_id}/files/{path}");
+        assert_eq!(
+            axum_path.params(),
+            &vec!["user_id".to_string(), "path".to_string()]
+        );
+    }
+
+    #[test]
+    fn tests_unknown_http_method_rejected() {
+        let res: Result<HttpMethod, serde_json::Error> = serde_json::from_str("\"fetch\"");
+        assert!(res.is_err());
+        assert_eq!(
+            serde_json::to_string(&HttpMethod::Delete).unwrap(),
+            "\"delete\""
+        );
+        assert_eq!(
+            HttpMethod::Options.to_string(),
+            HttpMethod::Options.as_str()
+        );
+    }
+
+    #[test]
+    fn tests_route_dynamic_follows_params() {
+        let route_object: RouteObject = serde_json::from_str(
+            r#"{"route": "/task/{id}", "is_route_dynamic": "false", "method": "get"}"#,
+        )
+        .unwrap();
+        assert!(route_object.route.is_dynamic());
+        let saved: String = serde_json::to_string(&route_object).unwrap();
+        assert!(!saved.contains("is_route_dynamic"));
+    }
+}
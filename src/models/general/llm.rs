@@ -11,6 +11,63 @@ pub struct ChatCompletion {
     pub model: String,
     pub messages: Vec<Message>,
     pub temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct StreamOptions {
+    pub include_usage: bool,
+}
+
+// Sampling parameters for one request, resolved from the LLM config
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CompletionParams {
+    pub model: String,
+    pub temperature: f32,
+    pub max_tokens: Option<u32>,
+    pub top_p: Option<f32>,
+    pub stop: Option<Vec<String>>,
+    pub seed: Option<u64>,
+}
+
+impl Default for CompletionParams {
+    fn default() -> Self {
+        Self {
+            model: "gpt-4".to_string(),
+            temperature: 0.1,
+            max_tokens: None,
+            top_p: None,
+            stop: None,
+            seed: None,
+        }
+    }
+}
+
+impl ChatCompletion {
+    pub fn new(params: &CompletionParams, messages: Vec<Message>) -> Self {
+        Self {
+            model: params.model.clone(),
+            messages,
+            temperature: params.temperature,
+            max_tokens: params.max_tokens,
+            top_p: params.top_p,
+            stop: params.stop.clone(),
+            seed: params.seed,
+            stream: None,
+            stream_options: None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -23,7 +80,49 @@ pub struct APIChoice {
     pub message: APIMessage,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct APIUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct APIResponse {
     pub choices: Vec<APIChoice>,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub usage: Option<APIUsage>,
+}
+
+// Completion text along with what it cost
+#[derive(Debug, Clone)]
+pub struct LLMResponse {
+    pub content: String,
+    pub model: String,
+    pub usage: Option<APIUsage>,
+}
+
+// Streamed completions arrive as a series of chunks carrying content deltas
+#[derive(Debug, Deserialize)]
+pub struct APIStreamDelta {
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct APIStreamChoice {
+    pub delta: APIStreamDelta,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct APIStreamChunk {
+    #[serde(default)]
+    pub choices: Vec<APIStreamChoice>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub usage: Option<APIUsage>,
+}
@@ -0,0 +1,280 @@
+use crate::models::general::llm::Message;
+
+const OPERATION_PREFIX: &str = "OPERATION: ";
+const SUMMARY_PREFIX: &str = "SUMMARY OF EARLIER EXCHANGES:";
+const TRUNCATED_MARKER: &str = "\n...[truncated]";
+const RECALL_FOLLOW_UP: &str = "The messages above are earlier calls. Now print what the FUNCTION in the first message returns for its INPUT.";
+
+// Rough token estimate, OpenAI models average about four characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+pub fn estimate_message_tokens(messages: &[Message]) -> usize {
+    // Each message carries a few tokens of role and formatting overhead
+    messages
+        .iter()
+        .map(|message| estimate_tokens(&message.content) + 4)
+        .sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryPolicy {
+    pub context_window_tokens: usize,
+    pub reserved_response_tokens: usize,
+    pub max_turn_tokens: usize,
+    pub max_stored_messages: usize,
+    pub summary_line_chars: usize,
+}
+
+impl Default for MemoryPolicy {
+    // Sized for gpt-4's 8k context window
+    fn default() -> Self {
+        Self {
+            context_window_tokens: 8192,
+            reserved_response_tokens: 2048,
+            max_turn_tokens: 1500,
+            max_stored_messages: 40,
+            summary_line_chars: 160,
+        }
+    }
+}
+
+impl MemoryPolicy {
+    // Tokens left for history once the new prompt and the response are accounted for
+    pub fn history_budget(&self, prompt: &Message) -> usize {
+        self.context_window_tokens
+            .saturating_sub(self.reserved_response_tokens)
+            .saturating_sub(estimate_tokens(&prompt.content) + 4)
+    }
+}
+
+// The user turn stored in memory for a single ai_function call
+pub fn memory_prompt(agent_operation: &str, msg_context: &str) -> Message {
+    Message {
+        role: "user".to_string(),
+        content: format!(
+            "{}{}\nINPUT: {}",
+            OPERATION_PREFIX, agent_operation, msg_context
+        ),
+    }
+}
+
+// Ends a conversation with recalled history on the current call again
+pub fn recall_follow_up() -> Message {
+    Message {
+        role: "user".to_string(),
+        content: RECALL_FOLLOW_UP.to_string(),
+    }
+}
+
+pub fn memory_response(llm_response: &str) -> Message {
+    Message {
+        role: "assistant".to_string(),
+        content: llm_response.to_string(),
+    }
+}
+
+// Operation a stored user turn was recorded for
+fn memory_operation(message: &Message) -> Option<&str> {
+    message
+        .content
+        .strip_prefix(OPERATION_PREFIX)
+        .and_then(|rest| rest.lines().next())
+}
+
+// Pick earlier turns for the given operations that fit within the token budget.
+// Newest exchanges are kept verbatim, older ones are condensed into one summary.
+pub fn recall_memory(
+    memory: &[Message],
+    operations: &[&str],
+    budget_tokens: usize,
+    policy: &MemoryPolicy,
+) -> Vec<Message> {
+    if operations.is_empty() || budget_tokens == 0 {
+        return vec![];
+    }
+
+    // Relevant exchanges, oldest first
+    let exchanges: Vec<(&Message, &Message)> = memory
+        .windows(2)
+        .map(|pair| (&pair[0], &pair[1]))
+        .filter(|(prompt, response)| {
+            response.role == "assistant"
+                && memory_operation(prompt).is_some_and(|op| operations.contains(&op))
+        })
+        .collect();
+
+    // Keep newest exchanges while they fit, leaving room for a summary
+    let summary_reserve: usize = budget_tokens / 5;
+    let mut used_tokens: usize = 0;
+    let mut kept: Vec<Message> = vec![];
+    let mut dropped: Vec<(&Message, &Message)> = vec![];
+    for (prompt, response) in exchanges.iter().rev() {
+        let pair: Vec<Message> = vec![
+            truncate_message(prompt, policy.max_turn_tokens),
+            truncate_message(response, policy.max_turn_tokens),
+        ];
+        let pair_tokens: usize = estimate_message_tokens(&pair);
+        if dropped.is_empty() && used_tokens + pair_tokens + summary_reserve <= budget_tokens {
+            used_tokens += pair_tokens;
+            kept.extend(pair.into_iter().rev());
+        } else {
+            dropped.insert(0, (*prompt, *response));
+        }
+    }
+
+    if !dropped.is_empty() {
+        let summary: Message = summarize_exchanges(&dropped, policy.summary_line_chars);
+        let summary_tokens: usize = budget_tokens.saturating_sub(used_tokens + 4);
+        kept.push(truncate_message(&summary, summary_tokens));
+    }
+
+    // Collected newest first
+    kept.reverse();
+    kept
+}
+
+// Fold the oldest stored turns into a summary once memory grows past the policy limit
+pub fn compact_memory(memory: &mut Vec<Message>, policy: &MemoryPolicy) {
+    if memory.len() <= policy.max_stored_messages {
+        return;
+    }
+
+    // Keep an even number of recent messages so prompt/response pairs stay aligned
+    let keep: usize = (policy.max_stored_messages.saturating_sub(1)) / 2 * 2;
+    let split_at: usize = memory.len() - keep;
+    let older: Vec<Message> = memory.drain(..split_at).collect();
+
+    let mut pairs: Vec<(&Message, &Message)> = vec![];
+    let mut previous_summary: Option<&Message> = None;
+    let mut iter = older.iter().peekable();
+    while let Some(message) = iter.next() {
+        if message.content.starts_with(SUMMARY_PREFIX) {
+            previous_summary = Some(message);
+        } else if message.role == "user" {
+            if let Some(response) = iter.next_if(|next| next.role == "assistant") {
+                pairs.push((message, response));
+            }
+        }
+    }
+
+    let mut summary: Message = summarize_exchanges(&pairs, policy.summary_line_chars);
+    if let Some(previous) = previous_summary {
+        let earlier: &str = previous.content.trim_start_matches(SUMMARY_PREFIX);
+        summary.content = format!(
+            "{}{}{}",
+            SUMMARY_PREFIX,
+            earlier,
+            summary.content.trim_start_matches(SUMMARY_PREFIX)
+        );
+    }
+    let summary: Message = truncate_message(&summary, policy.max_turn_tokens);
+    memory.insert(0, summary);
+}
+
+// One line per exchange: the operation and the start of its response
+fn summarize_exchanges(exchanges: &[(&Message, &Message)], line_chars: usize) -> Message {
+    let lines: Vec<String> = exchanges
+        .iter()
+        .map(|(prompt, response)| {
+            let operation: &str = memory_operation(prompt).unwrap_or("unknown");
+            let gist: String = response
+                .content
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+                .chars()
+                .take(line_chars)
+                .collect();
+            format!("\n- {} -> {}", operation, gist)
+        })
+        .collect();
+
+    Message {
+        role: "system".to_string(),
+        content: format!("{}{}", SUMMARY_PREFIX, lines.join("")),
+    }
+}
+
+fn truncate_message(message: &Message, max_tokens: usize) -> Message {
+    if estimate_tokens(&message.content) <= max_tokens {
+        return message.clone();
+    }
+    let max_chars: usize = (max_tokens * 4).saturating_sub(TRUNCATED_MARKER.len());
+    let mut content: String = message.content.chars().take(max_chars).collect();
+    content.push_str(TRUNCATED_MARKER);
+    Message {
+        role: message.role.clone(),
+        content,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(operation: &str, input: &str, output: &str) -> Vec<Message> {
+        vec![memory_prompt(operation, input), memory_response(output)]
+    }
+
+    #[test]
+    fn tests_recall_memory_filters_operations() {
+        let mut memory: Vec<Message> = vec![];
+        memory.extend(exchange("print_project_scope", "fitness app", "{}"));
+        memory.extend(exchange(
+            "print_fixed_code",
+            "broken code 1",
+            "fixed code 1",
+        ));
+        memory.extend(exchange(
+            "print_fixed_code",
+            "broken code 2",
+            "fixed code 2",
+        ));
+
+        let policy: MemoryPolicy = MemoryPolicy::default();
+        let recalled: Vec<Message> = recall_memory(&memory, &["print_fixed_code"], 4000, &policy);
+
+        assert_eq!(recalled.len(), 4);
+        assert!(recalled[0].content.contains("broken code 1"));
+        assert_eq!(recalled[3].content, "fixed code 2");
+    }
+
+    #[test]
+    fn tests_recall_memory_summarizes_over_budget() {
+        let mut memory: Vec<Message> = vec![];
+        for i in 0..10 {
+            let big_output: String = format!("fixed code {} {}", i, "x".repeat(2000));
+            memory.extend(exchange("print_fixed_code", "broken", &big_output));
+        }
+
+        let policy: MemoryPolicy = MemoryPolicy::default();
+        let budget: usize = 2000;
+        let recalled: Vec<Message> = recall_memory(&memory, &["print_fixed_code"], budget, &policy);
+
+        assert!(recalled[0].content.starts_with(SUMMARY_PREFIX));
+        assert!(estimate_message_tokens(&recalled) <= budget);
+        assert!(recalled.last().unwrap().content.starts_with("fixed code 9"));
+    }
+
+    #[test]
+    fn tests_compact_memory() {
+        let mut memory: Vec<Message> = vec![];
+        for i in 0..30 {
+            memory.extend(exchange(
+                "print_site_urls",
+                "project",
+                &format!("[\"url{}\"]", i),
+            ));
+        }
+
+        let policy: MemoryPolicy = MemoryPolicy::default();
+        compact_memory(&mut memory, &policy);
+
+        assert!(memory.len() <= policy.max_stored_messages);
+        assert!(memory[0].content.starts_with(SUMMARY_PREFIX));
+        assert_eq!(memory[1].role, "user");
+        assert_eq!(memory.last().unwrap().content, "[\"url29\"]");
+    }
+}
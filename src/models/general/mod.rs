@@ -1 +1,3 @@
-pub mod llm;
\ No newline at end of file
+pub mod api_schema;
+pub mod llm;
+pub mod memory;
@@ -1,4 +1,4 @@
 pub mod agent_basic;
 pub mod agents;
 pub mod agents_manager;
-pub mod general;
\ No newline at end of file
+pub mod general;
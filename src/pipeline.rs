@@ -0,0 +1,543 @@
+use crate::apis::cache::ResponseCache;
+use crate::apis::llm_config::LLMConfig;
+use crate::apis::provider::{LLMProvider, OpenAIProvider};
+use crate::apis::usage::{Budget, UsageTracker};
+use crate::helpers::backend_templates::{BackendTemplate, Storage};
+use crate::helpers::clarify::Clarifier;
+use crate::helpers::events::{AgentEvent, AgentObserver, ChannelObserver, TerminalObserver};
+use crate::helpers::general::{load_factsheet, Workspace};
+use crate::helpers::prompt_library::PromptLibrary;
+use crate::helpers::review::{default_review_gates, ReviewGate, Reviewer, TerminalReviewer};
+use crate::helpers::run_context::{new_usage_tracker, with_run_context, RunContext, RunControl};
+use crate::models::agents::agent_architect::ArchitectConfig;
+use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+use crate::models::agents_manager::managing_agent::ManagingAgent;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+// What a finished run leaves behind
+#[derive(Debug, Clone)]
+pub struct PipelineOutput {
+    pub factsheet: FactSheet,
+    pub artifacts: Vec<PathBuf>,
+}
+
+// A stopped run, with the factsheet as far as the agents got
+#[derive(Debug, Clone)]
+pub struct PipelineError {
+    pub message: String,
+    pub factsheet: Option<Box<FactSheet>>,
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+// Configures a project run, e.g.
+// Pipeline::builder()
+//     .provider(CassetteProvider::replay(dir))
+//     .workspace(Workspace::for_project(project_dir, template_path))
+//     .observer(|event: &AgentEvent| println!("{:?}", event))
+//     .build()
+//     .run("Build me a todo app")
+pub struct PipelineBuilder {
+    provider: Arc<dyn LLMProvider>,
+    workspace: Workspace,
+    agents: Vec<Box<dyn SpecialFunctions>>,
+    observers: Vec<Arc<dyn AgentObserver>>,
+    reviewer: Arc<dyn Reviewer>,
+    review_gates: Vec<ReviewGate>,
+    clarifier: Option<Arc<dyn Clarifier>>,
+    backend_template: Option<BackendTemplate>,
+    backend_storage: Option<Storage>,
+    architect: ArchitectConfig,
+    llm_config: LLMConfig,
+    prompts: PromptLibrary,
+    response_cache: Option<ResponseCache>,
+    usage: Arc<Mutex<UsageTracker>>,
+    control: RunControl,
+    print_to_terminal: bool,
+}
+
+impl Default for PipelineBuilder {
+    fn default() -> Self {
+        Self {
+            provider: Arc::new(OpenAIProvider),
+            workspace: Workspace::default(),
+            agents: vec![],
+            observers: vec![],
+            reviewer: Arc::new(TerminalReviewer),
+            review_gates: default_review_gates(),
+            clarifier: None,
+            backend_template: None,
+            backend_storage: None,
+            architect: ArchitectConfig::default(),
+            llm_config: LLMConfig::default(),
+            prompts: PromptLibrary::builtin(),
+            response_cache: None,
+            usage: new_usage_tracker(Budget::default()),
+            control: RunControl::default(),
+            print_to_terminal: true,
+        }
+    }
+}
+
+impl PipelineBuilder {
+    pub fn provider(mut self, provider: impl LLMProvider + 'static) -> Self {
+        self.provider = Arc::new(provider);
+        self
+    }
+
+    pub fn workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = workspace;
+        self
+    }
+
+    // Agents run in the order added. Without any, the architect and backend developer run.
+    pub fn agent(mut self, agent: Box<dyn SpecialFunctions>) -> Self {
+        self.agents.push(agent);
+        self
+    }
+
+    // Any AgentObserver, or a closure taking &AgentEvent
+    pub fn observer(mut self, observer: impl AgentObserver + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    // Events as a stream instead of a callback
+    pub fn event_channel(self) -> (Self, UnboundedReceiver<AgentEvent>) {
+        let (sender, receiver) = unbounded_channel::<AgentEvent>();
+        (self.observer(ChannelObserver::new(sender)), receiver)
+    }
+
+    // Approves or rejects every review gate without asking
+    pub fn approve_code(self, approve: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.reviewer(approve)
+    }
+
+    // Decides at the review gates, asked on the terminal by default
+    pub fn reviewer(mut self, reviewer: impl Reviewer + 'static) -> Self {
+        self.reviewer = Arc::new(reviewer);
+        self
+    }
+
+    // Where the run stops for review, only before running the server by default
+    pub fn review_gates(mut self, review_gates: Vec<ReviewGate>) -> Self {
+        self.review_gates = review_gates;
+        self
+    }
+
+    // Ask clarifying questions and gather requirements before the goal is set
+    pub fn clarifier(mut self, clarifier: impl Clarifier + 'static) -> Self {
+        self.clarifier = Some(Arc::new(clarifier));
+        self
+    }
+
+    // Write the backend from this template instead of picking one from the project scope
+    pub fn backend_template(mut self, backend_template: BackendTemplate) -> Self {
+        self.backend_template = Some(backend_template);
+        self
+    }
+
+    // Store the generated backend's data this way, e.g. Storage::Sqlite with migrations
+    pub fn backend_storage(mut self, backend_storage: Storage) -> Self {
+        self.backend_storage = Some(backend_storage);
+        self
+    }
+
+    // Replacement url rounds and the working urls the architect needs
+    pub fn architect_config(mut self, architect: ArchitectConfig) -> Self {
+        self.architect = architect;
+        self
+    }
+
+    // Model parameters per agent and ai_function, built-in defaults otherwise
+    pub fn llm_config(mut self, llm_config: LLMConfig) -> Self {
+        self.llm_config = llm_config;
+        self
+    }
+
+    // Prompt templates, the built-in ones otherwise
+    pub fn prompts(mut self, prompts: PromptLibrary) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    // Serve repeated prompts from this cache, every call goes to the provider otherwise
+    pub fn response_cache(mut self, response_cache: ResponseCache) -> Self {
+        self.response_cache = Some(response_cache);
+        self
+    }
+
+    // Stop the run once it has spent this much, unlimited otherwise
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.usage = new_usage_tracker(budget);
+        self
+    }
+
+    // Keep a clone of the tracker to read the run's usage from outside
+    pub fn usage_tracker(&self) -> Arc<Mutex<UsageTracker>> {
+        self.usage.clone()
+    }
+
+    // Keep a clone of the control to pause and resume the run from outside
+    pub fn control(mut self, control: RunControl) -> Self {
+        self.control = control;
+        self
+    }
+
+    // The terminal is an observer of every run unless turned off
+    pub fn print_to_terminal(mut self, print_to_terminal: bool) -> Self {
+        self.print_to_terminal = print_to_terminal;
+        self
+    }
+
+    pub fn build(self) -> Pipeline {
+        let mut observers: Vec<Arc<dyn AgentObserver>> = vec![];
+        if self.print_to_terminal {
+            observers.push(Arc::new(TerminalObserver));
+        }
+        observers.extend(self.observers);
+
+        Pipeline {
+            context: RunContext {
+                provider: self.provider,
+                workspace: self.workspace,
+                observers,
+                reviewer: self.reviewer,
+                review_gates: self.review_gates,
+                clarifier: self.clarifier,
+                backend_template: self.backend_template,
+                backend_storage: self.backend_storage,
+                architect: self.architect,
+                llm_config: Arc::new(self.llm_config),
+                prompts: Arc::new(self.prompts),
+                response_cache: self.response_cache.map(Arc::new),
+                usage: self.usage,
+                control: self.control,
+                print_to_terminal: self.print_to_terminal,
+            },
+            agents: self.agents,
+        }
+    }
+}
+
+pub struct Pipeline {
+    context: RunContext,
+    agents: Vec<Box<dyn SpecialFunctions>>,
+}
+
+// A new project from a request, or a change to the last finished one
+enum ProjectStart {
+    Request(String),
+    Amend(Box<FactSheet>, String),
+}
+
+impl Pipeline {
+    pub fn builder() -> PipelineBuilder {
+        PipelineBuilder::default()
+    }
+
+    // Turn the user request into a goal, then let every agent work on the factsheet
+    pub async fn run(self, user_request: &str) -> Result<PipelineOutput, PipelineError> {
+        self.start(ProjectStart::Request(user_request.to_string()))
+            .await
+    }
+
+    // Apply a change request to the project the last finished run left in the workspace
+    pub async fn amend(self, change_request: &str) -> Result<PipelineOutput, PipelineError> {
+        let factsheet_path: PathBuf = self.context.workspace.factsheet_path();
+        let factsheet: FactSheet =
+            load_factsheet(&factsheet_path).map_err(|message| PipelineError {
+                message,
+                factsheet: None,
+            })?;
+        self.start(ProjectStart::Amend(
+            Box::new(factsheet),
+            change_request.to_string(),
+        ))
+        .await
+    }
+
+    async fn start(self, project_start: ProjectStart) -> Result<PipelineOutput, PipelineError> {
+        let workspace: Workspace = self.context.workspace.clone();
+        if let Some(schema_dir) = workspace.api_schema_path.parent() {
+            if schema_dir != Path::new("") {
+                fs::create_dir_all(schema_dir).map_err(|e| PipelineError {
+                    message: format!("Could not create {}: {}", schema_dir.display(), e),
+                    factsheet: None,
+                })?;
+            }
+        }
+
+        let agents: Vec<Box<dyn SpecialFunctions>> = self.agents;
+        with_run_context(self.context, async move {
+            let manager_res: Result<ManagingAgent, String> = match project_start {
+                ProjectStart::Request(user_request) => ManagingAgent::new(user_request).await,
+                ProjectStart::Amend(factsheet, change_request) => {
+                    ManagingAgent::amend(*factsheet, change_request).await
+                }
+            }
+            .map_err(|e| e.to_string());
+            let mut manager: ManagingAgent = manager_res.map_err(|message| PipelineError {
+                message,
+                factsheet: None,
+            })?;
+            for agent in agents {
+                manager.add_agent(agent);
+            }
+
+            let project_res: Result<(), String> =
+                manager.execute_project().await.map_err(|e| e.to_string());
+            let factsheet: FactSheet = manager.factsheet().clone();
+            match project_res {
+                Ok(()) => Ok(PipelineOutput {
+                    factsheet,
+                    artifacts: workspace.artifacts(),
+                }),
+                Err(message) => Err(PipelineError {
+                    message,
+                    factsheet: Some(Box::new(factsheet)),
+                }),
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::provider::MockProvider;
+    use crate::apis::retry::LLMError;
+    use crate::helpers::command_line::PrintCommand;
+    use crate::helpers::general::save_backend_code;
+    use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+    use crate::models::agent_basic::basic_traits::BasicTraits;
+    use crate::models::general::llm::{CompletionParams, LLMResponse, Message};
+    use async_trait::async_trait;
+    use std::env;
+
+    // Answers every ai_function with a fixed goal
+    struct FixedProvider;
+
+    #[async_trait]
+    impl LLMProvider for FixedProvider {
+        fn name(&self) -> String {
+            "fixed".to_string()
+        }
+
+        async fn complete(
+            &self,
+            ai_function: &str,
+            _messages: Vec<Message>,
+            params: &CompletionParams,
+        ) -> Result<LLMResponse, LLMError> {
+            Ok(LLMResponse {
+                content: format!("goal from {}", ai_function),
+                model: params.model.clone(),
+                usage: None,
+            })
+        }
+    }
+
+    // Writes the goal into the workspace as its "backend code"
+    #[derive(Debug)]
+    struct NoteTaker {
+        attributes: BasicAgent,
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for NoteTaker {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        async fn execute(
+            &mut self,
+            factsheet: &mut FactSheet,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            PrintCommand::UnitTest.print_agent_message(&self.attributes.position, "Taking notes");
+            factsheet.backend_code = Some(factsheet.project_description.clone());
+            save_backend_code(&factsheet.project_description);
+            self.attributes.update_state(AgentState::Finished);
+            Ok(())
+        }
+    }
+
+    // Writes the change it was asked to make as its "backend code"
+    #[derive(Debug)]
+    struct Amender {
+        attributes: BasicAgent,
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for Amender {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        async fn execute(
+            &mut self,
+            factsheet: &mut FactSheet,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            assert_eq!(
+                factsheet.backend_code.as_deref(),
+                Some("fn main() { edited() }")
+            );
+            factsheet.backend_code = factsheet.change_request.clone();
+            self.attributes.update_state(AgentState::Finished);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_pipeline_amends_finished_project() {
+        let project_dir: PathBuf = env::temp_dir().join(format!("amend_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::create_dir_all(project_dir.join("schemas")).unwrap();
+        let workspace: Workspace = Workspace::for_project(&project_dir, None);
+        let amend_pipeline = || {
+            Pipeline::builder()
+                .provider(MockProvider)
+                .workspace(workspace.clone())
+                .agent(Box::new(Amender {
+                    attributes: BasicAgent::new("Amend".to_string(), "Amender".to_string()),
+                }))
+                .print_to_terminal(false)
+                .build()
+        };
+
+        let no_project: PipelineError = amend_pipeline().amend("add due dates").await.unwrap_err();
+        assert!(no_project
+            .message
+            .starts_with("No finished project to amend"));
+
+        let finished: FactSheet = FactSheet {
+            project_description: "build a website backend that stores tasks".to_string(),
+            requirements: None,
+            project_scope: None,
+            external_urls: None,
+            external_url_probes: None,
+            backend_template: Some("actix_json".to_string()),
+            database_migration: None,
+            change_request: None,
+            backend_code: Some("fn main() {}".to_string()),
+            api_endpoint_schema: None,
+        };
+        fs::write(
+            workspace.factsheet_path(),
+            serde_json::to_string(&finished).unwrap(),
+        )
+        .unwrap();
+        fs::write(&workspace.exec_main_path, "fn main() { edited() }").unwrap();
+
+        let output: PipelineOutput = amend_pipeline().amend("add due dates").await.unwrap();
+        assert!(output.factsheet.project_description.contains("due date"));
+        assert_eq!(
+            output.factsheet.backend_code.as_deref(),
+            Some("add an optional due_date field to tasks")
+        );
+        assert_eq!(
+            output.factsheet.backend_template.as_deref(),
+            Some("actix_json")
+        );
+
+        // The next change request starts from this run
+        let saved: FactSheet = load_factsheet(&workspace.factsheet_path()).unwrap();
+        assert_eq!(saved.change_request, None);
+        assert_eq!(saved.backend_code, output.factsheet.backend_code);
+        let _ = fs::remove_dir_all(&project_dir);
+    }
+
+    #[tokio::test]
+    async fn tests_pipeline_with_custom_provider_and_agent() {
+        let project_dir: PathBuf = env::temp_dir().join(format!("pipeline_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+
+        let (builder, mut events) = Pipeline::builder()
+            .provider(FixedProvider)
+            .workspace(Workspace::for_project(&project_dir, None))
+            .agent(Box::new(NoteTaker {
+                attributes: BasicAgent::new("Take notes".to_string(), "Note Taker".to_string()),
+            }))
+            .print_to_terminal(false)
+            .event_channel();
+
+        let output: PipelineOutput = builder.build().run("Build me a todo app").await.unwrap();
+
+        assert_eq!(
+            output.factsheet.project_description,
+            "goal from convert_user_input_to_goal"
+        );
+        assert_eq!(
+            output.factsheet.backend_code.as_deref(),
+            Some("goal from convert_user_input_to_goal")
+        );
+        assert_eq!(
+            output.artifacts,
+            vec![project_dir.join("src").join("main.rs")]
+        );
+
+        let mut received: Vec<AgentEvent> = vec![];
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        assert!(matches!(
+            &received[0],
+            AgentEvent::LLMCallStarted { agent, .. } if agent == "Project Manager"
+        ));
+        assert!(matches!(
+            &received[1],
+            AgentEvent::LLMCallFinished {
+                error: None,
+                cached: false,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &received[2],
+            AgentEvent::FactSheetUpdated { factsheet } if factsheet.backend_code.is_none()
+        ));
+        assert!(matches!(
+            &received[3],
+            AgentEvent::AgentStarted { agent, .. } if agent == "Note Taker"
+        ));
+        assert!(matches!(
+            &received[4],
+            AgentEvent::Message { kind: PrintCommand::UnitTest, message, .. } if message == "Taking notes"
+        ));
+        assert!(matches!(
+            &received[5],
+            AgentEvent::CodeWritten { previous: None, contents, .. }
+                if contents == "goal from convert_user_input_to_goal"
+        ));
+        assert!(matches!(
+            &received[6],
+            AgentEvent::StateChanged {
+                to: AgentState::Finished,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &received[7],
+            AgentEvent::FactSheetUpdated { factsheet } if factsheet.backend_code.is_some()
+        ));
+        assert!(matches!(
+            &received[8],
+            AgentEvent::AgentFinished { error: None, .. }
+        ));
+        assert_eq!(received.len(), 9);
+        let _ = fs::remove_dir_all(&project_dir);
+    }
+}
@@ -0,0 +1,803 @@
+use crate::apis::cache::{response_cache_from_env, ResponseCache};
+use crate::apis::llm_config::LLMConfig;
+use crate::apis::provider::{provider_from_env, LLMProvider};
+use crate::apis::usage::Budget;
+use crate::helpers::backend_templates::{
+    parse_backend_storage, parse_backend_template, BackendTemplate, Storage,
+};
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::events::{AgentEvent, JsonLinesObserver};
+use crate::helpers::general::{copy_project, Workspace};
+use crate::helpers::git_history::GitHistory;
+use crate::helpers::prompt_library::PromptLibrary;
+use crate::models::agents::agent_architect::{architect_config_from_env, ArchitectConfig};
+use crate::models::agents::agent_traits::FactSheet;
+use crate::pipeline::{Pipeline, PipelineBuilder, PipelineError, PipelineOutput};
+use axum::extract::{Path as UrlPath, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, Semaphore};
+use tokio::task::{AbortHandle, JoinHandle};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8000";
+const DEFAULT_WORK_DIR: &str = "jobs";
+const DEFAULT_MAX_CONCURRENT: usize = 2;
+const DEFAULT_MAX_FINISHED_JOBS: usize = 100;
+const UPDATE_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed | Self::Cancelled)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct JobSummary {
+    pub id: u64,
+    pub request: String,
+    pub status: JobStatus,
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub error: Option<String>,
+}
+
+// Generated file of a job, relative to its project directory
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Artifact {
+    pub path: String,
+    pub contents: String,
+}
+
+// What subscribers of a job receive: agent events, then status changes
+#[derive(Debug, Clone)]
+pub enum JobUpdate {
+    Event(AgentEvent),
+    Status(JobSummary),
+}
+
+impl JobUpdate {
+    fn to_sse(&self) -> Event {
+        let event: Result<Event, serde_json::Error> = match self {
+            Self::Event(agent_event) => Event::default().event("agent").json_data(agent_event),
+            Self::Status(summary) => Event::default().event("status").json_data(summary),
+        };
+        event.expect("Failed to serialize job update")
+    }
+}
+
+struct Job {
+    summary: JobSummary,
+    workspace: Workspace,
+    factsheet: Option<FactSheet>,
+    events: Vec<AgentEvent>,
+    updates: Option<broadcast::Sender<JobUpdate>>,
+    abort: Option<AbortHandle>,
+}
+
+impl Job {
+    fn publish(&self, update: JobUpdate) {
+        if let Some(updates) = &self.updates {
+            let _ = updates.send(update);
+        }
+    }
+
+    fn finish(&mut self, status: JobStatus, error: Option<String>) {
+        self.summary.status = status;
+        self.summary.error = error;
+        self.summary.finished_at = Some(now_secs());
+        self.publish(JobUpdate::Status(self.summary.clone()));
+
+        // Closing the channel ends every event stream of the job
+        self.updates = None;
+        self.abort = None;
+    }
+}
+
+// Pipeline for each job, the queue sets its workspace and observers
+pub type PipelineFactory = Arc<dyn Fn() -> PipelineBuilder + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    pub work_dir: PathBuf,
+    // Cargo project copied into every job directory, an empty project without it
+    pub template_project: Option<PathBuf>,
+    // Reference server for every job instead of the backend template's own
+    pub code_template_path: Option<PathBuf>,
+    pub max_concurrent: usize,
+    // Finished jobs kept with their events, the oldest are dropped once a new job comes in
+    pub max_finished_jobs: usize,
+    // Commit each code iteration to the job's project, tagged with the job id
+    pub git_history: bool,
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self {
+            work_dir: PathBuf::from(DEFAULT_WORK_DIR),
+            template_project: None,
+            code_template_path: None,
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+            max_finished_jobs: DEFAULT_MAX_FINISHED_JOBS,
+            git_history: true,
+        }
+    }
+}
+
+struct JobQueueInner {
+    config: ServiceConfig,
+    factory: PipelineFactory,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    next_id: AtomicU64,
+    permits: Arc<Semaphore>,
+}
+
+// Project runs in submission order, at most max_concurrent at a time
+#[derive(Clone)]
+pub struct JobQueue {
+    inner: Arc<JobQueueInner>,
+}
+
+impl JobQueue {
+    pub fn new(config: ServiceConfig, factory: PipelineFactory) -> Self {
+        let permits: Arc<Semaphore> = Arc::new(Semaphore::new(config.max_concurrent.max(1)));
+        Self {
+            inner: Arc::new(JobQueueInner {
+                config,
+                factory,
+                jobs: Mutex::new(BTreeMap::new()),
+                next_id: AtomicU64::new(1),
+                permits,
+            }),
+        }
+    }
+
+    pub fn submit(&self, request: &str) -> Result<JobSummary, String> {
+        self.evict_finished();
+        let id: u64 = self.inner.next_id.fetch_add(1, Ordering::SeqCst);
+        let project_dir: PathBuf = self.inner.config.work_dir.join(format!("job-{}", id));
+        prepare_project_dir(&project_dir, self.inner.config.template_project.as_deref())
+            .map_err(|e| format!("Could not prepare {}: {}", project_dir.display(), e))?;
+
+        let summary: JobSummary = JobSummary {
+            id,
+            request: request.to_string(),
+            status: JobStatus::Queued,
+            created_at: now_secs(),
+            started_at: None,
+            finished_at: None,
+            error: None,
+        };
+        let (updates, _) = broadcast::channel::<JobUpdate>(UPDATE_CHANNEL_CAPACITY);
+        self.inner.jobs.lock().unwrap().insert(
+            id,
+            Job {
+                summary: summary.clone(),
+                workspace: Workspace::for_project(
+                    &project_dir,
+                    self.inner.config.code_template_path.as_deref(),
+                ),
+                factsheet: None,
+                events: vec![],
+                updates: Some(updates),
+                abort: None,
+            },
+        );
+
+        // A panicking agent fails its job instead of leaving it running forever
+        let run: JoinHandle<()> = tokio::spawn(self.clone().run_job(id));
+        let abort: AbortHandle = run.abort_handle();
+        let queue: JobQueue = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run.await {
+                if e.is_panic() {
+                    queue.update(id, |job| {
+                        job.finish(JobStatus::Failed, Some("Job panicked".to_string()))
+                    });
+                }
+            }
+        });
+        self.update(id, |job| {
+            if !job.summary.status.is_finished() {
+                job.abort = Some(abort);
+            }
+        });
+
+        Ok(summary)
+    }
+
+    async fn run_job(self, id: u64) {
+        let _permit = self
+            .inner
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Job queue semaphore closed");
+
+        // A job cancelled before its abort handle was attached must not start
+        let (request, workspace) = match self
+            .update(id, |job| {
+                if job.summary.status.is_finished() {
+                    return None;
+                }
+                job.summary.status = JobStatus::Running;
+                job.summary.started_at = Some(now_secs());
+                job.publish(JobUpdate::Status(job.summary.clone()));
+                Some((job.summary.request.clone(), job.workspace.clone()))
+            })
+            .flatten()
+        {
+            Some(job) => job,
+            None => return,
+        };
+
+        let mut pipeline: PipelineBuilder = (self.inner.factory)()
+            .workspace(workspace.clone())
+            .print_to_terminal(false);
+        let recorder: JobQueue = self.clone();
+        pipeline = pipeline.observer(move |event: &AgentEvent| {
+            recorder.update(id, |job| {
+                job.events.push(event.clone());
+                job.publish(JobUpdate::Event(event.clone()));
+            });
+        });
+        match JsonLinesObserver::create(&workspace.web_server_project_path.join("events.jsonl")) {
+            Ok(event_log) => pipeline = pipeline.observer(event_log),
+            Err(e) => PrintCommand::Issue.print_agent_message(
+                &format!("Job {}", id),
+                &format!("Could not open event log: {}", e),
+            ),
+        }
+        if self.inner.config.git_history {
+            let run_id: String = format!("job-{}", id);
+            let project_dir: &Path = &workspace.web_server_project_path;
+            match GitHistory::new(project_dir, &run_id) {
+                Ok(history) => pipeline = pipeline.observer(history),
+                Err(e) => {
+                    self.update(id, |job| job.finish(JobStatus::Failed, Some(e)));
+                    return;
+                }
+            }
+        }
+
+        let project_res: Result<PipelineOutput, PipelineError> =
+            pipeline.build().run(&request).await;
+        self.update(id, |job| {
+            if job.summary.status.is_finished() {
+                return;
+            }
+            match project_res {
+                Ok(output) => {
+                    job.factsheet = Some(output.factsheet);
+                    job.finish(JobStatus::Succeeded, None);
+                }
+                Err(e) => {
+                    job.factsheet = e.factsheet.map(|factsheet| *factsheet);
+                    job.finish(JobStatus::Failed, Some(e.message));
+                }
+            }
+        });
+    }
+
+    // Drop the oldest finished jobs past max_finished_jobs, their project directories stay on disk
+    fn evict_finished(&self) {
+        let mut jobs = self.inner.jobs.lock().unwrap();
+        let finished: Vec<u64> = jobs
+            .values()
+            .filter(|job| job.summary.status.is_finished())
+            .map(|job| job.summary.id)
+            .collect();
+        let excess: usize = finished
+            .len()
+            .saturating_sub(self.inner.config.max_finished_jobs);
+        for id in &finished[..excess] {
+            jobs.remove(id);
+        }
+    }
+
+    fn update<T>(&self, id: u64, change: impl FnOnce(&mut Job) -> T) -> Option<T> {
+        self.inner.jobs.lock().unwrap().get_mut(&id).map(change)
+    }
+
+    pub fn list(&self) -> Vec<JobSummary> {
+        let jobs = self.inner.jobs.lock().unwrap();
+        jobs.values().map(|job| job.summary.clone()).collect()
+    }
+
+    pub fn summary(&self, id: u64) -> Option<JobSummary> {
+        self.update(id, |job| job.summary.clone())
+    }
+
+    // None for an unknown job, Some(None) while no factsheet exists yet
+    pub fn factsheet(&self, id: u64) -> Option<Option<FactSheet>> {
+        self.update(id, |job| job.factsheet.clone())
+    }
+
+    pub fn artifacts(&self, id: u64) -> Option<Vec<Artifact>> {
+        let workspace: Workspace = self.update(id, |job| job.workspace.clone())?;
+        let artifacts: Vec<Artifact> = workspace
+            .artifacts()
+            .into_iter()
+            .filter_map(|path| {
+                let contents: String = fs::read_to_string(&path).ok()?;
+                let relative: &Path = path
+                    .strip_prefix(&workspace.web_server_project_path)
+                    .unwrap_or(&path);
+                Some(Artifact {
+                    path: relative.display().to_string(),
+                    contents,
+                })
+            })
+            .collect();
+        Some(artifacts)
+    }
+
+    // Stops a queued or running job, finished jobs are left as they are
+    pub fn cancel(&self, id: u64) -> Option<JobSummary> {
+        self.update(id, |job| {
+            if !job.summary.status.is_finished() {
+                if let Some(abort) = job.abort.take() {
+                    abort.abort();
+                }
+                job.finish(JobStatus::Cancelled, None);
+            }
+            job.summary.clone()
+        })
+    }
+
+    // Updates so far and the live channel, which is closed once the job has finished
+    pub fn subscribe(&self, id: u64) -> Option<(Vec<JobUpdate>, broadcast::Receiver<JobUpdate>)> {
+        self.update(id, |job| {
+            let mut replay: Vec<JobUpdate> =
+                job.events.iter().cloned().map(JobUpdate::Event).collect();
+            let receiver: broadcast::Receiver<JobUpdate> = match &job.updates {
+                Some(updates) => updates.subscribe(),
+                None => {
+                    replay.push(JobUpdate::Status(job.summary.clone()));
+                    broadcast::channel::<JobUpdate>(1).1
+                }
+            };
+            (replay, receiver)
+        })
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// Every job builds in its own copy of the project so runs never share files
+fn prepare_project_dir(project_dir: &Path, template_project: Option<&Path>) -> io::Result<()> {
+    match template_project {
+        Some(template_project) => copy_project(template_project, project_dir),
+        None => fs::create_dir_all(project_dir.join("src")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmitJob {
+    request: String,
+}
+
+fn not_found(id: u64) -> Response {
+    (StatusCode::NOT_FOUND, format!("No job {}", id)).into_response()
+}
+
+async fn submit_job(State(queue): State<JobQueue>, Json(body): Json<SubmitJob>) -> Response {
+    if body.request.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "request must not be empty").into_response();
+    }
+    match queue.submit(body.request.trim()) {
+        Ok(summary) => (StatusCode::ACCEPTED, Json(summary)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn list_jobs(State(queue): State<JobQueue>) -> Json<Vec<JobSummary>> {
+    Json(queue.list())
+}
+
+async fn job_status(State(queue): State<JobQueue>, UrlPath(id): UrlPath<u64>) -> Response {
+    match queue.summary(id) {
+        Some(summary) => Json(summary).into_response(),
+        None => not_found(id),
+    }
+}
+
+async fn job_factsheet(State(queue): State<JobQueue>, UrlPath(id): UrlPath<u64>) -> Response {
+    match queue.factsheet(id) {
+        Some(Some(factsheet)) => Json(factsheet).into_response(),
+        Some(None) => (
+            StatusCode::NOT_FOUND,
+            format!("Job {} has no factsheet yet", id),
+        )
+            .into_response(),
+        None => not_found(id),
+    }
+}
+
+async fn job_artifacts(State(queue): State<JobQueue>, UrlPath(id): UrlPath<u64>) -> Response {
+    match queue.artifacts(id) {
+        Some(artifacts) => Json(artifacts).into_response(),
+        None => not_found(id),
+    }
+}
+
+async fn cancel_job(State(queue): State<JobQueue>, UrlPath(id): UrlPath<u64>) -> Response {
+    match queue.cancel(id) {
+        Some(summary) => Json(summary).into_response(),
+        None => not_found(id),
+    }
+}
+
+// Server-Sent Events: past agent events first, then live ones until the job finishes
+async fn job_events(
+    State(queue): State<JobQueue>,
+    UrlPath(id): UrlPath<u64>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Response> {
+    let (replay, receiver) = queue.subscribe(id).ok_or(not_found(id))?;
+    let live = BroadcastStream::new(receiver).filter_map(|update| update.ok());
+    let stream = tokio_stream::iter(replay)
+        .chain(live)
+        .map(|update: JobUpdate| Ok(update.to_sse()));
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+pub fn router(queue: JobQueue) -> Router {
+    Router::new()
+        .route("/jobs", post(submit_job).get(list_jobs))
+        .route("/jobs/:id", get(job_status))
+        .route("/jobs/:id/factsheet", get(job_factsheet))
+        .route("/jobs/:id/artifacts", get(job_artifacts))
+        .route("/jobs/:id/cancel", post(cancel_job))
+        .route("/jobs/:id/events", get(job_events))
+        .with_state(queue)
+}
+
+// e.g. `LLM_PROVIDER=mock cargo run -- serve --max-concurrent 4`
+pub async fn serve_command(args: &[String]) -> Result<String, String> {
+    let usage: &str = "Usage: serve [--addr <host:port>] [--max-concurrent <n>] [--max-finished-jobs <n>] [--work-dir <dir>] [--template-project <dir>] [--code-template <file>] [--backend-template <name>] [--backend-storage <json|sqlite>] [--approve-code] [--no-git-history]";
+    let mut addr: String = DEFAULT_ADDR.to_string();
+    let mut config: ServiceConfig = ServiceConfig::default();
+    let mut approve_code: bool = false;
+    let mut backend_template: Option<BackendTemplate> = None;
+    let mut backend_storage: Option<Storage> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().ok_or(usage.to_string());
+        match arg.as_str() {
+            "--addr" => addr = value()?,
+            "--max-concurrent" => {
+                config.max_concurrent = value()?.parse().map_err(|_| usage.to_string())?
+            }
+            "--max-finished-jobs" => {
+                config.max_finished_jobs = value()?.parse().map_err(|_| usage.to_string())?
+            }
+            "--work-dir" => config.work_dir = PathBuf::from(value()?),
+            "--template-project" => config.template_project = Some(PathBuf::from(value()?)),
+            "--code-template" => config.code_template_path = Some(PathBuf::from(value()?)),
+            "--backend-template" => backend_template = parse_backend_template(&value()?)?,
+            "--backend-storage" => backend_storage = parse_backend_storage(&value()?)?,
+            "--approve-code" => approve_code = true,
+            "--no-git-history" => config.git_history = false,
+            _ => return Err(usage.to_string()),
+        }
+    }
+    if let (Some(template), Some(storage)) = (backend_template, backend_storage) {
+        template.with_storage(storage)?;
+    }
+    let addr: SocketAddr = addr
+        .parse()
+        .map_err(|e| format!("Invalid address {}: {}", addr, e))?;
+
+    // Without --approve-code jobs stop before generated code is built and run
+    let provider: Arc<dyn LLMProvider> = provider_from_env()?;
+    let architect_config: ArchitectConfig = architect_config_from_env()?;
+    let llm_config: LLMConfig = LLMConfig::from_env()?;
+    let prompts: PromptLibrary = PromptLibrary::from_env()?;
+    let response_cache: Option<ResponseCache> = response_cache_from_env();
+    // Every job gets its own usage totals and the whole budget
+    let budget: Budget = Budget::from_env();
+    let factory: PipelineFactory = Arc::new(move || {
+        let mut builder: PipelineBuilder = Pipeline::builder()
+            .provider(provider.clone())
+            .approve_code(move || approve_code)
+            .architect_config(architect_config)
+            .llm_config(llm_config.clone())
+            .prompts(prompts.clone())
+            .budget(budget);
+        if let Some(response_cache) = &response_cache {
+            builder = builder.response_cache(response_cache.clone());
+        }
+        if let Some(backend_template) = backend_template {
+            builder = builder.backend_template(backend_template);
+        }
+        if let Some(backend_storage) = backend_storage {
+            builder = builder.backend_storage(backend_storage);
+        }
+        builder
+    });
+
+    let queue: JobQueue = JobQueue::new(config, factory);
+    println!("Serving project requests on http://{}", addr);
+    axum::Server::bind(&addr)
+        .serve(router(queue).into_make_service())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok("Server stopped".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::provider::MockProvider;
+    use crate::models::agent_basic::basic_agent::BasicAgent;
+    use crate::models::agent_basic::basic_traits::BasicTraits;
+    use crate::models::agents::agent_architect::AgentSolutionArchitect;
+    use crate::models::agents::agent_traits::SpecialFunctions;
+    use async_trait::async_trait;
+    use reqwest::Client;
+    use std::env;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    // Keeps its job running until cancelled
+    #[derive(Debug)]
+    struct Sleeper {
+        attributes: BasicAgent,
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for Sleeper {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        async fn execute(
+            &mut self,
+            _factsheet: &mut FactSheet,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        }
+    }
+
+    async fn start_service(name: &str, factory: PipelineFactory) -> String {
+        let work_dir: PathBuf =
+            env::temp_dir().join(format!("service_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&work_dir);
+        let config: ServiceConfig = ServiceConfig {
+            work_dir,
+            template_project: None,
+            code_template_path: None,
+            max_concurrent: 1,
+            max_finished_jobs: 1,
+            git_history: false,
+        };
+
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router(JobQueue::new(config, factory)).into_make_service());
+        tokio::spawn(server);
+        format!("http://{}", addr)
+    }
+
+    async fn wait_for_status(client: &Client, url: &str, status: &str) -> serde_json::Value {
+        for _ in 0..100 {
+            let summary: serde_json::Value =
+                client.get(url).send().await.unwrap().json().await.unwrap();
+            if summary["status"] == status {
+                return summary;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("{} never reached status {}", url, status);
+    }
+
+    #[tokio::test]
+    async fn tests_service_runs_job_with_mock_llm() {
+        let factory: PipelineFactory = Arc::new(|| {
+            Pipeline::builder()
+                .provider(MockProvider)
+                .agent(Box::new(AgentSolutionArchitect::new()))
+        });
+        let base: String = start_service("run", factory).await;
+        let client: Client = Client::new();
+
+        let submitted: serde_json::Value = client
+            .post(format!("{}/jobs", base))
+            .json(&serde_json::json!({ "request": "Build me a todo app" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let id: u64 = submitted["id"].as_u64().unwrap();
+
+        wait_for_status(&client, &format!("{}/jobs/{}", base, id), "succeeded").await;
+
+        let factsheet: FactSheet = client
+            .get(format!("{}/jobs/{}/factsheet", base, id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert!(factsheet.project_scope.unwrap().is_crud_required);
+
+        let artifacts: Vec<serde_json::Value> = client
+            .get(format!("{}/jobs/{}/artifacts", base, id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert!(artifacts.is_empty());
+
+        // Finished jobs replay their events, ending with the final status
+        let events: String = client
+            .get(format!("{}/jobs/{}/events", base, id))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(events.contains("event:agent"));
+        assert!(events.contains("\"event\":\"llm_call_started\""));
+        assert!(events.contains("\"ai_function\":\"print_project_scope\""));
+        assert!(events.contains("event:status\ndata:{\"id\":1,"));
+        assert!(events.contains("\"status\":\"succeeded\""));
+
+        let missing = client
+            .get(format!("{}/jobs/999", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn tests_cancelled_job_does_not_start() {
+        let work_dir: PathBuf =
+            env::temp_dir().join(format!("service_cancelled_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&work_dir);
+        let config: ServiceConfig = ServiceConfig {
+            work_dir: work_dir.clone(),
+            template_project: None,
+            code_template_path: None,
+            max_concurrent: 1,
+            max_finished_jobs: 1,
+            git_history: false,
+        };
+        let factory: PipelineFactory = Arc::new(|| Pipeline::builder().provider(MockProvider));
+        let queue: JobQueue = JobQueue::new(config, factory);
+
+        // The job task picks up a job that was cancelled while it was being spawned
+        let id: u64 = queue.submit("Build me a todo app").unwrap().id;
+        queue.cancel(id);
+        queue.clone().run_job(id).await;
+
+        let summary: JobSummary = queue.summary(id).unwrap();
+        assert_eq!(summary.status, JobStatus::Cancelled);
+        assert_eq!(summary.started_at, None);
+        let _ = fs::remove_dir_all(&work_dir);
+    }
+
+    #[tokio::test]
+    async fn tests_service_queues_and_cancels_jobs() {
+        let factory: PipelineFactory = Arc::new(|| {
+            Pipeline::builder()
+                .provider(MockProvider)
+                .agent(Box::new(Sleeper {
+                    attributes: BasicAgent::new("Sleep".to_string(), "Sleeper".to_string()),
+                }))
+        });
+        let base: String = start_service("cancel", factory).await;
+        let client: Client = Client::new();
+
+        for _ in 0..2 {
+            client
+                .post(format!("{}/jobs", base))
+                .json(&serde_json::json!({ "request": "Build me a todo app" }))
+                .send()
+                .await
+                .unwrap();
+        }
+
+        // Only one job runs at a time
+        wait_for_status(&client, &format!("{}/jobs/1", base), "running").await;
+        let queued: serde_json::Value = client
+            .get(format!("{}/jobs/2", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(queued["status"], "queued");
+
+        let cancelled: serde_json::Value = client
+            .post(format!("{}/jobs/1/cancel", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(cancelled["status"], "cancelled");
+
+        // The freed slot goes to the queued job
+        wait_for_status(&client, &format!("{}/jobs/2", base), "running").await;
+        client
+            .post(format!("{}/jobs/2/cancel", base))
+            .send()
+            .await
+            .unwrap();
+        let listed: Vec<serde_json::Value> = client
+            .get(format!("{}/jobs", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().all(|job| job["status"] == "cancelled"));
+
+        // A new job evicts the finished ones past the cap, oldest first
+        client
+            .post(format!("{}/jobs", base))
+            .json(&serde_json::json!({ "request": "Build me a todo app" }))
+            .send()
+            .await
+            .unwrap();
+        let listed: Vec<serde_json::Value> = client
+            .get(format!("{}/jobs", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let ids: Vec<u64> = listed
+            .iter()
+            .map(|job| job["id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![2, 3]);
+    }
+}
@@ -0,0 +1,253 @@
+///This is the SQLite version of the template web-server the LLM uses as reference
+///Same REST API as code_template.rs, but tasks and users are rows in database.sqlite
+///The tables come from migrations/0001_init.sql, for this template:
+///  CREATE TABLE IF NOT EXISTS tasks (id INTEGER PRIMARY KEY, name TEXT NOT NULL, complete INTEGER NOT NULL);
+///  CREATE TABLE IF NOT EXISTS users (id INTEGER PRIMARY KEY, username TEXT NOT NULL UNIQUE, password TEXT NOT NULL);
+
+///This web server is a REST API that is utilizes JSON, and can do basic CRUD operations
+use actix_cors::Cors;
+use actix_web::{http::header, web, App, HttpResponse, HttpServer, Responder};
+use async_trait::async_trait; //for the LLM to know its ok to use
+use reqwest::Client as HttpClient; //for the LLM to know its ok to use
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Task {
+    id: u64,
+    name: String,
+    complete: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct User {
+    id: u64,
+    username: String,
+    password: String,
+}
+
+struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    //Applies each migrations/*.sql file once, in name order. The file persists between runs
+    fn open(path: &str) -> rusqlite::Result<Self> {
+        let conn: Connection = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (name TEXT PRIMARY KEY);",
+        )?;
+        let applied_count: i64 =
+            conn.query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| row.get(0))?;
+
+        let mut migrations: Vec<PathBuf> = fs::read_dir("migrations")
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().map_or(false, |ext| ext == "sql"))
+                    .filter(|path| path.file_name().map_or(false, |name| name != "seed.sql"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        migrations.sort();
+
+        for migration in migrations {
+            let name: String = migration.file_name().unwrap().to_string_lossy().to_string();
+            let is_applied: bool = conn
+                .query_row(
+                    "SELECT name FROM schema_migrations WHERE name = ?1",
+                    params![name],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?
+                .is_some();
+            if !is_applied {
+                let sql: String = fs::read_to_string(&migration).expect("Failed to read migration");
+                conn.execute_batch(&sql)?;
+                conn.execute("INSERT INTO schema_migrations (name) VALUES (?1)", params![name])?;
+            }
+        }
+
+        //Seed data only goes into a new database, so a restart keeps what clients wrote
+        if applied_count == 0 {
+            if let Ok(seed) = fs::read_to_string("migrations/seed.sql") {
+                conn.execute_batch(&seed)?;
+            }
+        }
+        Ok(Self { conn })
+    }
+
+    //CRUD DATA
+    fn insert_task(&self, task: &Task) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO tasks (id, name, complete) VALUES (?1, ?2, ?3)",
+            params![task.id, task.name, task.complete],
+        )?;
+        Ok(())
+    }
+
+    fn get_task(&self, id: u64) -> rusqlite::Result<Option<Task>> {
+        self.conn
+            .query_row(
+                "SELECT id, name, complete FROM tasks WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok(Task {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        complete: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    fn delete_task(&self, id: u64) -> rusqlite::Result<()> {
+        self.conn
+            .execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn get_all_tasks(&self) -> rusqlite::Result<Vec<Task>> {
+        let mut stmt = self.conn.prepare("SELECT id, name, complete FROM tasks")?;
+        let tasks = stmt.query_map([], |row| {
+            Ok(Task {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                complete: row.get(2)?,
+            })
+        })?;
+        tasks.collect()
+    }
+
+    //USER DATA RELATED FUNCTIONS
+    fn insert_user(&self, user: &User) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO users (id, username, password) VALUES (?1, ?2, ?3)",
+            params![user.id, user.username, user.password],
+        )?;
+        Ok(())
+    }
+
+    fn get_user_by_name(&self, username: &str) -> rusqlite::Result<Option<User>> {
+        self.conn
+            .query_row(
+                "SELECT id, username, password FROM users WHERE username = ?1",
+                params![username],
+                |row| {
+                    Ok(User {
+                        id: row.get(0)?,
+                        username: row.get(1)?,
+                        password: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+    }
+}
+
+//State of app that will be manipulated. Wrapped in mutex for safetey
+struct AppState {
+    db: Mutex<Database>,
+}
+
+async fn create_task(app_state: web::Data<AppState>, task: web::Json<Task>) -> impl Responder {
+    let db = app_state.db.lock().unwrap();
+    match db.insert_task(&task) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+async fn update_task(app_state: web::Data<AppState>, task: web::Json<Task>) -> impl Responder {
+    let db = app_state.db.lock().unwrap();
+    match db.insert_task(&task) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+async fn read_task(app_state: web::Data<AppState>, id: web::Path<u64>) -> impl Responder {
+    let db = app_state.db.lock().unwrap();
+    match db.get_task(id.into_inner()) {
+        Ok(Some(task)) => HttpResponse::Ok().json(task),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+async fn read_all_tasks(app_state: web::Data<AppState>) -> impl Responder {
+    let db = app_state.db.lock().unwrap();
+    match db.get_all_tasks() {
+        Ok(tasks) => HttpResponse::Ok().json(tasks),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+async fn delete_task(app_state: web::Data<AppState>, id: web::Path<u64>) -> impl Responder {
+    let db = app_state.db.lock().unwrap();
+    match db.delete_task(id.into_inner()) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+async fn register(app_state: web::Data<AppState>, user: web::Json<User>) -> impl Responder {
+    let db = app_state.db.lock().unwrap();
+    match db.insert_user(&user) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::BadRequest().body("Username is taken"),
+    }
+}
+
+async fn login(app_state: web::Data<AppState>, user: web::Json<User>) -> impl Responder {
+    let db = app_state.db.lock().unwrap();
+    match db.get_user_by_name(&user.username) {
+        Ok(Some(stored_user)) if stored_user.password == user.password => {
+            HttpResponse::Ok().body("Logged in successfully!")
+        }
+        _ => HttpResponse::BadRequest().body("Invalid username or password"), //invalid
+    }
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let db: Database = Database::open("database.sqlite").expect("Failed to open database");
+    let data = web::Data::new(AppState { db: Mutex::new(db) });
+
+    //The port is read from PORT so the server can be tested next to other services
+    let port: u16 = std::env::var("PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(8080);
+
+    HttpServer::new(move || {
+        App::new()
+            .wrap(
+                Cors::permissive()
+                    .allowed_origin_fn(|origin, _req_head| {
+                        origin.as_bytes().starts_with(b"http://localhost") || origin == "null"
+                    })
+                    .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
+                    .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT])
+                    .allowed_header(header::CONTENT_TYPE)
+                    .supports_credentials()
+                    .max_age(3600),
+            )
+            .app_data(data.clone())
+            .route("/task", web::post().to(create_task))
+            .route("/task", web::get().to(read_all_tasks))
+            .route("/task", web::put().to(update_task))
+            .route("/task/{id}", web::get().to(read_task))
+            .route("/task/{id}", web::delete().to(delete_task))
+            .route("/register", web::post().to(register))
+            .route("/login", web::post().to(login))
+    })
+    .bind(("127.0.0.1", port))?
+    .run()
+    .await
+}
@@ -0,0 +1,127 @@
+///Authentication module written into generated actix-web projects as src/auth.rs
+///Passwords are hashed with argon2, signed in users get a random session token
+///Protected routes are wrapped with actix_web::middleware::from_fn(auth::require_session)
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::ErrorUnauthorized;
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpRequest, HttpResponse, Responder};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand_core::OsRng;
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+pub fn hash_password(password: &str) -> String {
+    let salt: SaltString = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Failed to hash password")
+        .to_string()
+}
+
+pub fn verify_password(password_hash: &str, password: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+//Signed in users by session token. Register with App::app_data(web::Data::new(Sessions::default()))
+//and keep one instance outside HttpServer::new so every worker shares it
+#[derive(Default)]
+pub struct Sessions {
+    tokens: Mutex<HashMap<String, u64>>,
+}
+
+impl Sessions {
+    pub fn start(&self, user_id: u64) -> String {
+        let token: String = uuid::Uuid::new_v4().to_string();
+        self.tokens.lock().unwrap().insert(token.clone(), user_id);
+        token
+    }
+
+    pub fn user_id(&self, token: &str) -> Option<u64> {
+        self.tokens.lock().unwrap().get(token).copied()
+    }
+
+    pub fn end(&self, token: &str) -> bool {
+        self.tokens.lock().unwrap().remove(token).is_some()
+    }
+}
+
+//Body of a successful POST /login
+#[derive(Serialize)]
+pub struct LoginResponse {
+    pub token: String,
+}
+
+//Id of the signed in user, handlers read it with web::ReqData<UserId>
+#[derive(Clone, Copy, Debug)]
+pub struct UserId(pub u64);
+
+//Token of an "Authorization: Bearer <token>" header
+pub fn bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_string())
+}
+
+//Middleware for protected routes, anonymous requests get 401 Unauthorized
+pub async fn require_session(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let user_id: Option<u64> = bearer_token(req.request()).and_then(|token| {
+        req.app_data::<web::Data<Sessions>>()?.user_id(&token)
+    });
+    match user_id {
+        Some(user_id) => {
+            req.extensions_mut().insert(UserId(user_id));
+            next.call(req).await
+        }
+        None => Err(ErrorUnauthorized("Missing or invalid session token")),
+    }
+}
+
+//POST /logout, ends the session of the bearer token
+pub async fn logout(req: HttpRequest, sessions: web::Data<Sessions>) -> impl Responder {
+    match bearer_token(&req) {
+        Some(token) if sessions.end(&token) => HttpResponse::Ok().finish(),
+        _ => HttpResponse::Unauthorized().finish(),
+    }
+}
+
+//How main.rs uses the module:
+//  async fn register(app_state: web::Data<AppState>, user: web::Json<User>) -> impl Responder {
+//      let mut user = user.into_inner();
+//      user.password = auth::hash_password(&user.password);
+//      ...store the user
+//  }
+//  async fn login(app_state: web::Data<AppState>, sessions: web::Data<auth::Sessions>, user: web::Json<User>) -> impl Responder {
+//      match db.get_user_by_name(&user.username) {
+//          Some(stored) if auth::verify_password(&stored.password, &user.password) => {
+//              HttpResponse::Ok().json(auth::LoginResponse { token: sessions.start(stored.id) })
+//          }
+//          _ => HttpResponse::Unauthorized().finish(),
+//      }
+//  }
+//  App::new()
+//      .app_data(sessions.clone())
+//      .route("/register", web::post().to(register))
+//      .route("/login", web::post().to(login))
+//      .service(
+//          web::scope("")
+//              .wrap(actix_web::middleware::from_fn(auth::require_session))
+//              .route("/logout", web::post().to(auth::logout))
+//              .route("/task", web::get().to(read_all_tasks)),
+//      )
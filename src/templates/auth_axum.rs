@@ -0,0 +1,124 @@
+///Authentication module written into generated axum projects as src/auth.rs
+///Passwords are hashed with argon2, signed in users get a random session token
+///Protected routes get .route_layer(middleware::from_fn_with_state(sessions, auth::require_session))
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Extension;
+use rand_core::OsRng;
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub fn hash_password(password: &str) -> String {
+    let salt: SaltString = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Failed to hash password")
+        .to_string()
+}
+
+pub fn verify_password(password_hash: &str, password: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+//Signed in users by session token, shared as Arc<Sessions>
+#[derive(Default)]
+pub struct Sessions {
+    tokens: Mutex<HashMap<String, u64>>,
+}
+
+impl Sessions {
+    pub fn start(&self, user_id: u64) -> String {
+        let token: String = uuid::Uuid::new_v4().to_string();
+        self.tokens.lock().unwrap().insert(token.clone(), user_id);
+        token
+    }
+
+    pub fn user_id(&self, token: &str) -> Option<u64> {
+        self.tokens.lock().unwrap().get(token).copied()
+    }
+
+    pub fn end(&self, token: &str) -> bool {
+        self.tokens.lock().unwrap().remove(token).is_some()
+    }
+}
+
+//Body of a successful POST /login
+#[derive(Serialize)]
+pub struct LoginResponse {
+    pub token: String,
+}
+
+//Id of the signed in user, handlers read it with Extension<UserId>
+#[derive(Clone, Copy, Debug)]
+pub struct UserId(pub u64);
+
+//Token of an "Authorization: Bearer <token>" header
+pub fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_string())
+}
+
+//Middleware for protected routes, anonymous requests get 401 Unauthorized
+pub async fn require_session<B>(
+    State(sessions): State<Arc<Sessions>>,
+    mut req: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let user_id: u64 = bearer_token(req.headers())
+        .and_then(|token| sessions.user_id(&token))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    req.extensions_mut().insert(UserId(user_id));
+    Ok(next.run(req).await)
+}
+
+//POST /logout, ends the session of the bearer token
+pub async fn logout(
+    Extension(sessions): Extension<Arc<Sessions>>,
+    headers: HeaderMap,
+) -> StatusCode {
+    match bearer_token(&headers) {
+        Some(token) if sessions.end(&token) => StatusCode::OK,
+        _ => StatusCode::UNAUTHORIZED,
+    }
+}
+
+//How main.rs uses the module:
+//  async fn register(State(db): State<AppState>, Json(mut user): Json<User>) -> impl IntoResponse {
+//      user.password = auth::hash_password(&user.password);
+//      ...store the user
+//  }
+//  async fn login(State(db): State<AppState>, Extension(sessions): Extension<Arc<auth::Sessions>>, Json(user): Json<User>) -> Response {
+//      match db.lock().unwrap().get_user_by_name(&user.username) {
+//          Some(stored) if auth::verify_password(&stored.password, &user.password) => {
+//              Json(auth::LoginResponse { token: sessions.start(stored.id) }).into_response()
+//          }
+//          _ => StatusCode::UNAUTHORIZED.into_response(),
+//      }
+//  }
+//  let sessions: Arc<auth::Sessions> = Arc::new(auth::Sessions::default());
+//  let protected = Router::new()
+//      .route("/logout", post(auth::logout))
+//      .route("/task", get(read_all_tasks))
+//      .route_layer(middleware::from_fn_with_state(sessions.clone(), auth::require_session));
+//  let app = Router::new()
+//      .route("/register", post(register))
+//      .route("/login", post(login))
+//      .merge(protected)
+//      .layer(Extension(sessions))
+//      .with_state(state);
@@ -0,0 +1,183 @@
+///This is the axum version of the template web-server the LLM uses as reference
+///Same REST API and JSON file database as code_template.rs, written for axum
+
+///This web server is a REST API that is utilizes JSON, and can do basic CRUD operations
+use async_trait::async_trait; //for the LLM to know its ok to use
+use axum::{
+    extract::{Path, State},
+    http::{header, Method, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use reqwest::Client as HttpClient; //for the LLM to know its ok to use
+use serde::{Deserialize, Serialize};
+use tower_http::cors::{Any, CorsLayer};
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::{fs, u64};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Task {
+    id: u64,
+    name: String,
+    complete: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct User {
+    id: u64,
+    username: String,
+    password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Database {
+    tasks: HashMap<u64, Task>,
+    users: HashMap<u64, User>,
+}
+
+impl Database {
+    fn new() -> Self {
+        Self {
+            tasks: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+
+    //CRUD DATA
+    fn insert_task(&mut self, task: Task) {
+        self.tasks.insert(task.id, task);
+    }
+
+    fn get_task(&self, id: &u64) -> Option<&Task> {
+        self.tasks.get(id)
+    }
+
+    fn delete_task(&mut self, id: &u64) {
+        self.tasks.remove(id);
+    }
+
+    fn update_task(&mut self, task: Task) {
+        self.tasks.insert(task.id, task);
+    }
+
+    fn get_all_tasks(&self) -> Vec<&Task> {
+        self.tasks.values().collect()
+    }
+
+    //USER DATA RELATED FUNCTIONS
+    fn insert_user(&mut self, user: User) {
+        self.users.insert(user.id, user);
+    }
+
+    fn get_user_by_name(&self, username: &str) -> Option<&User> {
+        self.users.values().find(|u| u.username == username)
+    }
+
+    //DATABASE SAVING
+    fn save_to_file(&self) -> std::io::Result<()> {
+        let data: String = serde_json::to_string(&self)?;
+        let mut file = fs::File::create("database.json")?;
+        file.write_all(data.as_bytes())?;
+        Ok(())
+    }
+
+    fn load_from_file() -> std::io::Result<Self> {
+        let file_content = fs::read_to_string("database.json")?;
+        let db: Database = serde_json::from_str(&file_content)?;
+        Ok(db)
+    }
+}
+
+//State of app that will be manipulated. Shared between handlers, wrapped in mutex for safetey
+type AppState = Arc<Mutex<Database>>;
+
+async fn create_task(State(db): State<AppState>, Json(task): Json<Task>) -> impl IntoResponse {
+    let mut db = db.lock().unwrap();
+    db.insert_task(task);
+    let _ = db.save_to_file();
+    StatusCode::OK //should return 200 status code
+}
+
+async fn update_task(State(db): State<AppState>, Json(task): Json<Task>) -> impl IntoResponse {
+    let mut db = db.lock().unwrap();
+    db.update_task(task);
+    let _ = db.save_to_file();
+    StatusCode::OK
+}
+
+async fn read_task(State(db): State<AppState>, Path(id): Path<u64>) -> impl IntoResponse {
+    let db = db.lock().unwrap();
+    match db.get_task(&id) {
+        Some(task) => Json(task.clone()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn read_all_tasks(State(db): State<AppState>) -> impl IntoResponse {
+    let db = db.lock().unwrap();
+    let tasks: Vec<Task> = db.get_all_tasks().into_iter().cloned().collect();
+    Json(tasks)
+}
+
+async fn delete_task(State(db): State<AppState>, Path(id): Path<u64>) -> impl IntoResponse {
+    let mut db = db.lock().unwrap();
+    db.delete_task(&id);
+    let _ = db.save_to_file();
+    StatusCode::OK
+}
+
+async fn register(State(db): State<AppState>, Json(user): Json<User>) -> impl IntoResponse {
+    let mut db = db.lock().unwrap();
+    db.insert_user(user);
+    let _ = db.save_to_file();
+    StatusCode::OK
+}
+
+async fn login(State(db): State<AppState>, Json(user): Json<User>) -> impl IntoResponse {
+    let db = db.lock().unwrap();
+    match db.get_user_by_name(&user.username) {
+        Some(stored_user) if stored_user.password == user.password => {
+            (StatusCode::OK, "Logged in successfully!")
+        }
+        _ => (StatusCode::BAD_REQUEST, "Invalid username or password"), //invalid
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let db: Database = match Database::load_from_file() {
+        Ok(db) => db,
+        Err(_) => Database::new(),
+    };
+    let state: AppState = Arc::new(Mutex::new(db));
+
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(vec![Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers(vec![header::AUTHORIZATION, header::ACCEPT, header::CONTENT_TYPE]);
+
+    //Path params are written as :id in axum
+    let app = Router::new()
+        .route("/task", get(read_all_tasks).post(create_task).put(update_task))
+        .route("/task/:id", get(read_task).delete(delete_task))
+        .route("/register", axum::routing::post(register))
+        .route("/login", axum::routing::post(login))
+        .layer(cors)
+        .with_state(state);
+
+    //The port is read from PORT so the server can be tested next to other services
+    let port: u16 = std::env::var("PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(8080);
+    let addr: SocketAddr = SocketAddr::from(([127, 0, 0, 1], port));
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await
+        .unwrap();
+}